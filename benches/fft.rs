@@ -13,4 +13,17 @@ pub fn fft_in_place(c: &mut Criterion) {
     });
 }
 
-criterion_group!(group, fft_in_place);
\ No newline at end of file
+pub fn fft_in_place_parallel(c: &mut Criterion) {
+
+    let size: usize = 1 << 16;
+    let mut values = field::rand_vector(size);
+    let r = field::get_root_of_unity(size);
+    let twiddles = fft::get_twiddles(r, size);
+    let threads = 4;
+
+    c.bench_function("FFT (in-place, parallel)", |bench| {
+        bench.iter(|| fft::fft_in_place(black_box(&mut values), black_box(&twiddles), black_box(1), black_box(1), black_box(0), black_box(threads)))
+    });
+}
+
+criterion_group!(group, fft_in_place, fft_in_place_parallel);
\ No newline at end of file
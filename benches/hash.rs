@@ -1,5 +1,5 @@
 use criterion::{ black_box, criterion_group, Criterion };
-use distaff::crypto::hash;
+use distaff::crypto::{ hash, HashFunction, MerkleTree };
 
 pub fn poseidon(c: &mut Criterion) {
     let v: [u8; 64] = [
@@ -66,4 +66,27 @@ pub fn sha3(c: &mut Criterion) {
     });
 }
 
-criterion_group!(group, poseidon, rescue, gmimc, blake3, sha3);
+pub fn merkle_tree(c: &mut Criterion) {
+    let leaves = build_leaves(1 << 14);
+    c.bench_function("Merkle tree (Blake3)", |bench| {
+        bench.iter(|| MerkleTree::new(black_box(leaves.clone()), black_box(HashFunction::Blake3)))
+    });
+}
+
+pub fn merkle_tree_parallel(c: &mut Criterion) {
+    let leaves = build_leaves(1 << 14);
+    let threads = 4;
+    c.bench_function("Merkle tree (Blake3, parallel)", |bench| {
+        bench.iter(|| MerkleTree::new_parallel(black_box(leaves.clone()), black_box(HashFunction::Blake3), black_box(threads)))
+    });
+}
+
+fn build_leaves(n: usize) -> Vec<[u8; 32]> {
+    let mut leaves = vec![[0u8; 32]; n];
+    for (i, leaf) in leaves.iter_mut().enumerate() {
+        hash::blake3(&(i as u128).to_le_bytes(), leaf);
+    }
+    return leaves;
+}
+
+criterion_group!(group, poseidon, rescue, gmimc, blake3, sha3, merkle_tree, merkle_tree_parallel);
@@ -0,0 +1,33 @@
+use criterion::{ criterion_group, BatchSize, BenchmarkId, Criterion };
+use distaff::{ assembly, crypto::HashFunction, ProgramInputs, OutputSpec, ProofOptions, ProverConfig };
+
+pub fn prove(c: &mut Criterion) {
+
+    // computes 1000 steps of the Fibonacci sequence
+    let program = assembly::compile("
+    begin
+        repeat.1000
+            swap dup.2 drop add
+        end
+    end").unwrap();
+    let inputs = ProgramInputs::from_public(&[1, 0]);
+    let options = ProofOptions::new(32, 32, 0, HashFunction::Blake3);
+
+    let mut group = c.benchmark_group("Prove");
+    group.sample_size(10);
+    let max_threads = ProverConfig::default().num_threads();
+    let mut num_threads = 1;
+    while num_threads <= max_threads {
+        let config = ProverConfig::new(num_threads);
+        group.bench_with_input(BenchmarkId::new("threads", num_threads), &config, |bench, config| {
+            bench.iter_batched(
+                || distaff::generate_trace(&program, &inputs, &OutputSpec::Top(1)).unwrap(),
+                |trace| distaff::prove_with_config(trace, &options, config).unwrap(),
+                BatchSize::LargeInput)
+        });
+        num_threads *= 2;
+    }
+    group.finish();
+}
+
+criterion_group!(group, prove);
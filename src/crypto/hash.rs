@@ -9,6 +9,12 @@ use sha3::Digest;
 const ALPHA: u128 = 3;
 const INV_ALPHA: u128 = 226854911280625642308916371969163307691;
 
+// Sponge construction over algebraic permutations: 4 state elements are used as the rate and 2 as
+// the capacity; every rate element absorbs 15 bytes so that it is always a valid field element
+const SPONGE_RATE: usize = 4;
+const BYTES_PER_ELEMENT: usize = 15;
+const SPONGE_BLOCK_SIZE: usize = SPONGE_RATE * BYTES_PER_ELEMENT;
+
 // MDS matrix used by Poseidon and Rescue
 const MDS: [u128; 36] = [
      34702391375697798808541201166389247321, 292720401120629668097050277338444166479, 252221686506898646925660607780980529565,   1545301432720594930091500405440765270, 249229091188143033873076468277345141138, 220001593723324427188563221285612032538,
@@ -124,26 +130,30 @@ pub fn poseidon(values: &[u8], result: &mut [u8]) {
     let mut state = [0u128; 6];
     let state_bytes: &mut [u8; 64] = unsafe { &mut *(&state as *const _ as *mut [u8; 64]) };
     state_bytes[..values.len()].copy_from_slice(values);
+    poseidon_permutation(&mut state);
+
+    // return the result
+    result.copy_from_slice(as_bytes(&state[..2]));
+}
 
+/// Poseidon permutation
+pub fn poseidon_permutation(state: &mut [u128; 6]) {
     // execute round function 48 times
     for i in 0..91 {
 
-        add_constants(&mut state, i * 6);
+        add_constants(state, i * 6);
 
         if i < 4 || i >= 87 {
             // full round
-            apply_sbox(&mut state);
+            apply_sbox(state);
         }
         else {
             // partial round
             state[5] = field::exp(state[5], ALPHA);
         }
 
-        apply_mds(&mut state);
+        apply_mds(state);
     }
-
-    // return the result
-    result.copy_from_slice(as_bytes(&state[..2]));
 }
 
 // ------------------------------------------------------------------------------------------------
@@ -156,24 +166,28 @@ pub fn rescue(values: &[u8], result: &mut [u8]) {
     let mut state = [0u128; 6];
     let state_bytes: &mut [u8; 64] = unsafe { &mut *(&state as *const _ as *mut [u8; 64]) };
     state_bytes[..values.len()].copy_from_slice(values);
+    rescue_permutation(&mut state);
 
+    // return the result
+    result.copy_from_slice(as_bytes(&state[..2]));
+}
+
+/// Rescue permutation
+pub fn rescue_permutation(state: &mut [u128; 6]) {
     // apply round function 10 times
-    add_constants(&mut state, 0);
+    add_constants(state, 0);
     for i in 0..10 {
 
         // step 1
-        apply_inv_sbox(&mut state);
-        apply_mds(&mut state);
-        add_constants(&mut state, (i * 2 + 1) * 6);
+        apply_inv_sbox(state);
+        apply_mds(state);
+        add_constants(state, (i * 2 + 1) * 6);
 
         // step 2
-        apply_sbox(&mut state);
-        apply_mds(&mut state);
-        add_constants(&mut state, (i * 2 + 2) * 6);
+        apply_sbox(state);
+        apply_mds(state);
+        add_constants(state, (i * 2 + 2) * 6);
     }
-
-    // return the result
-    result.copy_from_slice(as_bytes(&state[..2]));
 }
 
 // ------------------------------------------------------------------------------------------------
@@ -186,7 +200,14 @@ pub fn gmimc(values: &[u8], result: &mut [u8]) {
     let mut state = [0u128; 6];
     let state_bytes: &mut [u8; 64] = unsafe { &mut *(&state as *const _ as *mut [u8; 64]) };
     state_bytes[..values.len()].copy_from_slice(values);
+    gmimc_permutation(&mut state);
+
+    // return the result
+    result.copy_from_slice(as_bytes(&state[..2]));
+}
 
+/// GMiMC_erf permutation
+pub fn gmimc_permutation(state: &mut [u128; 6]) {
     for i in 0..166 {
         let s0 = state[0];
         let mask = field::exp(field::add(s0, ARK[i]), ALPHA);
@@ -195,6 +216,31 @@ pub fn gmimc(values: &[u8], result: &mut [u8]) {
         }
         state[5] = s0;
     }
+}
+
+// ------------------------------------------------------------------------------------------------
+/// Hashes `values` of any length with the sponge construction over the specified algebraic
+/// `permutation`. The length of the input (in bytes) is written into the capacity before any
+/// values are absorbed, and the input is padded with a single 1 byte followed by 0 bytes to a
+/// multiple of the block size; thus, inputs of different lengths never share a padded encoding,
+/// and the result is different from the result of hashing a 64-byte input directly.
+pub fn sponge(values: &[u8], result: &mut [u8], permutation: fn(&mut [u128; 6])) {
+    debug_assert!(result.len() == 32, "expected result to be exactly 32 bytes but received {}", result.len());
+
+    let mut padded = values.to_vec();
+    padded.push(1);
+    padded.resize((padded.len() + SPONGE_BLOCK_SIZE - 1) / SPONGE_BLOCK_SIZE * SPONGE_BLOCK_SIZE, 0);
+
+    let mut state = [0u128; 6];
+    state[SPONGE_RATE] = values.len() as u128;
+    for block in padded.chunks(SPONGE_BLOCK_SIZE) {
+        for (i, bytes) in block.chunks(BYTES_PER_ELEMENT).enumerate() {
+            let mut element = [0u8; 16];
+            element[..BYTES_PER_ELEMENT].copy_from_slice(bytes);
+            state[i] = field::add(state[i], u128::from_le_bytes(element));
+        }
+        permutation(&mut state);
+    }
 
     // return the result
     result.copy_from_slice(as_bytes(&state[..2]));
@@ -259,6 +305,7 @@ fn apply_mds(state: &mut[u128; 6]) {
 mod tests {
 
     use crate::utils::{ as_bytes };
+    use super::super::HashFunction;
 
     #[test]
     fn poseidon() {
@@ -295,4 +342,40 @@ mod tests {
              71,  74, 255, 77,  41, 125,  25, 152, 162, 106,  65, 108,  84, 216, 37, 37],
             result);
     }
+
+    #[test]
+    fn sponge() {
+        for &hash_fn in HashFunction::all().iter().filter(|hash_fn| hash_fn.is_algebraic()) {
+            let values = (0..200).map(|i| i as u8).collect::<Vec<u8>>();
+            let digest = |values: &[u8]| {
+                let mut result = [0u8; 32];
+                hash_fn.hash(values, &mut result);
+                result
+            };
+
+            // inputs which differ only in trailing zeros or in length have different digests
+            let mut padded = values.clone();
+            padded.push(0);
+            assert_ne!(digest(&values), digest(&padded));
+            assert_ne!(digest(&values[..65]), digest(&values[..66]));
+            assert_ne!(digest(&padded[..120]), digest(&padded[..121]));
+
+            // the padding byte cannot be supplied as a part of the input
+            let mut with_padding = values[..100].to_vec();
+            with_padding.push(1);
+            assert_ne!(digest(&values[..100]), digest(&with_padding));
+
+            // a long input does not hash to the same value as its prefix chained with the rest
+            let mut chained = digest(&values[..64]).to_vec();
+            chained.extend_from_slice(&values[64..96]);
+            assert_ne!(digest(&values[..96]), digest(&chained));
+
+            // every byte of the input affects the digest
+            for i in [0, 64, 127, 199].iter() {
+                let mut changed = values.clone();
+                changed[*i] ^= 1;
+                assert_ne!(digest(&values), digest(&changed));
+            }
+        }
+    }
 }
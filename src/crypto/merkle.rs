@@ -1,7 +1,9 @@
 use std::slice;
 use std::collections::{ HashMap, BTreeSet };
+use crossbeam_utils::thread;
 use serde::{ Serialize, Deserialize };
 use crate::crypto::{ HashFunction };
+use crate::math::parallel;
 
 // TYPES AND INTERFACES
 // ================================================================================================
@@ -22,11 +24,25 @@ pub struct BatchMerkleProof {
 impl MerkleTree {
 
     /// Creates a new merkle tree from the provide leaves and using the provided hash function.
-    pub fn new(leaves: Vec<[u8; 32]>, hash: HashFunction) -> MerkleTree {
+    pub fn new(leaves: Vec<[u8; 32]>, hash_fn: HashFunction) -> MerkleTree {
         assert!(leaves.len().is_power_of_two(), "number of leaves must be a power of 2");
         assert!(leaves.len() >= 2, "a tree must contain at least 2 leaves");
 
-        let nodes = build_merkle_nodes(&leaves, hash);
+        let nodes = build_merkle_nodes(&leaves, hash_fn);
+        return MerkleTree {
+            values  : leaves,
+            nodes   : nodes
+        };
+    }
+
+    /// Creates a new merkle tree from the provided leaves in the same way as `new()`, but
+    /// distributes hashing across up to `num_threads` threads; the resulting tree is the same
+    /// regardless of the number of threads.
+    pub fn new_parallel(leaves: Vec<[u8; 32]>, hash_fn: HashFunction, num_threads: usize) -> MerkleTree {
+        assert!(leaves.len().is_power_of_two(), "number of leaves must be a power of 2");
+        assert!(leaves.len() >= 2, "a tree must contain at least 2 leaves");
+
+        let nodes = build_merkle_nodes_parallel(&leaves, hash_fn, num_threads);
         return MerkleTree {
             values  : leaves,
             nodes   : nodes
@@ -124,14 +140,14 @@ impl MerkleTree {
     }
 
     /// Checks whether the path for the specified index is valid.
-    pub fn verify(root: &[u8; 32], index: usize, proof: &[[u8; 32]], hash: HashFunction) -> bool {
+    pub fn verify(root: &[u8; 32], index: usize, proof: &[[u8; 32]], hash_fn: HashFunction) -> bool {
         let mut buf = [0u8; 64];
         let mut v = [0u8; 32];
 
         let r = index & 1;
         &buf[0..32].copy_from_slice(&proof[r]);
         &buf[32..64].copy_from_slice(&proof[1 - r]);
-        hash(&buf, &mut v);
+        hash_fn.hash(&buf, &mut v);
 
         let mut index = (index + usize::pow(2, (proof.len() - 1) as u32)) >> 1;
         for i in 2..proof.len() {
@@ -143,7 +159,7 @@ impl MerkleTree {
                 &buf[0..32].copy_from_slice(&proof[i]);
                 &buf[32..64].copy_from_slice(&v);
             }
-            hash(&buf, &mut v);
+            hash_fn.hash(&buf, &mut v);
             index = index >> 1;
         }
 
@@ -151,7 +167,7 @@ impl MerkleTree {
     }
 
     /// Checks whether the batch proof contains merkle paths for the of the specified indexes.
-    pub fn verify_batch(root: &[u8; 32], indexes: &[usize], proof: &BatchMerkleProof, hash: HashFunction) -> bool {
+    pub fn verify_batch(root: &[u8; 32], indexes: &[usize], proof: &BatchMerkleProof, hash_fn: HashFunction) -> bool {
         let mut buf = [0u8; 64];
         let mut v: HashMap<usize, [u8; 32]> = HashMap::new();
 
@@ -199,7 +215,7 @@ impl MerkleTree {
 
             // hash sibling nodes into their parent
             let mut parent = [0u8; 32];
-            hash(&buf, &mut parent);
+            hash_fn.hash(&buf, &mut parent);
 
             let parent_index = offset + index >> 1;
             v.insert(parent_index, parent);
@@ -248,7 +264,7 @@ impl MerkleTree {
                     &buf[32..64].copy_from_slice(sibling);
                 }
                 let mut parent = [0u8; 32];
-                hash(&buf, &mut parent);
+                hash_fn.hash(&buf, &mut parent);
 
                 // add the parent node to the next set of nodes
                 let parent_index = node_index >> 1;
@@ -266,7 +282,7 @@ impl MerkleTree {
 // HELPER FUNCTIONS
 // ================================================================================================
 
-pub fn build_merkle_nodes(leaves: &[[u8; 32]], hash: HashFunction) -> Vec<[u8; 32]> {
+pub fn build_merkle_nodes(leaves: &[[u8; 32]], hash_fn: HashFunction) -> Vec<[u8; 32]> {
     let n = leaves.len() / 2;
 
     // create un-initialized array to hold all intermediate nodes
@@ -279,7 +295,7 @@ pub fn build_merkle_nodes(leaves: &[[u8; 32]], hash: HashFunction) -> Vec<[u8; 3
 
     // build first row of internal nodes (parents of leaves)
     for (i, j) in (0..n).zip(n..nodes.len()) {
-        hash(&two_leaves[i], &mut nodes[j]);
+        hash_fn.hash(&two_leaves[i], &mut nodes[j]);
     }
 
     // re-interpret nodes as an array of two nodes fused together
@@ -287,7 +303,56 @@ pub fn build_merkle_nodes(leaves: &[[u8; 32]], hash: HashFunction) -> Vec<[u8; 3
 
     // calculate all other tree nodes
     for i in (1..n).rev() {
-        hash(&two_nodes[i], &mut nodes[i]);
+        hash_fn.hash(&two_nodes[i], &mut nodes[i]);
+    }
+
+    return nodes;
+}
+
+/// Builds internal nodes of a Merkle tree in the same way as `build_merkle_nodes()`, but splits
+/// the tree into subtrees, one per thread, which are built in parallel. Only the nodes above
+/// subtree roots are hashed in the current thread.
+pub fn build_merkle_nodes_parallel(leaves: &[[u8; 32]], hash_fn: HashFunction, num_threads: usize) -> Vec<[u8; 32]> {
+    let num_subtrees = parallel::get_num_batches(leaves.len() / 2, num_threads);
+    if num_subtrees == 1 {
+        return build_merkle_nodes(leaves, hash_fn);
+    }
+
+    let subtree_size = leaves.len() / num_subtrees;
+    let subtree_nodes = thread::scope(|s| {
+        let handles = leaves.chunks(subtree_size)
+            .map(|leaves| s.spawn(move |_| build_merkle_nodes(leaves, hash_fn)))
+            .collect::<Vec<_>>();
+        return handles.into_iter().map(|handle| handle.join().unwrap()).collect::<Vec<_>>();
+    }).unwrap();
+
+    return merge_subtree_nodes(&subtree_nodes, hash_fn);
+}
+
+/// Copies internal nodes of each subtree into their places in the combined tree, and computes
+/// the nodes above subtree roots. In the combined tree, each level of a subtree makes up a
+/// contiguous segment of the corresponding level of the tree.
+fn merge_subtree_nodes(subtrees: &[Vec<[u8; 32]>], hash_fn: HashFunction) -> Vec<[u8; 32]> {
+    let num_subtrees = subtrees.len();
+    let subtree_size = subtrees[0].len();
+    let mut nodes = vec![[0u8; 32]; num_subtrees * subtree_size];
+
+    for (i, subtree) in subtrees.iter().enumerate() {
+        let mut level_size = 1;
+        while level_size < subtree_size {
+            let source = &subtree[level_size..(level_size * 2)];
+            let offset = level_size * num_subtrees + i * level_size;
+            nodes[offset..(offset + level_size)].copy_from_slice(source);
+            level_size = level_size * 2;
+        }
+    }
+
+    // calculate nodes above subtree roots
+    let mut buf = [0u8; 64];
+    for i in (1..num_subtrees).rev() {
+        buf[0..32].copy_from_slice(&nodes[i * 2]);
+        buf[32..64].copy_from_slice(&nodes[i * 2 + 1]);
+        hash_fn.hash(&buf, &mut nodes[i]);
     }
 
     return nodes;
@@ -316,7 +381,7 @@ fn normalize_indexes(indexes: &[usize]) -> Vec<usize> {
 #[cfg(test)]
 mod tests {
 
-    use crate::crypto::hash;
+    use crate::crypto::{ hash, HashFunction };
 
     static LEAVES4: [[u8; 32]; 4] = [
         [166, 168,  47, 140, 153, 86, 156,  86, 226, 229, 149,  76,  70, 132, 209, 109, 166, 193, 113, 197,  42, 116, 170, 144,  74, 104,  29, 110, 220, 49, 224, 123],
@@ -339,7 +404,7 @@ mod tests {
     #[test]
     fn new_tree() {
         let leaves = LEAVES4.to_vec();
-        let tree = super::MerkleTree::new(leaves, hash::poseidon);
+        let tree = super::MerkleTree::new(leaves, HashFunction::Poseidon);
         let root = hash_2x1(
             &hash_2x1(&LEAVES4[0], &LEAVES4[1]),
             &hash_2x1(&LEAVES4[2], &LEAVES4[3])
@@ -347,7 +412,7 @@ mod tests {
         assert_eq!(&root, tree.root());
 
         let leaves = LEAVES8.to_vec();
-        let tree = super::MerkleTree::new(leaves, hash::poseidon);
+        let tree = super::MerkleTree::new(leaves, HashFunction::Poseidon);
         let root = hash_2x1(
             &hash_2x1(
                 &hash_2x1(&LEAVES8[0], &LEAVES8[1]),
@@ -365,7 +430,7 @@ mod tests {
     fn prove() {
         // depth 4
         let leaves = LEAVES4.to_vec();
-        let tree = super::MerkleTree::new(leaves, hash::poseidon);
+        let tree = super::MerkleTree::new(leaves, HashFunction::Poseidon);
 
         let proof = vec![
             LEAVES4[1],
@@ -383,7 +448,7 @@ mod tests {
 
         // depth 5
         let leaves = LEAVES8.to_vec();
-        let tree = super::MerkleTree::new(leaves, hash::poseidon);
+        let tree = super::MerkleTree::new(leaves, HashFunction::Poseidon);
 
         let proof = vec![
             LEAVES8[1],
@@ -406,27 +471,27 @@ mod tests {
     fn verify() {
         // depth 4
         let leaves = LEAVES4.to_vec();
-        let tree = super::MerkleTree::new(leaves, hash::poseidon);
+        let tree = super::MerkleTree::new(leaves, HashFunction::Poseidon);
         let proof = tree.prove(1);
-        assert_eq!(true, super::MerkleTree::verify(tree.root(), 1, &proof, hash::poseidon));
+        assert_eq!(true, super::MerkleTree::verify(tree.root(), 1, &proof, HashFunction::Poseidon));
 
         let proof = tree.prove(2);
-        assert_eq!(true, super::MerkleTree::verify(tree.root(), 2, &proof, hash::poseidon));
+        assert_eq!(true, super::MerkleTree::verify(tree.root(), 2, &proof, HashFunction::Poseidon));
 
         // depth 5
         let leaves = LEAVES8.to_vec();
-        let tree = super::MerkleTree::new(leaves, hash::poseidon);
+        let tree = super::MerkleTree::new(leaves, HashFunction::Poseidon);
         let proof = tree.prove(1);
-        assert_eq!(true, super::MerkleTree::verify(tree.root(), 1, &proof, hash::poseidon));
+        assert_eq!(true, super::MerkleTree::verify(tree.root(), 1, &proof, HashFunction::Poseidon));
 
         let proof = tree.prove(6);
-        assert_eq!(true, super::MerkleTree::verify(tree.root(), 6, &proof, hash::poseidon));
+        assert_eq!(true, super::MerkleTree::verify(tree.root(), 6, &proof, HashFunction::Poseidon));
     }
 
     #[test]
     fn prove_batch() {
         let leaves = LEAVES8.to_vec();
-        let tree = super::MerkleTree::new(leaves, hash::poseidon);
+        let tree = super::MerkleTree::new(leaves, HashFunction::Poseidon);
         
         // 1 index
         let proof = tree.prove_batch(&[1]);
@@ -495,30 +560,49 @@ mod tests {
     #[test]
     fn verify_batch() {
         let leaves = LEAVES8.to_vec();
-        let tree = super::MerkleTree::new(leaves, hash::poseidon);
+        let tree = super::MerkleTree::new(leaves, HashFunction::Poseidon);
 
         let proof = tree.prove_batch(&[1]);
-        assert_eq!(true, super::MerkleTree::verify_batch(tree.root(), &[1], &proof, hash::poseidon));
-        assert_eq!(false, super::MerkleTree::verify_batch(tree.root(), &[2], &proof, hash::poseidon));
+        assert_eq!(true, super::MerkleTree::verify_batch(tree.root(), &[1], &proof, HashFunction::Poseidon));
+        assert_eq!(false, super::MerkleTree::verify_batch(tree.root(), &[2], &proof, HashFunction::Poseidon));
 
         let proof = tree.prove_batch(&[1, 2]);
-        assert_eq!(true, super::MerkleTree::verify_batch(tree.root(), &[1, 2], &proof, hash::poseidon));
-        assert_eq!(false, super::MerkleTree::verify_batch(tree.root(), &[1], &proof, hash::poseidon));
-        assert_eq!(false, super::MerkleTree::verify_batch(tree.root(), &[1, 3], &proof, hash::poseidon));
-        assert_eq!(false, super::MerkleTree::verify_batch(tree.root(), &[1, 2, 3], &proof, hash::poseidon));
+        assert_eq!(true, super::MerkleTree::verify_batch(tree.root(), &[1, 2], &proof, HashFunction::Poseidon));
+        assert_eq!(false, super::MerkleTree::verify_batch(tree.root(), &[1], &proof, HashFunction::Poseidon));
+        assert_eq!(false, super::MerkleTree::verify_batch(tree.root(), &[1, 3], &proof, HashFunction::Poseidon));
+        assert_eq!(false, super::MerkleTree::verify_batch(tree.root(), &[1, 2, 3], &proof, HashFunction::Poseidon));
 
         let proof = tree.prove_batch(&[1, 6]);
-        assert_eq!(true, super::MerkleTree::verify_batch(tree.root(), &[1, 6], &proof, hash::poseidon));
+        assert_eq!(true, super::MerkleTree::verify_batch(tree.root(), &[1, 6], &proof, HashFunction::Poseidon));
 
         let proof = tree.prove_batch(&[1, 3, 6]);
-        assert_eq!(true, super::MerkleTree::verify_batch(tree.root(), &[1, 3, 6], &proof, hash::poseidon));
+        assert_eq!(true, super::MerkleTree::verify_batch(tree.root(), &[1, 3, 6], &proof, HashFunction::Poseidon));
 
         let proof = tree.prove_batch(&[0, 1, 2, 3, 4, 5, 6, 7]);
-        assert_eq!(true, super::MerkleTree::verify_batch(tree.root(), &[0, 1, 2, 3, 4, 5, 6, 7], &proof, hash::poseidon));
+        assert_eq!(true, super::MerkleTree::verify_batch(tree.root(), &[0, 1, 2, 3, 4, 5, 6, 7], &proof, HashFunction::Poseidon));
+    }
+
+    #[test]
+    fn new_parallel() {
+        let leaves = build_test_leaves(1024);
+        let expected = super::MerkleTree::new(leaves.clone(), HashFunction::Blake3);
+        for &num_threads in [1, 2, 3, 8, 1024].iter() {
+            let tree = super::MerkleTree::new_parallel(leaves.clone(), HashFunction::Blake3, num_threads);
+            assert_eq!(expected.nodes, tree.nodes);
+            assert_eq!(expected.values, tree.values);
+        }
     }
 
     // HELPER FUNCTIONS
     // --------------------------------------------------------------------------------------------
+    fn build_test_leaves(n: usize) -> Vec<[u8; 32]> {
+        let mut leaves = vec![[0u8; 32]; n];
+        for (i, leaf) in leaves.iter_mut().enumerate() {
+            hash::blake3(&(i as u128).to_le_bytes(), leaf);
+        }
+        return leaves;
+    }
+
     fn hash_2x1(v1: &[u8; 32], v2: &[u8; 32]) -> [u8; 32] {
         let mut buf = [0u8; 64];
         buf[..32].copy_from_slice(v1);
@@ -1,6 +1,102 @@
+use std::convert::TryFrom;
+use serde::{ Serialize, Deserialize };
+
 pub mod hash;
 
 mod merkle;
-pub use merkle::{ MerkleTree, BatchMerkleProof, build_merkle_nodes };
+pub use merkle::{ MerkleTree, BatchMerkleProof, build_merkle_nodes, build_merkle_nodes_parallel };
+
+// HASH FUNCTION
+// ================================================================================================
+
+/// Hash functions which can be used to build commitments in STARK proofs. A hash function is
+/// serialized as a single byte equal to its discriminant.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "u8", try_from = "u8")]
+#[repr(u8)]
+pub enum HashFunction {
+    Blake3      = 0,
+    Sha3        = 1,
+    Rescue      = 2,
+    Poseidon    = 3,
+    Gmimc       = 4,
+}
+
+impl HashFunction {
+
+    /// Returns all supported hash functions.
+    pub fn all() -> [HashFunction; 5] {
+        return [
+            HashFunction::Blake3,
+            HashFunction::Sha3,
+            HashFunction::Rescue,
+            HashFunction::Poseidon,
+            HashFunction::Gmimc,
+        ];
+    }
+
+    /// Returns the name of the hash function as used in human-readable encodings.
+    pub fn name(&self) -> &'static str {
+        return match self {
+            HashFunction::Blake3    => "blake3",
+            HashFunction::Sha3      => "sha3",
+            HashFunction::Rescue    => "rescue",
+            HashFunction::Poseidon  => "poseidon",
+            HashFunction::Gmimc     => "gmimc",
+        };
+    }
+
+    /// Returns the hash function with the specified name, if there is one.
+    pub fn from_name(name: &str) -> Option<HashFunction> {
+        return HashFunction::all().iter().find(|hash_fn| hash_fn.name() == name).copied();
+    }
+
+    /// Hashes `values` and writes the resulting 32-byte digest into `result`.
+    ///
+    /// Algebraic hash functions (Rescue, Poseidon, GMiMC) hash inputs of up to 64 bytes with
+    /// a single application of their permutation; longer inputs are absorbed into a sponge
+    /// which encodes the length of the input and pads it (see `hash::sponge()`).
+    pub fn hash(&self, values: &[u8], result: &mut [u8]) {
+        match self {
+            HashFunction::Rescue if values.len() > 64   => hash::sponge(values, result, hash::rescue_permutation),
+            HashFunction::Poseidon if values.len() > 64 => hash::sponge(values, result, hash::poseidon_permutation),
+            HashFunction::Gmimc if values.len() > 64    => hash::sponge(values, result, hash::gmimc_permutation),
+            _ => self.hash_chunk(values, result),
+        }
+    }
+
+    /// Returns true if the hash function is defined over the base field rather than over bytes.
+    pub fn is_algebraic(&self) -> bool {
+        return match self {
+            HashFunction::Blake3 | HashFunction::Sha3 => false,
+            _ => true,
+        };
+    }
+
+    fn hash_chunk(&self, values: &[u8], result: &mut [u8]) {
+        match self {
+            HashFunction::Blake3    => hash::blake3(values, result),
+            HashFunction::Sha3      => hash::sha3(values, result),
+            HashFunction::Rescue    => hash::rescue(values, result),
+            HashFunction::Poseidon  => hash::poseidon(values, result),
+            HashFunction::Gmimc     => hash::gmimc(values, result),
+        }
+    }
+}
+
+impl From<HashFunction> for u8 {
+    fn from(hash_fn: HashFunction) -> u8 {
+        return hash_fn as u8;
+    }
+}
+
+impl TryFrom<u8> for HashFunction {
+    type Error = String;
 
-pub type HashFunction = fn(&[u8], &mut [u8]);
\ No newline at end of file
+    fn try_from(value: u8) -> Result<HashFunction, String> {
+        return HashFunction::all().iter()
+            .find(|&&hash_fn| hash_fn as u8 == value)
+            .copied()
+            .ok_or(format!("hash function {}", value));
+    }
+}
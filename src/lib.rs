@@ -12,13 +12,16 @@ pub mod math;
 pub mod utils;
 
 mod stark;
-pub use stark::{ StarkProof, ProofOptions };
+pub use stark::{ StarkProof, ProofStats, ProofOptions, ProverConfig, VerificationError, DeserializationError, PolicyError, VerifierPolicy };
 
 mod processor;
-pub use processor::{ OpCode, OpHint };
+pub use processor::{ OpCode, OpHint, ExecutionError, ExecutionResult, ExecutionTrace };
 
 mod programs;
-pub use programs::{ Program, ProgramInputs, assembly, blocks };
+pub use programs::{ Program, ProgramInputs, OutputSpec, assembly, blocks };
+
+mod receipt;
+pub use receipt::{ ExecutionReceipt, RECEIPT_FORMAT_VERSION };
 
 // EXECUTOR
 // ================================================================================================
@@ -27,51 +30,361 @@ pub use programs::{ Program, ProgramInputs, assembly, blocks };
 /// 
 /// * `inputs` specifies the initial stack state and provides secret input tapes;
 /// * `num_outputs` specifies the number of elements from the top of the stack to be returned;
+/// 
+/// # Panics
+/// Panics if the program cannot be executed against the provided inputs; use `try_execute()`
+/// to handle execution failures as errors.
 pub fn execute(program: &Program, inputs: &ProgramInputs, num_outputs: usize, options: &ProofOptions) -> (Vec<u128>, StarkProof)
 {
-    assert!(num_outputs <= MAX_OUTPUTS, 
-        "cannot produce more than {} outputs, but requested {}", MAX_OUTPUTS, num_outputs);
+    return match try_execute(program, inputs, num_outputs, options) {
+        Ok(result) => result,
+        Err(error) => panic!("{}", error),
+    };
+}
+
+/// Executes the specified `program` and returns the result together with a STARK-based proof of
+/// execution; returns an `ExecutionError` if the program could not be executed against the
+/// provided inputs.
+pub fn try_execute(program: &Program, inputs: &ProgramInputs, num_outputs: usize, options: &ProofOptions)
+    -> Result<(Vec<u128>, StarkProof), ExecutionError>
+{
+    let trace = generate_trace(program, inputs, &OutputSpec::Top(num_outputs))?;
+    let outputs = trace.outputs().to_vec();
+    let proof = prove(trace, options)?;
+    return Ok((outputs, proof));
+}
+
+/// Executes the specified `program` against a public input sequence of any length, and returns
+/// values written by the program to the output tape together with a STARK-based proof of
+/// execution; the proof can be verified with `verify_sequences()`.
+/// 
+/// * `inputs` must be created with `ProgramInputs::from_sequence()`;
+/// * at the end of execution, the program must leave digests of the output and the input
+///   sequences (in this order) at the top of the stack; the digests can be computed with
+///   procedures of `std::io` module.
+pub fn execute_sequences(program: &Program, inputs: &ProgramInputs, options: &ProofOptions)
+    -> Result<(Vec<u128>, StarkProof), ExecutionError>
+{
+    let (trace, output_tape) = build_trace(program, inputs, &OutputSpec::Top(2 * HASH_DIGEST_SIZE))?;
+    let proof = prove(trace, options)?;
+    return Ok((output_tape, proof));
+}
+
+/// Executes the specified `program` and returns its execution trace; a proof of execution can
+/// be generated from the trace later by calling `prove()`.
+/// 
+/// * `inputs` specifies the initial stack state and provides secret input tapes;
+/// * `outputs` specifies which elements of the stack are to be returned;
+pub fn generate_trace(program: &Program, inputs: &ProgramInputs, outputs: &OutputSpec)
+    -> Result<ExecutionTrace, ExecutionError>
+{
+    let (trace, _) = build_trace(program, inputs, outputs)?;
+    return Ok(trace);
+}
+
+/// Executes the specified `program` and returns its execution trace together with values
+/// written by the program to the output tape.
+fn build_trace(program: &Program, inputs: &ProgramInputs, outputs: &OutputSpec)
+    -> Result<(ExecutionTrace, Vec<u128>), ExecutionError>
+{
+    // make sure outputs can be bound to stack registers
+    let num_outputs = match outputs {
+        OutputSpec::Top(num_outputs) => *num_outputs,
+        OutputSpec::Positions(positions) => positions.len(),
+        OutputSpec::Stack => 0,
+    };
+    if num_outputs > MAX_OUTPUTS {
+        return Err(ExecutionError::TooManyOutputs { requested: num_outputs, max: MAX_OUTPUTS });
+    }
+    if let OutputSpec::Positions(positions) = outputs {
+        if let Some(&position) = positions.iter().find(|&&p| p >= MAX_STACK_DEPTH) {
+            return Err(ExecutionError::InvalidOutputPosition { position, max: MAX_STACK_DEPTH });
+        }
+        if let Some(position) = utils::find_duplicate(positions) {
+            return Err(ExecutionError::DuplicateOutputPosition { position });
+        }
+    }
 
     // execute the program to create an execution trace
     let now = Instant::now();
-    let (trace, ctx_depth, loop_depth) = processor::execute(program, inputs);
-    let mut trace = stark::TraceTable::new(trace, ctx_depth, loop_depth, options.extension_factor());
+    let (mut registers, ctx_depth, loop_depth, output_tape) = processor::execute(program, inputs)?;
+
+    // make sure there is a stack register for each output; stack items which were never
+    // touched by the program are zeros, and so are the registers added here; the number of
+    // items on the final stack is read from the stack size register
+    let user_stack_offset = stark::TraceState::compute_user_stack_offset(ctx_depth, loop_depth);
+    let trace_length = registers[0].len();
+    let stack_size = registers[user_stack_offset - 1][trace_length - 1] as usize;
+    let output_positions = outputs.positions(stack_size);
+    let stack_size = if outputs.binds_stack_size() { Some(stack_size) } else { None };
+    let min_stack_depth = output_positions.iter().map(|&p| p + 1).max().unwrap_or(0);
+    while registers.len() - user_stack_offset < min_stack_depth {
+        registers.push(vec![math::field::ZERO; trace_length]);
+    }
+
     debug!("Generated execution trace of {} registers and {} steps in {} ms",
-        trace.register_count(),
-        trace.unextended_length(),
+        registers.len(),
+        registers[0].len(),
         now.elapsed().as_millis());
 
     // copy the user stack state the the last step to return as output
-    let last_state = trace.get_last_state();
-    let outputs = last_state.user_stack()[..num_outputs].to_vec();
+    let last_state = get_last_state(&registers, ctx_depth, loop_depth);
+    let user_stack = last_state.user_stack();
+    let outputs = output_positions.iter().map(|&p| user_stack[p]).collect::<Vec<u128>>();
+    validate_last_state(program, &last_state)?;
+
+    let trace = ExecutionTrace::new(registers, ctx_depth, loop_depth,
+        program.hash(), inputs.get_public_inputs(), &outputs, &output_positions, stack_size);
+    return Ok((trace, output_tape));
+}
+
+/// Generates a STARK-based proof of execution from the specified execution `trace`; returns an
+/// `ExecutionError` if the `trace` is malformed, or if `options` fix trace length and the `trace`
+/// is longer than this length.
+pub fn prove(trace: ExecutionTrace, options: &ProofOptions) -> Result<StarkProof, ExecutionError>
+{
+    return prove_with_config(trace, options, &ProverConfig::default());
+}
+
+/// Generates a STARK-based proof of execution from the specified execution `trace` in the same
+/// way as `prove()`, but uses the specified prover `config` (e.g. to limit the number of threads
+/// used for proof generation); the resulting proof does not depend on the `config`.
+pub fn prove_with_config(trace: ExecutionTrace, options: &ProofOptions, config: &ProverConfig)
+    -> Result<StarkProof, ExecutionError>
+{
+    trace.validate()?;
+    if let Some(max) = options.fixed_trace_length() {
+        if trace.trace_length() > max {
+            return Err(ExecutionError::TraceTooLong { trace_length: trace.trace_length(), max });
+        }
+    }
+
+    let inputs = trace.inputs().to_vec();
+    let outputs = trace.outputs().to_vec();
+    let output_positions = trace.output_positions().to_vec();
+    let stack_size = trace.stack_size();
+    let (mut ctx_depth, mut loop_depth) = (trace.ctx_depth(), trace.loop_depth());
+    let mut registers = trace.into_registers();
+
+    // if trace width is fixed, pad context, loop, and user stacks of the trace with zero registers
+    if let Some(max) = options.fixed_trace_depths() {
+        let stack_depth = registers.len() - stark::TraceState::compute_user_stack_offset(ctx_depth, loop_depth);
+        let depths = [ctx_depth, loop_depth, stack_depth];
+        if depths.iter().zip(max.iter()).any(|(depth, max)| depth > max) {
+            return Err(ExecutionError::TraceTooWide { depths, max });
+        }
+        pad_trace_depths(&mut registers, depths, max);
+        ctx_depth = max[0];
+        loop_depth = max[1];
+    }
+
+    // make sure the trace is long enough for the specified options; if trace length is fixed,
+    // all traces are padded to the same length
+    pad_registers(&mut registers, options.min_trace_length());
+
+    let mut trace = stark::TraceTable::new(
+        registers, ctx_depth, loop_depth, options.extension_factor(), options.zero_knowledge());
+
+    return Ok(stark::prove(&mut trace, &inputs, &outputs, &output_positions, stack_size, options, config));
+}
+
+/// Executes the specified `program` without generating a proof of execution, and returns the
+/// final state of the stack together with execution trace stats. This can be used to check
+/// program outputs and to estimate the cost of proving execution of the program.
+pub fn run(program: &Program, inputs: &ProgramInputs) -> Result<ExecutionResult, ExecutionError>
+{
+    // execute the program to create an execution trace
+    let (trace, ctx_depth, loop_depth, output_tape) = processor::execute(program, inputs)?;
+    let last_state = get_last_state(&trace, ctx_depth, loop_depth);
+    validate_last_state(program, &last_state)?;
 
+    return Ok(ExecutionResult {
+        stack       : last_state.user_stack().to_vec(),
+        output_tape,
+        op_count    : last_state.op_counter(),
+        trace_length: trace[0].len(),
+        stack_depth : last_state.stack_depth(),
+        ctx_depth, loop_depth,
+    });
+}
+
+/// Returns the state of the execution trace described by `registers` at the last step.
+fn get_last_state(registers: &Vec<Vec<u128>>, ctx_depth: usize, loop_depth: usize) -> stark::TraceState
+{
+    let stack_depth = registers.len() - stark::TraceState::compute_user_stack_offset(ctx_depth, loop_depth);
+    let mut last_state = stark::TraceState::new(ctx_depth, loop_depth, stack_depth);
+    last_state.update_from_trace(registers, registers[0].len() - 1);
+    return last_state;
+}
+
+/// Extends register traces to the specified length by repeating the last state of the trace;
+/// since the last state of a trace is a VOID operation, the padded trace remains valid.
+fn pad_registers(registers: &mut Vec<Vec<u128>>, trace_length: usize) {
+    for register in registers.iter_mut() {
+        if register.len() < trace_length {
+            let last_value = register[register.len() - 1];
+            register.resize(trace_length, last_value);
+        }
+    }
+}
+
+/// Inserts zero registers into context, loop, and user stacks of the trace described by
+/// `registers` to increase their depths from `depths` to `max`; since stack registers beyond
+/// the depth of a stack are always zero, the padded trace satisfies the same constraints.
+fn pad_trace_depths(registers: &mut Vec<Vec<u128>>, depths: [usize; 3], max: [usize; 3]) {
+    let trace_length = registers[0].len();
+    let ctx_stack_end = stark::TraceState::compute_decoder_width(depths[0], 0);
+    let loop_stack_end = stark::TraceState::compute_decoder_width(max[0], depths[1]);
+    registers.splice(ctx_stack_end..ctx_stack_end, vec![vec![0; trace_length]; max[0] - depths[0]]);
+    registers.splice(loop_stack_end..loop_stack_end, vec![vec![0; trace_length]; max[1] - depths[1]]);
+    registers.resize(registers.len() + max[2] - depths[2], vec![0; trace_length]);
+}
+
+/// Makes sure that the execution trace ending in `last_state` can be proven for the `program`.
+fn validate_last_state(program: &Program, last_state: &stark::TraceState) -> Result<(), ExecutionError>
+{
     // make sure number of executed operations was sufficient
-    assert!(last_state.op_counter() as usize >= MIN_TRACE_LENGTH,
-        "a program must consist of at least {} operation, but only {} were executed",
-        MIN_TRACE_LENGTH,
-        last_state.op_counter());
+    if (last_state.op_counter() as usize) < MIN_TRACE_LENGTH {
+        return Err(ExecutionError::TraceTooShort {
+            op_count: last_state.op_counter(), min: MIN_TRACE_LENGTH });
+    }
 
     // make sure program hash generated by the VM matches the hash of the program
     let program_hash = utils::as_bytes(last_state.program_hash());
-    assert!(program.hash() == program_hash,
-        "expected program hash {} does not match trace hash {}",
-        hex::encode(program.hash()),
-        hex::encode(program_hash));
-
-    // generate STARK proof
-    let proof = stark::prove(&mut trace, inputs.get_public_inputs(), &outputs, options);
+    if program.hash() != program_hash {
+        let mut actual = [0u8; 32];
+        actual.copy_from_slice(program_hash);
+        return Err(ExecutionError::ProgramHashMismatch { expected: *program.hash(), actual });
+    }
 
-    return (outputs, proof);
+    return Ok(());
 }
 
 // VERIFIER
 // ================================================================================================
 
 /// Verifies that if a program with the specified `program_hash` is executed with the 
-/// provided `public_inputs` and some secret inputs, the result is equal to the `outputs`.
-pub fn verify(program_hash: &[u8; 32], public_inputs: &[u128], outputs: &[u128], proof: &StarkProof) -> Result<bool, String>
+/// provided `public_inputs` and some secret inputs, the result is equal to the `outputs`;
+/// returns a `VerificationError` describing the reason for rejecting the proof otherwise.
+pub fn verify(program_hash: &[u8; 32], public_inputs: &[u128], outputs: &[u128], proof: &StarkProof)
+    -> Result<bool, VerificationError>
+{
+    return verify_with_outputs(program_hash, public_inputs, &OutputSpec::Top(outputs.len()), outputs, proof);
+}
+
+/// Verifies a batch of proofs, each given as a tuple of program hash, public inputs, outputs,
+/// and a proof, and returns the result of verification for each proof in the same order.
+/// Each proof is verified in the same way as by `verify()`, but proofs are distributed across
+/// all available CPU cores, and precomputations which depend only on the shape of the
+/// execution trace are shared between proofs. A proof which makes the verifier panic is
+/// rejected with `VerificationError::MalformedProof`.
+pub fn verify_batch(proofs: &[(&[u8; 32], &[u128], &[u128], &StarkProof)])
+    -> Vec<Result<bool, VerificationError>>
+{
+    let items = proofs.iter().map(|&(program_hash, public_inputs, outputs, proof)| {
+        let output_positions = OutputSpec::Top(outputs.len()).positions(outputs.len());
+        (program_hash, public_inputs, outputs, output_positions, None, proof)
+    }).collect::<Vec<_>>();
+
+    let num_threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    return stark::verify_batch(&items, num_threads);
+}
+
+/// Verifies execution of a program in the same way as `verify()`, but first makes sure that
+/// options of the `proof` satisfy the specified `policy`; proofs which fall below the policy
+/// are rejected with `VerificationError::OptionsPolicyViolation`.
+pub fn verify_with_policy(
+    program_hash    : &[u8; 32],
+    public_inputs   : &[u128],
+    outputs         : &[u128],
+    proof           : &StarkProof,
+    policy          : &VerifierPolicy) -> Result<bool, VerificationError>
 {
-    return stark::verify(program_hash, public_inputs, outputs, proof);
+    policy.validate(proof)?;
+    return verify(program_hash, public_inputs, outputs, proof);
+}
+
+/// Verifies execution of a program in the same way as `verify_with_outputs()`, but first makes
+/// sure that options of the `proof` satisfy the specified `policy`.
+pub fn verify_with_outputs_and_policy(
+    program_hash    : &[u8; 32],
+    public_inputs   : &[u128],
+    output_spec     : &OutputSpec,
+    outputs         : &[u128],
+    proof           : &StarkProof,
+    policy          : &VerifierPolicy) -> Result<bool, VerificationError>
+{
+    policy.validate(proof)?;
+    return verify_with_outputs(program_hash, public_inputs, output_spec, outputs, proof);
+}
+
+/// Verifies a batch of proofs in the same way as `verify_batch()`, but first makes sure that
+/// options of each proof satisfy the specified `policy`; proofs which fall below the policy are
+/// rejected with `VerificationError::OptionsPolicyViolation` without being verified.
+pub fn verify_batch_with_policy(proofs: &[(&[u8; 32], &[u128], &[u128], &StarkProof)], policy: &VerifierPolicy)
+    -> Vec<Result<bool, VerificationError>>
+{
+    let checks = proofs.iter().map(|(_, _, _, proof)| policy.validate(proof)).collect::<Vec<_>>();
+    let accepted = proofs.iter().zip(checks.iter())
+        .filter(|(_, check)| check.is_ok())
+        .map(|(&item, _)| item)
+        .collect::<Vec<_>>();
+
+    let mut results = verify_batch(&accepted).into_iter();
+    return checks.into_iter().map(|check| match check {
+        Ok(()) => results.next().unwrap(),
+        Err(error) => Err(error),
+    }).collect();
+}
+
+/// Verifies the statement and the proof contained in an execution `receipt` in the same way as
+/// `verify_with_outputs()`, using the output spec of the receipt; receipts created with an
+/// unsupported version of the receipt format are rejected with
+/// `VerificationError::UnsupportedReceiptVersion`.
+pub fn verify_receipt(receipt: &ExecutionReceipt) -> Result<bool, VerificationError>
+{
+    if receipt.version() != RECEIPT_FORMAT_VERSION {
+        return Err(VerificationError::UnsupportedReceiptVersion {
+            version: receipt.version(), expected: RECEIPT_FORMAT_VERSION });
+    }
+    return verify_with_outputs(receipt.program_hash(), receipt.public_inputs(), receipt.output_spec(),
+        receipt.outputs(), receipt.proof());
+}
+
+/// Verifies that if a program with the specified `program_hash` is executed with the provided
+/// `public_inputs` and some secret inputs, items of the final stack selected by `output_spec`
+/// are equal to the `outputs`; with `OutputSpec::Stack`, the `outputs` must contain all items
+/// of the final stack.
+pub fn verify_with_outputs(
+    program_hash    : &[u8; 32],
+    public_inputs   : &[u128],
+    output_spec     : &OutputSpec,
+    outputs         : &[u128],
+    proof           : &StarkProof) -> Result<bool, VerificationError>
+{
+    let output_positions = output_spec.positions(outputs.len());
+    if output_positions.len() != outputs.len() {
+        return Err(VerificationError::OutputCountMismatch {
+            expected: output_positions.len(), actual: outputs.len() });
+    }
+    let stack_size = if output_spec.binds_stack_size() { Some(outputs.len()) } else { None };
+    return stark::verify(program_hash, public_inputs, outputs, &output_positions, stack_size, proof);
+}
+
+/// Verifies that if a program with the specified `program_hash` is executed against the public
+/// input sequence `inputs` and some secret inputs, it writes the `outputs` sequence to the output
+/// tape; both sequences can be of any length. Digests of both sequences are computed with
+/// `utils::hasher::digest_sequence()`: the length of the input sequence is bound to the initial
+/// stack (see `ProgramInputs::from_sequence()`), and digests of the output and the input
+/// sequences are bound to the top 4 items of the final stack.
+pub fn verify_sequences(program_hash: &[u8; 32], inputs: &[u128], outputs: &[u128], proof: &StarkProof)
+    -> Result<bool, VerificationError>
+{
+    let input_digest = utils::hasher::digest_sequence(inputs);
+    let output_digest = utils::hasher::digest_sequence(outputs);
+    let public_inputs = [math::field::ZERO, inputs.len() as u128];
+    let digests = [output_digest[1], output_digest[0], input_digest[1], input_digest[0]];
+    return verify(program_hash, &public_inputs, &digests, proof);
 }
 
 // GLOBAL CONSTANTS
@@ -134,6 +447,10 @@ const HD_OP_BITS_RANGE      : Range<usize> = Range { start: 13, end: 15 };
 //    0      1    2    .................................    31
 // ├─────┴─────┴─────┴─────┴─────┴─────┴─────┴─────┴─────┴─────┤
 
-pub const MAX_PUBLIC_INPUTS : usize = 8;
-pub const MAX_OUTPUTS       : usize = MAX_PUBLIC_INPUTS;
-pub const MAX_STACK_DEPTH   : usize = 32;
\ No newline at end of file
+pub const MAX_STACK_DEPTH   : usize = 32;
+
+// public inputs and outputs are bound to user stack registers at the first and the last steps
+// of the execution trace; so, any register of the stack can hold an input or an output. longer
+// sequences are bound through their digests (see execute_sequences() and verify_sequences())
+pub const MAX_PUBLIC_INPUTS : usize = MAX_STACK_DEPTH;
+pub const MAX_OUTPUTS       : usize = MAX_STACK_DEPTH;
\ No newline at end of file
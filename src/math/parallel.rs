@@ -178,6 +178,19 @@ pub fn inv(values: &[u128], num_threads: usize) -> Vec<u128> {
     return result;
 }
 
+// BATCHING
+// ================================================================================================
+
+/// Returns the number of batches into which `n` values should be split to be processed by up to
+/// `num_threads` threads. The result is a power of 2 which divides `n` (assuming `n` is a power
+/// of 2), and thus can be passed as `num_threads` to all functions in this module as well as to
+/// `fft::fft_in_place()`.
+pub fn get_num_batches(n: usize, num_threads: usize) -> usize {
+    let num_threads = std::cmp::max(num_threads, 1);
+    let num_batches = if num_threads.is_power_of_two() { num_threads } else { num_threads.next_power_of_two() / 2 };
+    return std::cmp::max(std::cmp::min(num_batches, n), 1);
+}
+
 // TESTS
 // ================================================================================================
 #[cfg(test)]
@@ -290,6 +303,16 @@ mod tests {
         assert_eq!(expected, x);
     }
 
+    #[test]
+    fn get_num_batches() {
+        assert_eq!(1, super::get_num_batches(1024, 0));
+        assert_eq!(1, super::get_num_batches(1024, 1));
+        assert_eq!(2, super::get_num_batches(1024, 3));
+        assert_eq!(8, super::get_num_batches(1024, 8));
+        assert_eq!(8, super::get_num_batches(1024, 12));
+        assert_eq!(4, super::get_num_batches(4, 16));
+    }
+
     #[test]
     fn inv() {
 
@@ -32,9 +32,14 @@ pub fn eval_fft(p: &mut [u128], unpermute: bool) {
 /// 
 /// If `unpermute` parameter is set to false, the evaluations will be left in permuted state.
 pub fn eval_fft_twiddles(p: &mut [u128], twiddles: &[u128], unpermute: bool) {
+    eval_fft_twiddles_par(p, twiddles, unpermute, 1);
+}
+
+/// Evaluates polynomial `p` in the same way as `eval_fft_twiddles()`, but distributes the
+/// evaluation across `num_threads` threads; `num_threads` must be a power of 2.
+pub fn eval_fft_twiddles_par(p: &mut [u128], twiddles: &[u128], unpermute: bool, num_threads: usize) {
     debug_assert!(p.len() == twiddles.len() * 2, "Invalid number of twiddles");
-    // TODO: don't hard-code num_threads
-    fft::fft_in_place(p, &twiddles, 1, 1, 0, 1);
+    fft::fft_in_place(p, &twiddles, 1, 1, 0, num_threads);
     if unpermute {
         fft::permute(p);
     }
@@ -91,8 +96,14 @@ pub fn interpolate_fft(v: &mut [u128], unpermute: bool) {
 /// 
 /// If `unpermute` parameter is set to false, the evaluations will be left in permuted state.
 pub fn interpolate_fft_twiddles(v: &mut [u128], inv_twiddles: &[u128], unpermute: bool) {
-    // TODO: don't hard-code num_threads
-    fft::fft_in_place(v, &inv_twiddles, 1, 1, 0, 1);
+    interpolate_fft_twiddles_par(v, inv_twiddles, unpermute, 1);
+}
+
+/// Interpolates a polynomial from values `v` in the same way as `interpolate_fft_twiddles()`,
+/// but distributes the interpolation across `num_threads` threads; `num_threads` must be a
+/// power of 2.
+pub fn interpolate_fft_twiddles_par(v: &mut [u128], inv_twiddles: &[u128], unpermute: bool, num_threads: usize) {
+    fft::fft_in_place(v, &inv_twiddles, 1, 1, 0, num_threads);
     let inv_length = field::inv(v.len() as u128);
     for e in v.iter_mut() {
         *e = field::mul(*e, inv_length);
@@ -280,7 +291,7 @@ fn get_zero_roots(xs: &[u128]) -> Vec<u128> {
 #[cfg(test)]
 mod tests {
 
-    use crate::math::{ field };
+    use crate::math::{ field, fft };
     use crate::utils::remove_leading_zeros;
 
     #[test]
@@ -330,6 +341,28 @@ mod tests {
         assert_eq!(y1, y2);
     }
 
+    #[test]
+    fn fft_twiddles_par() {
+        let n: usize = 1024;
+        let root = field::get_root_of_unity(n);
+        let twiddles = fft::get_twiddles(root, n);
+        let inv_twiddles = fft::get_inv_twiddles(root, n);
+        let poly = field::rand_vector(n);
+
+        let mut y1 = poly.clone();
+        super::eval_fft_twiddles(&mut y1, &twiddles, true);
+
+        // results do not depend on the number of threads
+        for &num_threads in [1, 2, 8].iter() {
+            let mut y2 = poly.clone();
+            super::eval_fft_twiddles_par(&mut y2, &twiddles, true, num_threads);
+            assert_eq!(y1, y2);
+
+            super::interpolate_fft_twiddles_par(&mut y2, &inv_twiddles, true, num_threads);
+            assert_eq!(poly, y2);
+        }
+    }
+
     #[test]
     fn add() {
         let poly1: [u128; 3] = [384863712573444386, 7682273369345308472, 13294661765012277990];
@@ -6,6 +6,7 @@ use crate::{
     SPONGE_WIDTH, BASE_CYCLE_LENGTH, PUSH_OP_ALIGNMENT,
 };
 use super::opcodes::{ FlowOps, UserOps };
+use super::ExecutionError;
 
 #[cfg(test)]
 mod tests;
@@ -157,15 +158,16 @@ impl Decoder {
     // --------------------------------------------------------------------------------------------
 
     /// Initiates a new program block (Group or Switch).
-    pub fn start_block(&mut self) {
+    pub fn start_block(&mut self) -> Result<(), ExecutionError> {
         assert!(self.step % BASE_CYCLE_LENGTH == BASE_CYCLE_LENGTH - 1,
             "cannot start context block at step {}: operation alignment is not valid", self.step);
 
         self.advance_step(false);
-        self.save_context();
+        self.save_context()?;
         self.copy_loop_stack();
         self.set_op_bits(FlowOps::Begin, UserOps::Noop);
         self.set_sponge([0, 0, 0, 0]);
+        return Ok(());
     }
 
     /// Terminates a program block (Group, Switch, or Loop).
@@ -191,15 +193,16 @@ impl Decoder {
     }
 
     /// Initiates a new Loop block
-    pub fn start_loop(&mut self, loop_image: u128) {
+    pub fn start_loop(&mut self, loop_image: u128) -> Result<(), ExecutionError> {
         assert!(self.step % BASE_CYCLE_LENGTH == BASE_CYCLE_LENGTH - 1,
             "cannot start a loop at step {}: operation alignment is not valid", self.step);
 
         self.advance_step(false);
-        self.save_context();
-        self.save_loop_image(loop_image);
+        self.save_context()?;
+        self.save_loop_image(loop_image)?;
         self.set_op_bits(FlowOps::Loop, UserOps::Noop);
         self.set_sponge([0, 0, 0, 0]);
+        return Ok(());
     }
 
     /// Prepares the decoder for the next iteration of a loop.
@@ -324,10 +327,12 @@ impl Decoder {
     // --------------------------------------------------------------------------------------------
 
     /// Pushes hash of the current program block onto the context stack.
-    fn save_context(&mut self) {
+    fn save_context(&mut self) -> Result<(), ExecutionError> {
         // increment context depth and make sure it doesn't overflow the stack
+        if self.ctx_depth == MAX_CONTEXT_DEPTH {
+            return Err(ExecutionError::ContextStackOverflow { step: self.step });
+        }
         self.ctx_depth += 1;
-        assert!(self.ctx_depth <= MAX_CONTEXT_DEPTH, "context stack overflow at step {}", self.step);
 
         // if the depth exceeds current number of registers allocated for the context stack,
         // add a new register trace to the stack
@@ -342,7 +347,8 @@ impl Decoder {
 
         // set the top of the stack to the hash of the current program block
         // which is located in the first register of the sponge
-        self.ctx_stack[0][self.step] = self.sponge[0]
+        self.ctx_stack[0][self.step] = self.sponge[0];
+        return Ok(());
     }
 
     /// Removes the top value from the context stack and returns it.
@@ -372,10 +378,12 @@ impl Decoder {
     // --------------------------------------------------------------------------------------------
 
     /// Pushes `loop_image` onto the loop stack.
-    fn save_loop_image(&mut self, loop_image: u128) {
+    fn save_loop_image(&mut self, loop_image: u128) -> Result<(), ExecutionError> {
         // increment loop depth and make sure it doesn't overflow the stack
+        if self.loop_depth == MAX_LOOP_DEPTH {
+            return Err(ExecutionError::LoopStackOverflow { step: self.step });
+        }
         self.loop_depth += 1;
-        assert!(self.loop_depth <= MAX_LOOP_DEPTH, "loop stack overflow at step {}", self.step);
 
         // if the depth exceeds current number of registers allocated for the loop stack,
         // add a new register trace to the stack
@@ -390,6 +398,7 @@ impl Decoder {
 
         // set the top of the stack to loop_image
         self.loop_stack[0][self.step] = loop_image;
+        return Ok(());
     }
 
     /// Copies contents of the loop stack from the previous to the current step and returns
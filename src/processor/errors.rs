@@ -0,0 +1,113 @@
+use super::OpCode;
+
+// TYPES AND INTERFACES
+// ================================================================================================
+
+/// Describes a failure which occurred while executing a program. Errors raised by individual
+/// operations carry the operation which failed, the step at which it was executed, and the state
+/// of the stack right before the operation was executed.
+#[derive(Clone, PartialEq)]
+pub enum ExecutionError {
+    StackUnderflow      { op: OpCode, step: usize, stack: Vec<u128> },
+    StackOverflow       { op: OpCode, step: usize, stack: Vec<u128> },
+    EmptyInputTape      { op: OpCode, step: usize, stack: Vec<u128>, tape: char },
+    FailedAssertion     { op: OpCode, step: usize, stack: Vec<u128> },
+    NonBinaryCondition  { op: OpCode, step: usize, stack: Vec<u128> },
+    NonBinaryValue      { op: OpCode, step: usize, stack: Vec<u128> },
+    ZeroInverse         { op: OpCode, step: usize, stack: Vec<u128> },
+    InvalidOperand      { op: OpCode, step: usize, stack: Vec<u128> },
+    InvalidOpHint       { op: OpCode, step: usize, stack: Vec<u128> },
+    NonBinaryBranch     { step: usize, stack: Vec<u128> },
+    ContextStackOverflow{ step: usize },
+    LoopStackOverflow   { step: usize },
+    TooManyOutputs      { requested: usize, max: usize },
+    InvalidOutputPosition { position: usize, max: usize },
+    DuplicateOutputPosition { position: usize },
+    TraceTooShort       { op_count: u128, min: usize },
+    TraceTooLong        { trace_length: usize, max: usize },
+    TraceTooWide        { depths: [usize; 3], max: [usize; 3] },
+    ProgramHashMismatch { expected: [u8; 32], actual: [u8; 32] },
+    MalformedTrace(String),
+}
+
+// COMMON TRAIT IMPLEMENTATIONS
+// ================================================================================================
+
+impl std::fmt::Display for ExecutionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use ExecutionError::*;
+        return match self {
+            StackUnderflow { step, .. } => {
+                write!(f, "stack underflow at step {}", step)
+            },
+            StackOverflow { step, .. } => {
+                write!(f, "stack overflow at step {}", step)
+            },
+            EmptyInputTape { step, tape, .. } => {
+                write!(f, "attempt to read from empty tape {} at step {}", tape, step)
+            },
+            FailedAssertion { op, step, .. } => {
+                write!(f, "{} failed at step {}", op.to_string().to_uppercase(), step)
+            },
+            NonBinaryCondition { op, step, .. } => {
+                write!(f, "{} on a non-binary condition at step {}", op.to_string().to_uppercase(), step)
+            },
+            NonBinaryValue { op, step, .. } => {
+                let preposition = if *op == OpCode::Not { "of" } else { "for" };
+                write!(f, "cannot compute {} {} a non-binary value at step {}",
+                    op.to_string().to_uppercase(), preposition, step)
+            },
+            ZeroInverse { op, step, .. } => {
+                write!(f, "cannot compute {} of 0 at step {}", op.to_string().to_uppercase(), step)
+            },
+            InvalidOperand { op, step, .. } => {
+                write!(f, "invalid operand for {} operation at step {}", op.to_string().to_uppercase(), step)
+            },
+            InvalidOpHint { op, step, .. } => {
+                write!(f, "invalid hint for {} operation at step {}", op.to_string().to_uppercase(), step)
+            },
+            NonBinaryBranch { step, stack } => {
+                write!(f, "cannot select a branch based on a non-binary condition {} at step {}", stack[0], step)
+            },
+            ContextStackOverflow { step } => {
+                write!(f, "context stack overflow at step {}", step)
+            },
+            LoopStackOverflow { step } => {
+                write!(f, "loop stack overflow at step {}", step)
+            },
+            TooManyOutputs { requested, max } => {
+                write!(f, "cannot produce more than {} outputs, but requested {}", max, requested)
+            },
+            InvalidOutputPosition { position, max } => {
+                write!(f, "cannot produce an output from stack position {}; positions must be smaller than {}", position, max)
+            },
+            DuplicateOutputPosition { position } => {
+                write!(f, "cannot produce more than one output from stack position {}", position)
+            },
+            TraceTooShort { op_count, min } => {
+                write!(f, "a program must consist of at least {} operation, but only {} were executed", min, op_count)
+            },
+            TraceTooLong { trace_length, max } => {
+                write!(f, "execution trace of {} steps does not fit into the fixed trace length of {} steps", trace_length, max)
+            },
+            TraceTooWide { depths, max } => {
+                write!(f, "execution trace with context, loop, and stack depths {:?} does not fit into the fixed trace depths {:?}", depths, max)
+            },
+            ProgramHashMismatch { expected, actual } => {
+                write!(f, "expected program hash {} does not match trace hash {}",
+                    hex::encode(expected), hex::encode(actual))
+            },
+            MalformedTrace(reason) => {
+                write!(f, "execution trace is malformed: {}", reason)
+            },
+        };
+    }
+}
+
+impl std::fmt::Debug for ExecutionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "execution error: {}", self)
+    }
+}
+
+impl std::error::Error for ExecutionError {}
@@ -174,6 +174,7 @@ pub enum OpHint {
     RcStart(u32),
     CmpStart(u32),
     PushValue(u128),
+    WriteOutput,
     None,
 }
 
@@ -193,6 +194,7 @@ impl std::fmt::Display for OpHint {
             OpHint::RcStart(value)   => write!(f, ".{}", value),
             OpHint::CmpStart(value)  => write!(f, ".{}", value),
             OpHint::PushValue(value) => write!(f, "({})", value),
+            OpHint::WriteOutput      => write!(f, "::write"),
             OpHint::None             => Ok(()),
         };
     }
@@ -0,0 +1,21 @@
+// TYPES AND INTERFACES
+// ================================================================================================
+
+/// Describes the outcome of executing a program without generating a proof of execution.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExecutionResult {
+    /// State of the user stack after the last operation was executed.
+    pub stack           : Vec<u128>,
+    /// Values written to the output tape, in the order in which they were written.
+    pub output_tape     : Vec<u128>,
+    /// Number of operations executed by the program, including operations inserted by the VM.
+    pub op_count        : u128,
+    /// Length of the execution trace; this is the trace length a proof would be generated for.
+    pub trace_length    : usize,
+    /// Maximum depth of the context stack reached during execution.
+    pub ctx_depth       : usize,
+    /// Maximum depth of the loop stack reached during execution.
+    pub loop_depth      : usize,
+    /// Maximum depth of the user stack reached during execution.
+    pub stack_depth     : usize,
+}
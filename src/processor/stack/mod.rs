@@ -4,6 +4,7 @@ use crate::{
     ProgramInputs, OpCode, OpHint,
     HASH_STATE_WIDTH, MIN_STACK_DEPTH, MAX_STACK_DEPTH,
 };
+use super::ExecutionError;
 
 #[cfg(test)]
 mod tests;
@@ -12,8 +13,10 @@ mod tests;
 // ================================================================================================
 pub struct Stack {
     registers   : Vec<Vec<u128>>,
+    depth_trace : Vec<u128>,
     tape_a      : Vec<u128>,
     tape_b      : Vec<u128>,
+    output_tape : Vec<u128>,
     max_depth   : usize,
     depth       : usize,
     step        : usize,
@@ -40,6 +43,10 @@ impl Stack {
             registers.push(register);
         }
 
+        // stack depth is recorded at every step so that it can be bound by the proof
+        let mut depth_trace = vec![field::ZERO; init_trace_length];
+        depth_trace[0] = public_inputs.len() as u128;
+
         // reverse secret inputs so that they are consumed in FIFO order
         let [secret_inputs_a, secret_inputs_b] = inputs.get_secret_inputs();
         let mut tape_a = secret_inputs_a.clone();
@@ -49,16 +56,19 @@ impl Stack {
 
         return Stack {
             registers,
+            depth_trace,
             tape_a,
             tape_b,
+            output_tape: Vec::new(),
             max_depth: public_inputs.len(),
             depth: public_inputs.len(),
             step: 0,
         };
     }
 
-    /// Executes `opcode` against the current state of the stack.
-    pub fn execute(&mut self, op_code: OpCode, op_hint: OpHint) {
+    /// Executes `opcode` against the current state of the stack; returns an error if the
+    /// operation cannot be executed against the current state of the stack.
+    pub fn execute(&mut self, op_code: OpCode, op_hint: OpHint) -> Result<(), ExecutionError> {
 
         // increment step pointer and make sure there is enough memory allocated to hold the trace
         self.advance_step();
@@ -81,7 +91,7 @@ impl Stack {
             OpCode::Dup4        => self.op_dup4(),
             OpCode::Pad2        => self.op_pad2(),
 
-            OpCode::Drop        => self.op_drop(),
+            OpCode::Drop        => self.op_drop(op_hint),
             OpCode::Drop4       => self.op_drop4(),
 
             OpCode::Swap        => self.op_swap(),
@@ -107,7 +117,11 @@ impl Stack {
             OpCode::BinAcc      => self.op_binacc(op_hint),
 
             OpCode::RescR       => self.op_rescr(),
-        }
+        }?;
+
+        // record the depth of the stack after the operation
+        self.depth_trace[self.step] = self.depth as u128;
+        return Ok(());
     }
 
     /// Returns trace length of register traces in the decoder.
@@ -116,7 +130,6 @@ impl Stack {
     }
 
     /// Returns value of the current step pointer.
-    #[cfg(test)]
     pub fn current_step(&self) -> usize {
         return self.step;
     }
@@ -126,6 +139,11 @@ impl Stack {
         return self.registers[0][self.step];
     }
 
+    /// Returns all values on the stack at the current step.
+    pub fn current_state(&self) -> Vec<u128> {
+        return (0..self.depth).map(|i| self.registers[i][self.step]).collect();
+    }
+
     /// Populate all register traces with values for steps between the current step
     /// and the end of the trace.
     pub fn finalize_trace(&mut self) {
@@ -134,57 +152,77 @@ impl Stack {
             register.resize(self.step + 1, field::ZERO);
             register.resize(trace_length, register[self.step]);
         }
+        self.depth_trace.truncate(self.step + 1);
+        self.depth_trace.resize(trace_length, self.depth as u128);
 
         // update the step pointer to point to the last step
         self.step = self.trace_length() - 1;
     }
 
-    /// Merges all register traces into a single vector of traces.
+    /// Returns values written to the output tape so far, in the order in which they were written.
+    pub fn output_tape(&self) -> &[u128] {
+        return &self.output_tape;
+    }
+
+    /// Merges all register traces into a single vector of traces; the first trace holds the
+    /// depth of the stack at every step, and is followed by traces of stack registers.
     pub fn into_register_traces(mut self) -> Vec<Vec<u128>> {
         self.registers.truncate(self.max_depth);
-        return self.registers;
+        let mut result = Vec::with_capacity(self.registers.len() + 1);
+        result.push(self.depth_trace);
+        result.append(&mut self.registers);
+        return result;
     }
 
     // FLOW CONTROL OPERATIONS
     // --------------------------------------------------------------------------------------------
-    fn op_noop(&mut self) {
+    fn op_noop(&mut self) -> Result<(), ExecutionError> {
         self.copy_state(0);
+        return Ok(());
     }
 
-    fn op_assert(&mut self) {
-        assert!(self.depth >= 1, "stack underflow at step {}", self.step);
+    fn op_assert(&mut self) -> Result<(), ExecutionError> {
+        self.check_depth(1, OpCode::Assert)?;
         let value = self.registers[0][self.step - 1];
-        assert!(value == field::ONE, "ASSERT failed at step {}", self.step);
+        if value != field::ONE {
+            return Err(self.failed_assertion(OpCode::Assert));
+        }
         self.shift_left(1, 1);
+        return Ok(());
     }
 
-    fn op_asserteq(&mut self) {
-        assert!(self.depth >= 2, "stack underflow at step {}", self.step);
+    fn op_asserteq(&mut self) -> Result<(), ExecutionError> {
+        self.check_depth(2, OpCode::AssertEq)?;
         let x = self.registers[0][self.step - 1];
         let y = self.registers[1][self.step - 1];
-        assert!(x == y, "ASSERTEQ failed at step {}", self.step);
+        if x != y {
+            return Err(self.failed_assertion(OpCode::AssertEq));
+        }
         self.shift_left(2, 2);
+        return Ok(());
     }
 
     // INPUT OPERATIONS
     // --------------------------------------------------------------------------------------------
-    fn op_push(&mut self, hint: OpHint) {
-        self.shift_right(0, 1);
+    fn op_push(&mut self, hint: OpHint) -> Result<(), ExecutionError> {
         let op_value = match hint {
             OpHint::PushValue(value) => value,
-            _ => panic!("invalid value for PUSH operation at step {}", self.step),
+            _ => return Err(ExecutionError::InvalidOpHint {
+                op: OpCode::Push, step: self.step, stack: self.snapshot() }),
         };
+        self.shift_right(0, 1, OpCode::Push)?;
         self.registers[0][self.step] = op_value;
+        return Ok(());
     }
 
-    fn op_read(&mut self, hint: OpHint) {
+    fn op_read(&mut self, hint: OpHint) -> Result<(), ExecutionError> {
         // process execution hint
         match hint {
             OpHint::EqStart => {
                 // if we are about to equality comparison sequence, push inverse of the difference
                 // between top two stack values onto secret tape A, if they are equal; otherwise
                 // push value 1
-                assert!(self.depth >= 2, "stack underflow at step {}", self.step);
+                self.check_depth(2, OpCode::Read)?;
                 let x = self.registers[0][self.step - 1];
                 let y = self.registers[1][self.step - 1];
                 if x == y {
@@ -195,83 +233,97 @@ impl Stack {
                 }
             },
             _ => {
-                assert!(self.tape_a.len() > 0, "attempt to read from empty tape A at step {}", self.step);
+                self.check_tape('A', OpCode::Read)?;
             }
         }
 
-        self.shift_right(0, 1);
+        self.shift_right(0, 1, OpCode::Read)?;
         let value = self.tape_a.pop().unwrap();
         self.registers[0][self.step] = value;
+        return Ok(());
     }
 
-    fn op_read2(&mut self) {
-        assert!(self.tape_a.len() > 0, "attempt to read from empty tape A at step {}", self.step);
-        assert!(self.tape_b.len() > 0, "attempt to read from empty tape B at step {}", self.step);
-        self.shift_right(0, 2);
+    fn op_read2(&mut self) -> Result<(), ExecutionError> {
+        self.check_tape('A', OpCode::Read2)?;
+        self.check_tape('B', OpCode::Read2)?;
+        self.shift_right(0, 2, OpCode::Read2)?;
         let value_a = self.tape_a.pop().unwrap();
         let value_b = self.tape_b.pop().unwrap();
         self.registers[0][self.step] = value_b;
         self.registers[1][self.step] = value_a;
+        return Ok(());
     }
 
     // STACK MANIPULATION OPERATIONS
     // --------------------------------------------------------------------------------------------
-    fn op_dup(&mut self) {
-        assert!(self.depth >= 1, "stack underflow at step {}", self.step);
-        self.shift_right(0, 1);
+    fn op_dup(&mut self) -> Result<(), ExecutionError> {
+        self.check_depth(1, OpCode::Dup)?;
+        self.shift_right(0, 1, OpCode::Dup)?;
         self.registers[0][self.step] = self.registers[0][self.step - 1];
+        return Ok(());
     }
 
-    fn op_dup2(&mut self) {
-        assert!(self.depth >= 2, "stack underflow at step {}", self.step);
-        self.shift_right(0, 2);
+    fn op_dup2(&mut self) -> Result<(), ExecutionError> {
+        self.check_depth(2, OpCode::Dup2)?;
+        self.shift_right(0, 2, OpCode::Dup2)?;
         self.registers[0][self.step] = self.registers[0][self.step - 1];
         self.registers[1][self.step] = self.registers[1][self.step - 1];
+        return Ok(());
     }
 
-    fn op_dup4(&mut self) {
-        assert!(self.depth >= 4, "stack underflow at step {}", self.step);
-        self.shift_right(0, 4);
+    fn op_dup4(&mut self) -> Result<(), ExecutionError> {
+        self.check_depth(4, OpCode::Dup4)?;
+        self.shift_right(0, 4, OpCode::Dup4)?;
         self.registers[0][self.step] = self.registers[0][self.step - 1];
         self.registers[1][self.step] = self.registers[1][self.step - 1];
         self.registers[2][self.step] = self.registers[2][self.step - 1];
         self.registers[3][self.step] = self.registers[3][self.step - 1];
+        return Ok(());
     }
 
-    fn op_pad2(&mut self) {
-        self.shift_right(0, 2);
+    fn op_pad2(&mut self) -> Result<(), ExecutionError> {
+        self.shift_right(0, 2, OpCode::Pad2)?;
         self.registers[0][self.step] = field::ZERO;
         self.registers[1][self.step] = field::ZERO;
+        return Ok(());
     }
 
-    fn op_drop(&mut self) {
-        assert!(self.depth >= 1, "stack underflow at step {}", self.step);
+    fn op_drop(&mut self, hint: OpHint) -> Result<(), ExecutionError> {
+        self.check_depth(1, OpCode::Drop)?;
+        // if the value is to be written, append it to the output tape before dropping it
+        if let OpHint::WriteOutput = hint {
+            self.output_tape.push(self.registers[0][self.step - 1]);
+        }
         self.shift_left(1, 1);
+        return Ok(());
     }
 
-    fn op_drop4(&mut self) {
-        assert!(self.depth >= 4, "stack underflow at step {}", self.step);
+    fn op_drop4(&mut self) -> Result<(), ExecutionError> {
+        self.check_depth(4, OpCode::Drop4)?;
         self.shift_left(4, 4);
+        return Ok(());
     }
 
-    fn op_swap(&mut self) {
-        assert!(self.depth >= 2, "stack underflow at step {}", self.step);
+    fn op_swap(&mut self) -> Result<(), ExecutionError> {
+        self.check_depth(2, OpCode::Swap)?;
         self.registers[0][self.step] = self.registers[1][self.step - 1];
         self.registers[1][self.step] = self.registers[0][self.step - 1];
         self.copy_state(2);
+        return Ok(());
     }
 
-    fn op_swap2(&mut self) {
-        assert!(self.depth >= 4, "stack underflow at step {}", self.step);
+    fn op_swap2(&mut self) -> Result<(), ExecutionError> {
+        self.check_depth(4, OpCode::Swap2)?;
         self.registers[0][self.step] = self.registers[2][self.step - 1];
         self.registers[1][self.step] = self.registers[3][self.step - 1];
         self.registers[2][self.step] = self.registers[0][self.step - 1];
         self.registers[3][self.step] = self.registers[1][self.step - 1];
         self.copy_state(4);
+        return Ok(());
     }
 
-    fn op_swap4(&mut self) {
-        assert!(self.depth >= 8, "stack underflow at step {}", self.step);
+    fn op_swap4(&mut self) -> Result<(), ExecutionError> {
+        self.check_depth(8, OpCode::Swap4)?;
         self.registers[0][self.step] = self.registers[4][self.step - 1];
         self.registers[1][self.step] = self.registers[5][self.step - 1];
         self.registers[2][self.step] = self.registers[6][self.step - 1];
@@ -281,19 +333,21 @@ impl Stack {
         self.registers[6][self.step] = self.registers[2][self.step - 1];
         self.registers[7][self.step] = self.registers[3][self.step - 1];
         self.copy_state(8);
+        return Ok(());
     }
 
-    fn op_roll4(&mut self) {
-        assert!(self.depth >= 4, "stack underflow at step {}", self.step);
+    fn op_roll4(&mut self) -> Result<(), ExecutionError> {
+        self.check_depth(4, OpCode::Roll4)?;
         self.registers[0][self.step] = self.registers[3][self.step - 1];
         self.registers[1][self.step] = self.registers[0][self.step - 1];
         self.registers[2][self.step] = self.registers[1][self.step - 1];
         self.registers[3][self.step] = self.registers[2][self.step - 1];
         self.copy_state(4);
+        return Ok(());
     }
 
-    fn op_roll8(&mut self) {
-        assert!(self.depth >= 8, "stack underflow at step {}", self.step);
+    fn op_roll8(&mut self) -> Result<(), ExecutionError> {
+        self.check_depth(8, OpCode::Roll8)?;
         self.registers[0][self.step] = self.registers[7][self.step - 1];
         self.registers[1][self.step] = self.registers[0][self.step - 1];
         self.registers[2][self.step] = self.registers[1][self.step - 1];
@@ -303,12 +357,13 @@ impl Stack {
         self.registers[6][self.step] = self.registers[5][self.step - 1];
         self.registers[7][self.step] = self.registers[6][self.step - 1];
         self.copy_state(8);
+        return Ok(());
     }
 
     // SELECTION OPERATIONS
     // --------------------------------------------------------------------------------------------
-    fn op_choose(&mut self) {
-        assert!(self.depth >= 3, "stack underflow at step {}", self.step);
+    fn op_choose(&mut self) -> Result<(), ExecutionError> {
+        self.check_depth(3, OpCode::Choose)?;
         let condition = self.registers[2][self.step - 1];
         if condition == field::ONE {
             self.registers[0][self.step] = self.registers[0][self.step - 1];
@@ -317,13 +372,15 @@ impl Stack {
             self.registers[0][self.step] = self.registers[1][self.step - 1];
         }
         else {
-            assert!(false, "CHOOSE on a non-binary condition at step {}", self.step);
+            return Err(ExecutionError::NonBinaryCondition {
+                op: OpCode::Choose, step: self.step, stack: self.snapshot() });
         }
         self.shift_left(3, 2);
+        return Ok(());
     }
 
-    fn op_choose2(&mut self) {
-        assert!(self.depth >= 6, "stack underflow at step {}", self.step);
+    fn op_choose2(&mut self) -> Result<(), ExecutionError> {
+        self.check_depth(6, OpCode::Choose2)?;
         let condition = self.registers[4][self.step - 1];
         if condition == field::ONE {
             self.registers[0][self.step] = self.registers[0][self.step - 1];
@@ -334,79 +391,95 @@ impl Stack {
             self.registers[1][self.step] = self.registers[3][self.step - 1];
         }
         else {
-            assert!(false, "CHOOSE2 on a non-binary condition at step {}", self.step);
+            return Err(ExecutionError::NonBinaryCondition {
+                op: OpCode::Choose2, step: self.step, stack: self.snapshot() });
         }
         self.shift_left(6, 4);
+        return Ok(());
     }
 
     // ARITHMETIC AND BOOLEAN OPERATIONS
     // --------------------------------------------------------------------------------------------
-    fn op_add(&mut self) {
-        assert!(self.depth >= 2, "stack underflow at step {}", self.step);
+    fn op_add(&mut self) -> Result<(), ExecutionError> {
+        self.check_depth(2, OpCode::Add)?;
         let x = self.registers[0][self.step - 1];
         let y = self.registers[1][self.step - 1];
         self.registers[0][self.step] = field::add(x, y);
         self.shift_left(2, 1);
+        return Ok(());
     }
 
-    fn op_mul(&mut self) {
-        assert!(self.depth >= 2, "stack underflow at step {}", self.step);
+    fn op_mul(&mut self) -> Result<(), ExecutionError> {
+        self.check_depth(2, OpCode::Mul)?;
         let x = self.registers[0][self.step - 1];
         let y = self.registers[1][self.step - 1];
         self.registers[0][self.step] = field::mul(x, y);
         self.shift_left(2, 1);
+        return Ok(());
     }
 
-    fn op_inv(&mut self) {
-        assert!(self.depth >= 1, "stack underflow at step {}", self.step);
+    fn op_inv(&mut self) -> Result<(), ExecutionError> {
+        self.check_depth(1, OpCode::Inv)?;
         let x = self.registers[0][self.step - 1];
-        assert!(x != field::ZERO, "cannot compute INV of {} at step {}", field::ZERO, self.step);
+        if x == field::ZERO {
+            return Err(ExecutionError::ZeroInverse {
+                op: OpCode::Inv, step: self.step, stack: self.snapshot() });
+        }
         self.registers[0][self.step] = field::inv(x);
         self.copy_state(1);
+        return Ok(());
     }
 
-    fn op_neg(&mut self) {
-        assert!(self.depth >= 1, "stack underflow at step {}", self.step);
+    fn op_neg(&mut self) -> Result<(), ExecutionError> {
+        self.check_depth(1, OpCode::Neg)?;
         let x = self.registers[0][self.step - 1];
         self.registers[0][self.step] = field::neg(x);
         self.copy_state(1);
+        return Ok(());
     }
 
-    fn op_not(&mut self) {
-        assert!(self.depth >= 1, "stack underflow at step {}", self.step);
+    fn op_not(&mut self) -> Result<(), ExecutionError> {
+        self.check_depth(1, OpCode::Not)?;
         let x = self.registers[0][self.step - 1];
-        assert!(is_binary(x), "cannot compute NOT of a non-binary value at step {}", self.step);
+        if !is_binary(x) {
+            return Err(self.non_binary_value(OpCode::Not));
+        }
         self.registers[0][self.step] = field::sub(field::ONE, x);
         self.copy_state(1);
+        return Ok(());
     }
 
-    fn op_and(&mut self) {
-        assert!(self.depth >= 2, "stack underflow at step {}", self.step);
+    fn op_and(&mut self) -> Result<(), ExecutionError> {
+        self.check_depth(2, OpCode::And)?;
         let x = self.registers[0][self.step - 1];
         let y = self.registers[1][self.step - 1];
-        assert!(is_binary(x), "cannot compute AND for a non-binary value at step {}", self.step);
-        assert!(is_binary(y), "cannot compute AND for a non-binary value at step {}", self.step);
+        if !is_binary(x) || !is_binary(y) {
+            return Err(self.non_binary_value(OpCode::And));
+        }
 
         self.registers[0][self.step] = if x == field::ONE && y == field::ONE { field::ONE } else { field::ZERO };
         self.shift_left(2, 1);
+        return Ok(());
     }
 
-    fn op_or(&mut self) {
-        assert!(self.depth >= 2, "stack underflow at step {}", self.step);
+    fn op_or(&mut self) -> Result<(), ExecutionError> {
+        self.check_depth(2, OpCode::Or)?;
         let x = self.registers[0][self.step - 1];
         let y = self.registers[1][self.step - 1];
-        assert!(is_binary(x), "cannot compute OR for a non-binary value at step {}", self.step);
-        assert!(is_binary(y), "cannot compute OR for a non-binary value at step {}", self.step);
+        if !is_binary(x) || !is_binary(y) {
+            return Err(self.non_binary_value(OpCode::Or));
+        }
 
         self.registers[0][self.step] = if x == field::ONE || y == field::ONE { field::ONE } else { field::ZERO };
         self.shift_left(2, 1);
+        return Ok(());
     }
 
 
     // COMPARISON OPERATIONS
     // --------------------------------------------------------------------------------------------
-    fn op_eq(&mut self) {
-        assert!(self.depth >= 3, "stack underflow at step {}", self.step);
+    fn op_eq(&mut self) -> Result<(), ExecutionError> {
+        self.check_depth(3, OpCode::Eq)?;
         let aux = self.registers[0][self.step - 1];
         let x = self.registers[1][self.step - 1];
         let y = self.registers[2][self.step - 1];
@@ -414,19 +487,23 @@ impl Stack {
             self.registers[0][self.step] = field::ONE;
         } else {
             let diff = field::sub(x, y);
-            assert!(aux == field::inv(diff), "invalid AUX value for EQ operation at step {}", self.step);
+            if aux != field::inv(diff) {
+                return Err(ExecutionError::InvalidOperand {
+                    op: OpCode::Eq, step: self.step, stack: self.snapshot() });
+            }
             self.registers[0][self.step] = field::ZERO;
         }
         self.shift_left(3, 2);
+        return Ok(());
     }
 
-    fn op_cmp(&mut self, hint: OpHint) {
+    fn op_cmp(&mut self, hint: OpHint) -> Result<(), ExecutionError> {
         // process execution hint
         match hint {
             OpHint::CmpStart(n) => {
                 // if we are about to start comparison sequence, push binary decompositions
                 // of a and b values onto the tapes
-                assert!(self.depth >= 10, "stack underflow at step {}", self.step);
+                self.check_depth(10, OpCode::Cmp)?;
                 let a_val = self.registers[8][self.step - 1];
                 let b_val = self.registers[9][self.step - 1];
                 for i in 0..n {
@@ -435,19 +512,18 @@ impl Stack {
                 }
             },
             _ => {
-                assert!(self.depth >= 8, "stack underflow at step {}", self.step);
-                assert!(self.tape_a.len() > 0, "attempt to read from empty tape A at step {}", self.step);
-                assert!(self.tape_b.len() > 0, "attempt to read from empty tape B at step {}", self.step);
+                self.check_depth(8, OpCode::Cmp)?;
+                self.check_tape('A', OpCode::Cmp)?;
+                self.check_tape('B', OpCode::Cmp)?;
             }
         }
 
         // get next bits of a and b values from the tapes
         let a_bit = self.tape_a.pop().unwrap();
-        assert!(a_bit == field::ZERO || a_bit == field::ONE,
-            "expected binary input at step {} but received: {}", self.step, a_bit);
         let b_bit = self.tape_b.pop().unwrap();
-        assert!(b_bit == field::ZERO || b_bit == field::ONE,
-            "expected binary input at step {} but received: {}", self.step, b_bit);
+        if !is_binary(a_bit) || !is_binary(b_bit) {
+            return Err(self.non_binary_value(OpCode::Cmp));
+        }
 
         // determine which bit is greater
         let bit_gt = field::mul(a_bit, field::sub(field::ONE, b_bit));
@@ -455,8 +531,10 @@ impl Stack {
 
         // compute current power of 2 for binary decomposition
         let power_of_two = self.registers[0][self.step - 1];
-        assert!(power_of_two.is_power_of_two(),
-            "expected top of the stack at step {} to be a power of 2, but received {}", self.step, power_of_two);
+        if !power_of_two.is_power_of_two() {
+            return Err(ExecutionError::InvalidOperand {
+                op: OpCode::Cmp, step: self.step, stack: self.snapshot() });
+        }
         let next_power_of_two = if power_of_two == 1 {
             field::div(power_of_two, 2)
         }
@@ -480,35 +558,39 @@ impl Stack {
         self.registers[7][self.step] = field::add(self.registers[7][self.step - 1], field::mul(a_bit, power_of_two));
 
         self.copy_state(8);
+        return Ok(());
     }
 
-    fn op_binacc(&mut self, hint: OpHint) {
+    fn op_binacc(&mut self, hint: OpHint) -> Result<(), ExecutionError> {
         // process execution hint
         match hint {
             OpHint::RcStart(n) => {
                 // if we are about to start range check sequence, push binary decompositions
                 // of the value onto tape A
-                assert!(self.depth >= 4, "stack underflow at step {}", self.step);
+                self.check_depth(4, OpCode::BinAcc)?;
                 let val = self.registers[3][self.step - 1];
                 for i in 0..n {
                     self.tape_a.push((val >> i) & 1);
                 }
             },
             _ => {
-                assert!(self.depth >= 3, "stack underflow at step {}", self.step);
-                assert!(self.tape_a.len() > 0, "attempt to read from empty tape A at step {}", self.step);
+                self.check_depth(3, OpCode::BinAcc)?;
+                self.check_tape('A', OpCode::BinAcc)?;
             }
         }
 
         // get the next bit of the value from tape A
         let bit = self.tape_a.pop().unwrap();
-        assert!(bit == field::ZERO || bit == field::ONE,
-            "expected binary input at step {} but received: {}", self.step, bit);
+        if !is_binary(bit) {
+            return Err(self.non_binary_value(OpCode::BinAcc));
+        }
 
         // compute current power of 2 for binary decomposition
         let power_of_two = self.registers[0][self.step - 1];
-        assert!(power_of_two.is_power_of_two(),
-            "expected top of the stack at step {} to be a power of 2, but received {}", self.step, power_of_two);
+        if !power_of_two.is_power_of_two() {
+            return Err(ExecutionError::InvalidOperand {
+                op: OpCode::BinAcc, step: self.step, stack: self.snapshot() });
+        }
         let next_power_of_two = if power_of_two == 1 {
                 field::div(power_of_two, 2)
             }
@@ -524,12 +606,13 @@ impl Stack {
         self.registers[2][self.step] = field::add(acc, field::mul(bit, power_of_two));
 
         self.copy_state(3);
+        return Ok(());
     }
 
     // CRYPTOGRAPHIC OPERATIONS
     // --------------------------------------------------------------------------------------------
-    fn op_rescr(&mut self) {
-        assert!(self.depth >= HASH_STATE_WIDTH, "stack underflow at step {}", self.step);
+    fn op_rescr(&mut self) -> Result<(), ExecutionError> {
+        self.check_depth(HASH_STATE_WIDTH, OpCode::RescR)?;
         let mut state = [
             self.registers[0][self.step - 1],
             self.registers[1][self.step - 1],
@@ -549,6 +632,7 @@ impl Stack {
         self.registers[5][self.step] = state[5];
 
         self.copy_state(HASH_STATE_WIDTH);
+        return Ok(());
     }
 
     // HELPER METHODS
@@ -577,10 +661,11 @@ impl Stack {
         self.depth -= pos_count;
     }
 
-    fn shift_right(&mut self, start: usize, pos_count: usize) {
-        
+    fn shift_right(&mut self, start: usize, pos_count: usize, op: OpCode) -> Result<(), ExecutionError> {
+        if self.depth + pos_count > MAX_STACK_DEPTH {
+            return Err(ExecutionError::StackOverflow { op, step: self.step, stack: self.snapshot() });
+        }
         self.depth += pos_count;
-        assert!(self.depth <= MAX_STACK_DEPTH, "stack overflow at step {}", self.step);
 
         if self.depth > self.max_depth {
             self.max_depth += pos_count;
@@ -592,6 +677,40 @@ impl Stack {
         for i in start..(self.depth - pos_count) {
             self.registers[i + pos_count][self.step] = self.registers[i][self.step - 1];
         }
+        return Ok(());
+    }
+
+    /// Returns an error if the stack has fewer than `depth` items.
+    fn check_depth(&self, depth: usize, op: OpCode) -> Result<(), ExecutionError> {
+        if self.depth < depth {
+            return Err(ExecutionError::StackUnderflow { op, step: self.step, stack: self.snapshot() });
+        }
+        return Ok(());
+    }
+
+    /// Returns an error if the specified secret input tape is empty.
+    fn check_tape(&self, tape: char, op: OpCode) -> Result<(), ExecutionError> {
+        let is_empty = match tape {
+            'A' => self.tape_a.is_empty(),
+            _   => self.tape_b.is_empty(),
+        };
+        if is_empty {
+            return Err(ExecutionError::EmptyInputTape { op, step: self.step, stack: self.snapshot(), tape });
+        }
+        return Ok(());
+    }
+
+    fn failed_assertion(&self, op: OpCode) -> ExecutionError {
+        return ExecutionError::FailedAssertion { op, step: self.step, stack: self.snapshot() };
+    }
+
+    fn non_binary_value(&self, op: OpCode) -> ExecutionError {
+        return ExecutionError::NonBinaryValue { op, step: self.step, stack: self.snapshot() };
+    }
+
+    /// Returns the state of the stack before the current step was executed.
+    fn snapshot(&self) -> Vec<u128> {
+        return (0..self.depth).map(|i| self.registers[i][self.step - 1]).collect();
     }
 
     /// Extends the stack by the specified number of registers.
@@ -609,6 +728,7 @@ impl Stack {
         if self.step >= self.trace_length() {
             let new_length = self.trace_length() * 2;
             for register in self.registers.iter_mut() { register.resize(new_length, field::ZERO); }
+            self.depth_trace.resize(new_length, field::ZERO);
         }
     }
 }
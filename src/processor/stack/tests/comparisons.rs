@@ -10,15 +10,15 @@ fn eq() {
     let inv_diff = field::inv(field::sub(1, 4));
     let mut stack = init_stack(&[3, 3, 4, 5], &[0, inv_diff], &[], TRACE_LENGTH);
 
-    stack.execute(OpCode::Read, OpHint::None);
-    stack.execute(OpCode::Eq, OpHint::None);
+    stack.execute(OpCode::Read, OpHint::None).unwrap();
+    stack.execute(OpCode::Eq, OpHint::None).unwrap();
     assert_eq!(vec![1, 4, 5, 0, 0, 0, 0, 0], get_stack_state(&stack, 2));
 
     assert_eq!(3, stack.depth);
     assert_eq!(5, stack.max_depth);
 
-    stack.execute(OpCode::Read, OpHint::None);
-    stack.execute(OpCode::Eq, OpHint::None);
+    stack.execute(OpCode::Read, OpHint::None).unwrap();
+    stack.execute(OpCode::Eq, OpHint::None).unwrap();
     assert_eq!(vec![0, 5, 0, 0, 0, 0, 0, 0], get_stack_state(&stack, 4));
 
     assert_eq!(2, stack.depth);
@@ -29,15 +29,15 @@ fn eq() {
 fn eq_with_hint() {
     let mut stack = init_stack(&[3, 3, 4, 5], &[], &[], TRACE_LENGTH);
 
-    stack.execute(OpCode::Read, OpHint::EqStart);
-    stack.execute(OpCode::Eq, OpHint::None);
+    stack.execute(OpCode::Read, OpHint::EqStart).unwrap();
+    stack.execute(OpCode::Eq, OpHint::None).unwrap();
     assert_eq!(vec![1, 4, 5, 0, 0, 0, 0, 0], get_stack_state(&stack, 2));
 
     assert_eq!(3, stack.depth);
     assert_eq!(5, stack.max_depth);
 
-    stack.execute(OpCode::Read, OpHint::EqStart);
-    stack.execute(OpCode::Eq, OpHint::None);
+    stack.execute(OpCode::Read, OpHint::EqStart).unwrap();
+    stack.execute(OpCode::Eq, OpHint::None).unwrap();
     assert_eq!(vec![0, 5, 0, 0, 0, 0, 0, 0], get_stack_state(&stack, 4));
 
     assert_eq!(2, stack.depth);
@@ -57,12 +57,12 @@ fn cmp_128() {
     // initialize the stack
     let (inputs_a, inputs_b) = build_inputs_for_cmp(a, b, 128);
     let mut stack = init_stack(&[0, 0, 0, 0, 0, a, b], &inputs_a, &inputs_b, 256);
-    stack.execute(OpCode::Pad2, OpHint::None);
-    stack.execute(OpCode::Push, OpHint::PushValue(p127));
+    stack.execute(OpCode::Pad2, OpHint::None).unwrap();
+    stack.execute(OpCode::Push, OpHint::PushValue(p127)).unwrap();
 
     // execute CMP operations
     for i in 2..130 {
-        stack.execute(OpCode::Cmp, OpHint::None);
+        stack.execute(OpCode::Cmp, OpHint::None).unwrap();
 
         let state = get_stack_state(&stack, i);
         let next  = get_stack_state(&stack, i + 1);
@@ -91,12 +91,12 @@ fn cmp_64() {
     // initialize the stack
     let (inputs_a, inputs_b) = build_inputs_for_cmp(a, b, 64);
     let mut stack = init_stack(&[0, 0, 0, 0, 0, a, b], &inputs_a, &inputs_b, 256);
-    stack.execute(OpCode::Pad2, OpHint::None);
-    stack.execute(OpCode::Push, OpHint::PushValue(p63));
+    stack.execute(OpCode::Pad2, OpHint::None).unwrap();
+    stack.execute(OpCode::Push, OpHint::PushValue(p63)).unwrap();
 
     // execute CMP operations
     for i in 2..66 {
-        stack.execute(OpCode::Cmp, OpHint::None);
+        stack.execute(OpCode::Cmp, OpHint::None).unwrap();
 
         let state = get_stack_state(&stack, i);
         let next  = get_stack_state(&stack, i + 1);
@@ -128,12 +128,12 @@ fn lt() {
     // initialize the stack
     let (inputs_a, inputs_b) = build_inputs_for_cmp(a, b, 128);
     let mut stack = init_stack(&[0, 0, 0, a, b, 7, 11], &inputs_a, &inputs_b, 256);
-    stack.execute(OpCode::Pad2, OpHint::None);
-    stack.execute(OpCode::Pad2, OpHint::None);
-    stack.execute(OpCode::Push, OpHint::PushValue(p127));
+    stack.execute(OpCode::Pad2, OpHint::None).unwrap();
+    stack.execute(OpCode::Pad2, OpHint::None).unwrap();
+    stack.execute(OpCode::Push, OpHint::PushValue(p127)).unwrap();
 
     // execute CMP operations
-    for _ in 3..131 { stack.execute(OpCode::Cmp, OpHint::None); }
+    for _ in 3..131 { stack.execute(OpCode::Cmp, OpHint::None).unwrap(); }
 
     // execute program finale
     lt_finale(&mut stack);
@@ -154,12 +154,12 @@ fn gt() {
     // initialize the stack
     let (inputs_a, inputs_b) = build_inputs_for_cmp(a, b, 128);
     let mut stack = init_stack(&[0, 0, 0, a, b, 7, 11], &inputs_a, &inputs_b, 256);
-    stack.execute(OpCode::Pad2, OpHint::None);
-    stack.execute(OpCode::Pad2, OpHint::None);
-    stack.execute(OpCode::Push, OpHint::PushValue(p127));
+    stack.execute(OpCode::Pad2, OpHint::None).unwrap();
+    stack.execute(OpCode::Pad2, OpHint::None).unwrap();
+    stack.execute(OpCode::Push, OpHint::PushValue(p127)).unwrap();
 
     // execute CMP operations
-    for _ in 3..131 { stack.execute(OpCode::Cmp, OpHint::None); }
+    for _ in 3..131 { stack.execute(OpCode::Cmp, OpHint::None).unwrap(); }
 
     // execute program finale
     gt_finale(&mut stack);
@@ -187,11 +187,11 @@ fn binacc_128() {
     let mut stack = init_stack(&[p127, 0, 0, x, 7, 11], &inputs_a, &[], 256);
 
     // execute binary aggregation operations
-    for _ in 0..128 { stack.execute(OpCode::BinAcc, OpHint::None); }
+    for _ in 0..128 { stack.execute(OpCode::BinAcc, OpHint::None).unwrap(); }
 
     // check the result
-    stack.execute(OpCode::Drop, OpHint::None);
-    stack.execute(OpCode::Drop, OpHint::None);
+    stack.execute(OpCode::Drop, OpHint::None).unwrap();
+    stack.execute(OpCode::Drop, OpHint::None).unwrap();
     let state = get_stack_state(&stack, 130);
     assert_eq!(vec![x, x, 7, 11, 0, 0, 0, 0], state);
 }
@@ -210,11 +210,11 @@ fn binacc_64() {
     let mut stack = init_stack(&[p127, 0, 0, x, 7, 11], &inputs_a, &[], 256);
 
     // execute binary aggregation operations
-    for _ in 0..64 { stack.execute(OpCode::BinAcc, OpHint::None); }
+    for _ in 0..64 { stack.execute(OpCode::BinAcc, OpHint::None).unwrap(); }
 
     // check the result
-    stack.execute(OpCode::Drop, OpHint::None);
-    stack.execute(OpCode::Drop, OpHint::None);
+    stack.execute(OpCode::Drop, OpHint::None).unwrap();
+    stack.execute(OpCode::Drop, OpHint::None).unwrap();
     let state = get_stack_state(&stack, 66);
     assert_eq!(vec![x, x, 7, 11, 0, 0, 0, 0], state);
 }
@@ -234,12 +234,12 @@ fn isodd_128() {
     let mut stack = init_stack(&[p127, 0, 0, x, 7, 11], &inputs_a, &[], 256);
 
     // execute binary aggregation operations
-    for _ in 0..128 { stack.execute(OpCode::BinAcc, OpHint::None); }
+    for _ in 0..128 { stack.execute(OpCode::BinAcc, OpHint::None).unwrap(); }
 
     // check the result
-    stack.execute(OpCode::Swap2, OpHint::None);
-    stack.execute(OpCode::AssertEq, OpHint::None);
-    stack.execute(OpCode::Drop, OpHint::None);
+    stack.execute(OpCode::Swap2, OpHint::None).unwrap();
+    stack.execute(OpCode::AssertEq, OpHint::None).unwrap();
+    stack.execute(OpCode::Drop, OpHint::None).unwrap();
     let state = get_stack_state(&stack, 131);
     assert_eq!(vec![is_odd, 7, 11, 0, 0, 0, 0, 0], state);
 }
@@ -261,24 +261,24 @@ fn build_inputs_for_cmp(a: u128, b: u128, size: usize) -> (Vec<u128>, Vec<u128>)
 }
 
 fn lt_finale(stack: &mut Stack) {
-    stack.execute(OpCode::Drop4, OpHint::None);
-    stack.execute(OpCode::Pad2, OpHint::None);
-    stack.execute(OpCode::Swap4, OpHint::None);
-    stack.execute(OpCode::Roll4, OpHint::None);
-    stack.execute(OpCode::AssertEq, OpHint::None);
-    stack.execute(OpCode::AssertEq, OpHint::None);
-    stack.execute(OpCode::Dup, OpHint::None);
-    stack.execute(OpCode::Drop4, OpHint::None);
+    stack.execute(OpCode::Drop4, OpHint::None).unwrap();
+    stack.execute(OpCode::Pad2, OpHint::None).unwrap();
+    stack.execute(OpCode::Swap4, OpHint::None).unwrap();
+    stack.execute(OpCode::Roll4, OpHint::None).unwrap();
+    stack.execute(OpCode::AssertEq, OpHint::None).unwrap();
+    stack.execute(OpCode::AssertEq, OpHint::None).unwrap();
+    stack.execute(OpCode::Dup, OpHint::None).unwrap();
+    stack.execute(OpCode::Drop4, OpHint::None).unwrap();
 }
 
 fn gt_finale(stack: &mut Stack) {
-    stack.execute(OpCode::Drop4, OpHint::None);
-    stack.execute(OpCode::Pad2, OpHint::None);
-    stack.execute(OpCode::Swap4, OpHint::None);
-    stack.execute(OpCode::Roll4, OpHint::None);
-    stack.execute(OpCode::AssertEq, OpHint::None);
-    stack.execute(OpCode::AssertEq, OpHint::None);
-    stack.execute(OpCode::Roll4, OpHint::None);
-    stack.execute(OpCode::Dup, OpHint::None);
-    stack.execute(OpCode::Drop4, OpHint::None);
+    stack.execute(OpCode::Drop4, OpHint::None).unwrap();
+    stack.execute(OpCode::Pad2, OpHint::None).unwrap();
+    stack.execute(OpCode::Swap4, OpHint::None).unwrap();
+    stack.execute(OpCode::Roll4, OpHint::None).unwrap();
+    stack.execute(OpCode::AssertEq, OpHint::None).unwrap();
+    stack.execute(OpCode::AssertEq, OpHint::None).unwrap();
+    stack.execute(OpCode::Roll4, OpHint::None).unwrap();
+    stack.execute(OpCode::Dup, OpHint::None).unwrap();
+    stack.execute(OpCode::Drop4, OpHint::None).unwrap();
 }
\ No newline at end of file
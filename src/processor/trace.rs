@@ -0,0 +1,202 @@
+use serde::{ Serialize, Deserialize };
+use crate::{
+    stark::TraceState, utils::{ as_bytes, find_duplicate },
+    MAX_CONTEXT_DEPTH, MAX_LOOP_DEPTH, MAX_STACK_DEPTH, MAX_PUBLIC_INPUTS, MIN_TRACE_LENGTH,
+};
+use super::ExecutionError;
+
+// TYPES AND INTERFACES
+// ================================================================================================
+
+/// Execution trace of a program together with all public data needed to generate a proof of
+/// the program's execution. A trace can be stored or sent elsewhere, and later passed to
+/// `prove()` to generate a proof with any set of `ProofOptions`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExecutionTrace {
+    registers       : Vec<Vec<u128>>,
+    ctx_depth       : usize,
+    loop_depth      : usize,
+    program_hash    : [u8; 32],
+    inputs          : Vec<u128>,
+    outputs         : Vec<u128>,
+    output_positions: Vec<usize>,
+    stack_size      : Option<usize>,
+}
+
+// EXECUTION TRACE IMPLEMENTATION
+// ================================================================================================
+impl ExecutionTrace {
+
+    pub fn new(
+        registers       : Vec<Vec<u128>>,
+        ctx_depth       : usize,
+        loop_depth      : usize,
+        program_hash    : &[u8; 32],
+        inputs          : &[u128],
+        outputs         : &[u128],
+        output_positions: &[usize],
+        stack_size      : Option<usize>) -> ExecutionTrace
+    {
+        return ExecutionTrace {
+            registers,
+            ctx_depth,
+            loop_depth,
+            program_hash    : *program_hash,
+            inputs          : inputs.to_vec(),
+            outputs         : outputs.to_vec(),
+            output_positions: output_positions.to_vec(),
+            stack_size,
+        };
+    }
+
+    /// Returns register traces of the execution trace; decoder registers come first, followed
+    /// by the stack size register and user stack registers.
+    pub fn registers(&self) -> &[Vec<u128>] {
+        return &self.registers;
+    }
+
+    /// Returns the number of registers in the execution trace.
+    pub fn register_count(&self) -> usize {
+        return self.registers.len();
+    }
+
+    /// Returns the number of steps in the execution trace.
+    pub fn trace_length(&self) -> usize {
+        return self.registers[0].len();
+    }
+
+    /// Returns maximum depth of the context stack reached during execution.
+    pub fn ctx_depth(&self) -> usize {
+        return self.ctx_depth;
+    }
+
+    /// Returns maximum depth of the loop stack reached during execution.
+    pub fn loop_depth(&self) -> usize {
+        return self.loop_depth;
+    }
+
+    /// Returns hash of the program which was executed to produce this trace.
+    pub fn program_hash(&self) -> &[u8; 32] {
+        return &self.program_hash;
+    }
+
+    /// Returns public inputs with which the program was executed.
+    pub fn inputs(&self) -> &[u128] {
+        return &self.inputs;
+    }
+
+    /// Returns outputs produced by the program.
+    pub fn outputs(&self) -> &[u128] {
+        return &self.outputs;
+    }
+
+    /// Returns stack positions from which the outputs were taken; the top of the stack is at
+    /// position 0.
+    pub fn output_positions(&self) -> &[usize] {
+        return &self.output_positions;
+    }
+
+    /// Returns the number of items on the stack at the end of execution if the outputs claim
+    /// it (i.e. if the outputs were selected with `OutputSpec::Stack`).
+    pub fn stack_size(&self) -> Option<usize> {
+        return self.stack_size;
+    }
+
+    /// Makes sure that the execution trace is well-formed and consistent with its public data:
+    /// register count and depths must be within the limits of the VM, all registers must have
+    /// the same power-of-two length, and inputs, outputs, and program hash must match values
+    /// of the registers. Traces produced by `generate_trace()` are always valid, but traces
+    /// constructed or deserialized elsewhere should be validated before they are proven.
+    pub fn validate(&self) -> Result<(), ExecutionError> {
+        if self.ctx_depth > MAX_CONTEXT_DEPTH {
+            return Err(malformed(format!("context depth {} is greater than {}",
+                self.ctx_depth, MAX_CONTEXT_DEPTH)));
+        }
+        if self.loop_depth > MAX_LOOP_DEPTH {
+            return Err(malformed(format!("loop depth {} is greater than {}",
+                self.loop_depth, MAX_LOOP_DEPTH)));
+        }
+
+        // make sure the number of registers matches the depths
+        let user_stack_offset = TraceState::compute_user_stack_offset(self.ctx_depth, self.loop_depth);
+        if self.registers.len() <= user_stack_offset {
+            return Err(malformed(format!("trace of {} registers has no user stack registers; \
+                decoder and stack size require {} registers", self.registers.len(), user_stack_offset)));
+        }
+        let stack_depth = self.registers.len() - user_stack_offset;
+        if stack_depth > MAX_STACK_DEPTH {
+            return Err(malformed(format!("stack depth {} is greater than {}", stack_depth, MAX_STACK_DEPTH)));
+        }
+
+        // make sure all registers have the same valid length
+        let trace_length = self.registers[0].len();
+        if !trace_length.is_power_of_two() || trace_length < MIN_TRACE_LENGTH {
+            return Err(malformed(format!("trace length {} is not a power of 2 greater than or equal to {}",
+                trace_length, MIN_TRACE_LENGTH)));
+        }
+        if let Some(i) = self.registers.iter().position(|r| r.len() != trace_length) {
+            return Err(malformed(format!("register {} has {} steps, but register 0 has {} steps",
+                i, self.registers[i].len(), trace_length)));
+        }
+
+        // make sure inputs are in the user stack registers at the first step
+        let stack_size = &self.registers[user_stack_offset - 1];
+        let user_stack = &self.registers[user_stack_offset..];
+        if self.inputs.len() > std::cmp::min(stack_depth, MAX_PUBLIC_INPUTS) {
+            return Err(malformed(format!("{} inputs do not fit into user stack of depth {}",
+                self.inputs.len(), stack_depth)));
+        }
+        if let Some(i) = (0..self.inputs.len()).find(|&i| user_stack[i][0] != self.inputs[i]) {
+            return Err(malformed(format!("input {} does not match stack register {} at the first step", i, i)));
+        }
+        if stack_size[0] != self.inputs.len() as u128 {
+            return Err(malformed(format!("stack size at the first step is not equal to the number of inputs {}",
+                self.inputs.len())));
+        }
+
+        // make sure outputs are in the user stack registers at the last step
+        if self.outputs.len() != self.output_positions.len() {
+            return Err(malformed(format!("{} outputs do not match {} output positions",
+                self.outputs.len(), self.output_positions.len())));
+        }
+        for (i, &position) in self.output_positions.iter().enumerate() {
+            if position >= stack_depth {
+                return Err(malformed(format!("output position {} is outside of user stack of depth {}",
+                    position, stack_depth)));
+            }
+            if user_stack[position][trace_length - 1] != self.outputs[i] {
+                return Err(malformed(format!("output {} does not match stack register {} at the last step",
+                    i, position)));
+            }
+        }
+        if let Some(position) = find_duplicate(&self.output_positions) {
+            return Err(malformed(format!("output position {} is used more than once", position)));
+        }
+        if let Some(size) = self.stack_size {
+            if stack_size[trace_length - 1] != size as u128 {
+                return Err(malformed(format!("stack size at the last step is not equal to the claimed size {}",
+                    size)));
+            }
+        }
+
+        // make sure the program hash is the hash computed by the decoder
+        let mut last_state = TraceState::new(self.ctx_depth, self.loop_depth, stack_depth);
+        last_state.update_from_trace(&self.registers, trace_length - 1);
+        if as_bytes(last_state.program_hash()) != &self.program_hash[..] {
+            return Err(malformed(String::from("program hash does not match the hash computed by the decoder")));
+        }
+
+        return Ok(());
+    }
+
+    /// Consumes the execution trace and returns its register traces.
+    pub fn into_registers(self) -> Vec<Vec<u128>> {
+        return self.registers;
+    }
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+fn malformed(reason: String) -> ExecutionError {
+    return ExecutionError::MalformedTrace(reason);
+}
@@ -1,9 +1,22 @@
+use super::lexer::Token;
+
 // TYPES AND INTERFACES
 // ================================================================================================
 pub struct AssemblyError {
     message : String,
     step    : usize,
-    op      : String
+    op      : String,
+    location: Option<SourceLocation>,
+}
+
+/// Location of the instruction which caused an assembly error; lines and columns are 1-based.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SourceLocation {
+    module      : Option<String>,
+    line        : usize,
+    column      : usize,
+    length      : usize,
+    source_line : String,
 }
 
 // ASSEMBLY ERROR IMPLEMENTATION
@@ -13,150 +26,346 @@ impl AssemblyError {
     // CONSTRUCTORS
     // --------------------------------------------------------------------------------------------
 
-    pub fn empty_program() -> AssemblyError {
+    pub(super) fn empty_program() -> AssemblyError {
         return AssemblyError {
             message : String::from("a program must contain at least one instruction"),
             step    : 0,
             op      : String::from("begin"),
+            location: None,
         };
     }
 
-    pub fn empty_block(op: &[&str], step: usize) -> AssemblyError {
+    pub(super) fn empty_block(op: &[&str], step: usize) -> AssemblyError {
         return AssemblyError {
             message : String::from("a program block must contain at least one instruction"),
             step    : step,
             op      : op.join("."),
+            location: None,
         };
     }
 
-    pub fn invalid_program_start(op: &str) -> AssemblyError {
+    pub(super) fn invalid_program_start(op: &str, step: usize) -> AssemblyError {
         return AssemblyError {
-            message : String::from("a program must start with a 'being' instruction"),
-            step    : 0,
+            message : String::from("a program must start with a 'begin' instruction"),
+            step    : step,
             op      : String::from(op),
+            location: None,
         };
     }
 
-    pub fn invalid_program_end(op: &str) -> AssemblyError {
+    pub(super) fn missing_program_body(step: usize) -> AssemblyError {
+        return AssemblyError {
+            message : String::from("constant and procedure definitions must be followed by a 'begin' instruction"),
+            step    : step,
+            op      : String::from("end"),
+            location: None,
+        };
+    }
+
+    pub(super) fn invalid_program_end(op: &str, step: usize) -> AssemblyError {
         return AssemblyError {
             message : String::from("a program must end with an 'end' instruction"),
-            step    : 0,
+            step    : step,
             op      : String::from(op),
+            location: None,
         };
     }
 
-    pub fn dangling_instructions(step: usize) -> AssemblyError {
+    pub(super) fn dangling_instructions(step: usize) -> AssemblyError {
         return AssemblyError {
             message : format!("dangling instructions after program end"),
             step    : step,
             op      : String::from("end"),
+            location: None,
         };
     }
 
-    pub fn invalid_op(op: &[&str], step: usize) -> AssemblyError {
+    pub(super) fn invalid_op(op: &[&str], step: usize) -> AssemblyError {
         return AssemblyError {
             message : format!("instruction {} is invalid", op.join(".")),
             step    : step,
             op      : op.join("."),
+            location: None,
         };
     }
 
-    pub fn missing_param(op: &[&str], step: usize) -> AssemblyError {
+    pub(super) fn missing_param(op: &[&str], step: usize) -> AssemblyError {
         return AssemblyError {
             message : format!("malformed instruction {}: parameter is missing", op[0]),
             step    : step,
             op      : op.join("."),
+            location: None,
         };
     }
 
-    pub fn extra_param(op: &[&str], step: usize) -> AssemblyError {
+    pub(super) fn extra_param(op: &[&str], step: usize) -> AssemblyError {
         return AssemblyError {
             message : format!("malformed instruction {}: too many parameters provided", op[0]),
             step    : step,
             op      : op.join("."),
+            location: None,
         };
     }
 
-    pub fn invalid_param(op: &[&str], step: usize) -> AssemblyError {
+    pub(super) fn invalid_param(op: &[&str], step: usize) -> AssemblyError {
         return AssemblyError {
             message : format!("malformed instruction {}: parameter '{}' is invalid", op[0], op[1]),
             step    : step,
             op      : op.join("."),
+            location: None,
         };
     }
 
-    pub fn invalid_param_reason(op: &[&str], step: usize, reason: String) -> AssemblyError {
+    pub(super) fn invalid_param_reason(op: &[&str], step: usize, reason: String) -> AssemblyError {
         return AssemblyError {
             message : format!("malformed instruction {}: {}", op[0], reason),
             step    : step,
             op      : op.join("."),
+            location: None,
         };
     }
 
-    pub fn invalid_block_head(op: &[&str], step: usize) -> AssemblyError {
+    pub(super) fn invalid_block_head(op: &[&str], step: usize) -> AssemblyError {
         return AssemblyError {
             message : format!("invalid block head '{}'", op.join(".")),
             step    : step,
             op      : op.join("."),
+            location: None,
         };
     }
 
-    pub fn invalid_num_iterations(op: &[&str], step: usize) -> AssemblyError {
+    pub(super) fn invalid_num_iterations(op: &[&str], step: usize) -> AssemblyError {
         return AssemblyError {
             message : format!("invalid repeat statement '{}': 2 or more iterations must be specified", op.join(".")),
             step    : step,
             op      : op.join("."),
+            location: None,
         };
     }
 
-    pub fn dangling_else(step: usize) -> AssemblyError {
+    pub(super) fn dangling_else(step: usize) -> AssemblyError {
         return AssemblyError {
             message : format!("else without matching if"),
             step    : step,
             op      : String::from("else"),
+            location: None,
         };
     }
 
-    pub fn unmatched_block(step: usize) -> AssemblyError {
+    pub(super) fn unmatched_block(step: usize) -> AssemblyError {
         return AssemblyError {
             message : format!("block without matching end"),
             step    : step,
             op      : String::from("block"),
+            location: None,
         };
     }
 
-    pub fn unmatched_if(step: usize) -> AssemblyError {
+    pub(super) fn unmatched_if(step: usize) -> AssemblyError {
         return AssemblyError {
             message : format!("if without matching else/end"),
             step    : step,
             op      : String::from("if.true"),
+            location: None,
         };
     }
 
-    pub fn unmatched_while(step: usize) -> AssemblyError {
+    pub(super) fn unmatched_while(step: usize) -> AssemblyError {
         return AssemblyError {
             message : format!("while without matching end"),
             step    : step,
             op      : String::from("while.true"),
+            location: None,
         };
     }
 
-    pub fn unmatched_repeat(step: usize, op: &[&str]) -> AssemblyError {
+    pub(super) fn unmatched_repeat(step: usize, op: &[&str]) -> AssemblyError {
         return AssemblyError {
             message : format!("repeat without matching end"),
             step    : step,
             op      : op.join("."),
+            location: None,
         };
     }
 
-    pub fn unmatched_else(step: usize) -> AssemblyError {
+    pub(super) fn unmatched_else(step: usize) -> AssemblyError {
         return AssemblyError {
             message : format!("else without matching end"),
             step    : step,
             op      : String::from("else"),
+            location: None,
         };
     }
 
+    pub(super) fn unmatched_proc(step: usize, op: &[&str]) -> AssemblyError {
+        return AssemblyError {
+            message : format!("proc without matching end"),
+            step    : step,
+            op      : op.join("."),
+            location: None,
+        };
+    }
+
+    pub(super) fn invalid_proc_declaration(op: &[&str], step: usize) -> AssemblyError {
+        return AssemblyError {
+            message : format!("invalid procedure declaration '{}'", op.join(".")),
+            step    : step,
+            op      : op.join("."),
+            location: None,
+        };
+    }
+
+    pub(super) fn duplicate_proc(name: &str, step: usize) -> AssemblyError {
+        return AssemblyError {
+            message : format!("procedure {} is already defined", name),
+            step    : step,
+            op      : format!("proc.{}", name),
+            location: None,
+        };
+    }
+
+    pub(super) fn misplaced_proc(step: usize) -> AssemblyError {
+        return AssemblyError {
+            message : format!("procedures can be defined only before the 'begin' instruction"),
+            step    : step,
+            op      : String::from("proc"),
+            location: None,
+        };
+    }
+
+    pub(super) fn undefined_proc(op: &[&str], step: usize) -> AssemblyError {
+        return AssemblyError {
+            message : format!("procedure {} is not defined; procedures must be defined before they are invoked", op[1]),
+            step    : step,
+            op      : op.join("."),
+            location: None,
+        };
+    }
+
+    pub(super) fn recursive_proc(op: &[&str], step: usize) -> AssemblyError {
+        return AssemblyError {
+            message : format!("procedure {} cannot invoke itself: recursive procedures are not supported", op[1]),
+            step    : step,
+            op      : op.join("."),
+            location: None,
+        };
+    }
+
+    pub(super) fn invalid_const_declaration(op: &[&str], step: usize, reason: String) -> AssemblyError {
+        return AssemblyError {
+            message : format!("invalid constant declaration '{}': {}", op.join("."), reason),
+            step    : step,
+            op      : op.join("."),
+            location: None,
+        };
+    }
+
+    pub(super) fn duplicate_const(name: &str, step: usize) -> AssemblyError {
+        return AssemblyError {
+            message : format!("constant {} is already defined", name),
+            step    : step,
+            op      : String::from("const"),
+            location: None,
+        };
+    }
+
+    pub(super) fn misplaced_const(step: usize) -> AssemblyError {
+        return AssemblyError {
+            message : format!("constants can be defined only before the 'begin' instruction"),
+            step    : step,
+            op      : String::from("const"),
+            location: None,
+        };
+    }
+
+    pub(super) fn invalid_use(op: &[&str], step: usize) -> AssemblyError {
+        return AssemblyError {
+            message : format!("invalid use statement '{}': module path must consist of names separated by ::", op.join(".")),
+            step    : step,
+            op      : op.join("."),
+            location: None,
+        };
+    }
+
+    pub(super) fn duplicate_module(op: &[&str], step: usize) -> AssemblyError {
+        return AssemblyError {
+            message : format!("a module named {} is already imported", op[1].rsplit("::").next().unwrap_or("")),
+            step    : step,
+            op      : op.join("."),
+            location: None,
+        };
+    }
+
+    pub(super) fn module_not_found(op: &[&str], step: usize) -> AssemblyError {
+        return AssemblyError {
+            message : format!("module {} could not be found", op[1]),
+            step    : step,
+            op      : op.join("."),
+            location: None,
+        };
+    }
+
+    pub(super) fn cyclic_import(op: &[&str], step: usize) -> AssemblyError {
+        return AssemblyError {
+            message : format!("module {} cannot be imported: cyclic imports are not supported", op[1]),
+            step    : step,
+            op      : op.join("."),
+            location: None,
+        };
+    }
+
+    pub(super) fn invalid_module_content(op: &str, step: usize) -> AssemblyError {
+        return AssemblyError {
+            message : format!("a module can contain only use statements, constants, and procedures"),
+            step    : step,
+            op      : String::from(op),
+            location: None,
+        };
+    }
+
+    pub(super) fn misplaced_use(step: usize) -> AssemblyError {
+        return AssemblyError {
+            message : format!("modules can be imported only before the 'begin' instruction"),
+            step    : step,
+            op      : String::from("use"),
+            location: None,
+        };
+    }
+
+    pub(super) fn unterminated_comment(step: usize) -> AssemblyError {
+        return AssemblyError {
+            message : format!("block comment without matching */"),
+            step    : step,
+            op      : String::from("/*"),
+            location: None,
+        };
+    }
+
+    /// Attaches the location of the `token` at which the error occurred to this error;
+    /// `source` is used to extract the line of code containing the token.
+    pub(super) fn with_location(mut self, source: &str, token: &Token) -> AssemblyError {
+        let source_line = source.lines().nth(token.line - 1).unwrap_or("");
+        self.location = Some(SourceLocation {
+            module      : None,
+            line        : token.line,
+            column      : token.column,
+            length      : token.text.chars().count(),
+            source_line : String::from(source_line),
+        });
+        return self;
+    }
+
+    /// Records that the location of this error refers to the source of the module at the
+    /// specified `path`; errors which already refer to a module are not changed.
+    pub(super) fn in_module(mut self, path: &str) -> AssemblyError {
+        if let Some(location) = &mut self.location {
+            if location.module.is_none() {
+                location.module = Some(String::from(path));
+            }
+        }
+        return self;
+    }
+
     // PUBLIC ACCESSORS
     // --------------------------------------------------------------------------------------------
     pub fn message(&self) -> &String {
@@ -167,23 +376,85 @@ impl AssemblyError {
         return &self.op;
     }
 
+    /// Returns the index of the instruction (among all instructions of the program) at which
+    /// the error occurred.
     pub fn step(&self) -> usize {
         return self.step;
     }
+
+    /// Returns location of the instruction at which the error occurred in the source code, or
+    /// None if the error does not refer to any specific instruction (e.g. empty program).
+    pub fn location(&self) -> Option<&SourceLocation> {
+        return self.location.as_ref();
+    }
+}
+
+// SOURCE LOCATION IMPLEMENTATION
+// ================================================================================================
+impl SourceLocation {
+
+    /// Returns the path of the imported module in which the error occurred, or None if the
+    /// error occurred in the program itself.
+    pub fn module(&self) -> Option<&str> {
+        return self.module.as_deref();
+    }
+
+    pub fn line(&self) -> usize {
+        return self.line;
+    }
+
+    pub fn column(&self) -> usize {
+        return self.column;
+    }
+
+    /// Returns the line of source code which contains the erroneous instruction.
+    pub fn source_line(&self) -> &str {
+        return &self.source_line;
+    }
 }
 
 
 // COMMON TRAIT IMPLEMENTATIONS
 // ================================================================================================
 
-impl std::fmt::Debug for AssemblyError {
+/// Errors are rendered together with the line of source code at which they occurred; the
+/// erroneous instruction is underlined with carets:
+/// 
+/// ```text
+/// assembly error at line 2, column 5: instruction pushh.1 is invalid
+///   |
+/// 2 |     pushh.1 add
+///   |     ^^^^^^^
+/// ```
+impl std::fmt::Display for AssemblyError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "assembly error at {}: {}", self.step, self.message)
+        let location = match &self.location {
+            Some(location) => location,
+            None => return write!(f, "assembly error: {}", self.message),
+        };
+        match &location.module {
+            Some(module) => writeln!(f, "assembly error in module {} at line {}, column {}: {}",
+                module, location.line, location.column, self.message)?,
+            None => writeln!(f, "assembly error at line {}, column {}: {}",
+                location.line, location.column, self.message)?,
+        };
+
+        // keep tabs in front of the instruction so that carets line up with the instruction
+        let margin = " ".repeat(location.line.to_string().len());
+        let indent: String = location.source_line.chars()
+            .take(location.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        writeln!(f, "{} |", margin)?;
+        writeln!(f, "{} | {}", location.line, location.source_line)?;
+        write!(f, "{} | {}{}", margin, indent, "^".repeat(std::cmp::max(location.length, 1)))
     }
 }
 
-impl std::fmt::Display for AssemblyError {
+impl std::fmt::Debug for AssemblyError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "assembly error at {}: {}", self.step, self.message)
+        write!(f, "{}", self)
     }
-}
\ No newline at end of file
+}
+
+impl std::error::Error for AssemblyError {}
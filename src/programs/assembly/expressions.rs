@@ -0,0 +1,241 @@
+use std::collections::HashMap;
+use crate::math::{ field };
+
+// CONSTANT EXPRESSIONS
+// ================================================================================================
+
+/// Evaluates a constant expression and returns its value as a field element; if the expression
+/// is malformed, returns a description of the problem.
+///
+/// An expression consists of decimal and hexadecimal (`0x` prefixed) literals, names of
+/// `constants`, parentheses, and the following operators (from lowest to highest precedence):
+/// `+` and `-`, `*` and `/`, unary `-`, and `**`. All operations are performed in the field; `/`
+/// multiplies by the inverse of the divisor, and the exponent of `**` is interpreted as an
+/// integer. Expressions may not contain whitespace.
+pub fn evaluate(expr: &str, constants: &HashMap<String, u128>) -> Result<u128, String> {
+    return evaluate_with(expr, constants, false);
+}
+
+/// Evaluates a constant expression in the same way as `evaluate()`, but requires every `/` in
+/// the expression to divide its operands (interpreted as integers) exactly; this way, `/`
+/// means integer division, and an expression such as `7/2` is rejected instead of evaluating
+/// to a multiple of the inverse of 2.
+pub fn evaluate_exact(expr: &str, constants: &HashMap<String, u128>) -> Result<u128, String> {
+    return evaluate_with(expr, constants, true);
+}
+
+fn evaluate_with(expr: &str, constants: &HashMap<String, u128>, exact_division: bool) -> Result<u128, String> {
+    let mut parser = ExpressionParser { expr, pos: 0, constants, exact_division };
+    let value = parser.parse_sum()?;
+
+    // make sure the entire expression was consumed
+    if parser.pos < expr.len() {
+        return Err(parser.unexpected());
+    }
+
+    return Ok(value);
+}
+
+// EXPRESSION PARSER
+// ================================================================================================
+
+/// Recursive descent parser which evaluates an expression as it is being parsed.
+struct ExpressionParser<'a> {
+    expr            : &'a str,
+    pos             : usize,
+    constants       : &'a HashMap<String, u128>,
+    exact_division  : bool,
+}
+
+impl<'a> ExpressionParser<'a> {
+
+    /// sum := product (('+' | '-') product)*
+    fn parse_sum(&mut self) -> Result<u128, String> {
+        let mut result = self.parse_product()?;
+        loop {
+            match self.peek() {
+                Some(b'+') => {
+                    self.pos += 1;
+                    result = field::add(result, self.parse_product()?);
+                },
+                Some(b'-') => {
+                    self.pos += 1;
+                    result = field::sub(result, self.parse_product()?);
+                },
+                _ => return Ok(result),
+            }
+        }
+    }
+
+    /// product := unary (('*' | '/') unary)*
+    fn parse_product(&mut self) -> Result<u128, String> {
+        let mut result = self.parse_unary()?;
+        loop {
+            match self.peek() {
+                Some(b'*') => {
+                    self.pos += 1;
+                    result = field::mul(result, self.parse_unary()?);
+                },
+                Some(b'/') => {
+                    self.pos += 1;
+                    let divisor = self.parse_unary()?;
+                    if divisor == field::ZERO {
+                        return Err(format!("division by zero in expression {}", self.expr));
+                    }
+                    else if self.exact_division && result % divisor != 0 {
+                        return Err(format!("{} is not divisible by {} in expression {}; only push accepts field division",
+                            result, divisor, self.expr));
+                    }
+                    result = field::div(result, divisor);
+                },
+                _ => return Ok(result),
+            }
+        }
+    }
+
+    /// unary := '-' unary | power
+    fn parse_unary(&mut self) -> Result<u128, String> {
+        if self.peek() == Some(b'-') {
+            self.pos += 1;
+            return Ok(field::neg(self.parse_unary()?));
+        }
+        return self.parse_power();
+    }
+
+    /// power := primary ('**' unary)?
+    fn parse_power(&mut self) -> Result<u128, String> {
+        let base = self.parse_primary()?;
+        if self.expr[self.pos..].starts_with("**") {
+            self.pos += 2;
+            let exponent = self.parse_unary()?;
+            return Ok(field::exp(base, exponent));
+        }
+        return Ok(base);
+    }
+
+    /// primary := number | constant | '(' sum ')'
+    fn parse_primary(&mut self) -> Result<u128, String> {
+        match self.peek() {
+            Some(b'(') => {
+                self.pos += 1;
+                let value = self.parse_sum()?;
+                if self.peek() != Some(b')') {
+                    return Err(self.unexpected());
+                }
+                self.pos += 1;
+                return Ok(value);
+            },
+            Some(c) if c.is_ascii_digit() => {
+                let literal = self.read_word();
+                let value = match literal.strip_prefix("0x") {
+                    Some(hex) => u128::from_str_radix(hex, 16),
+                    None => literal.parse::<u128>(),
+                };
+                return match value {
+                    Ok(value) if value < field::MODULUS => Ok(value),
+                    Ok(_) => Err(format!("value {} must be smaller than {}", literal, field::MODULUS)),
+                    Err(_) => Err(format!("{} is not a valid number", literal)),
+                };
+            },
+            Some(c) if c.is_ascii_alphabetic() || c == b'_' => {
+                let name = self.read_word();
+                return match self.constants.get(name) {
+                    Some(&value) => Ok(value),
+                    None => Err(format!("constant {} is not defined", name)),
+                };
+            },
+            _ => return Err(self.unexpected()),
+        }
+    }
+
+    // HELPER METHODS
+    // --------------------------------------------------------------------------------------------
+
+    fn peek(&self) -> Option<u8> {
+        return self.expr.as_bytes().get(self.pos).copied();
+    }
+
+    /// Reads a sequence of letters, digits, and underscores starting at the current position.
+    fn read_word(&mut self) -> &'a str {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if !c.is_ascii_alphanumeric() && c != b'_' { break; }
+            self.pos += 1;
+        }
+        return &self.expr[start..self.pos];
+    }
+
+    fn unexpected(&self) -> String {
+        return match self.expr[self.pos..].chars().next() {
+            Some(c) => format!("unexpected character '{}' in expression {}", c, self.expr),
+            None => format!("unexpected end of expression {}", self.expr),
+        };
+    }
+}
+
+// TESTS
+// ================================================================================================
+#[cfg(test)]
+mod tests {
+
+    use std::collections::HashMap;
+    use crate::math::{ field };
+
+    #[test]
+    fn evaluate() {
+        let mut constants = HashMap::new();
+        constants.insert(String::from("DEPTH"), 8);
+        constants.insert(String::from("_x1"), 3);
+
+        assert_eq!(Ok(42), super::evaluate("42", &constants));
+        assert_eq!(Ok(255), super::evaluate("0xff", &constants));
+        assert_eq!(Ok(7), super::evaluate("1+2*3", &constants));
+        assert_eq!(Ok(9), super::evaluate("(1+2)*3", &constants));
+        assert_eq!(Ok(2), super::evaluate("2**3**0", &constants));
+        assert_eq!(Ok(24), super::evaluate("DEPTH*_x1", &constants));
+        assert_eq!(Ok(256), super::evaluate("2**DEPTH", &constants));
+        assert_eq!(Ok(5), super::evaluate("DEPTH-_x1", &constants));
+
+        // arithmetic is performed in the field
+        assert_eq!(Ok(field::MODULUS - 1), super::evaluate("-1", &constants));
+        assert_eq!(Ok(field::MODULUS - 4), super::evaluate("-2**2", &constants));
+        assert_eq!(Ok(0), super::evaluate("2**128-45*2**40+1", &constants));
+        assert_eq!(Ok(field::inv(2)), super::evaluate("1/2", &constants));
+        assert_eq!(Ok(4), super::evaluate("8/2", &constants));
+    }
+
+    #[test]
+    fn evaluate_exact() {
+        let mut constants = HashMap::new();
+        constants.insert(String::from("DEPTH"), 8);
+
+        // exact division gives the same result as field division
+        assert_eq!(Ok(4), super::evaluate_exact("8/2", &constants));
+        assert_eq!(Ok(2), super::evaluate_exact("DEPTH/2/2", &constants));
+        assert_eq!(Ok(3), super::evaluate_exact("(DEPTH+1)/3", &constants));
+        assert_eq!(Ok(field::MODULUS - 1), super::evaluate_exact("-1", &constants));
+
+        // inexact division is rejected
+        assert_eq!(Err(String::from("7 is not divisible by 2 in expression 7/2; only push accepts field division")),
+            super::evaluate_exact("7/2", &constants));
+        assert_eq!(Err(String::from("4 is not divisible by 8 in expression DEPTH/2/DEPTH; only push accepts field division")),
+            super::evaluate_exact("DEPTH/2/DEPTH", &constants));
+        assert_eq!(Err(String::from("division by zero in expression 1/0")), super::evaluate_exact("1/0", &constants));
+    }
+
+    #[test]
+    fn evaluate_errors() {
+        let constants = HashMap::new();
+        assert_eq!(Err(String::from("constant N is not defined")), super::evaluate("N+1", &constants));
+        assert_eq!(Err(String::from("division by zero in expression 1/(2-2)")), super::evaluate("1/(2-2)", &constants));
+        assert_eq!(Err(String::from("unexpected end of expression 1+")), super::evaluate("1+", &constants));
+        assert_eq!(Err(String::from("unexpected character ')' in expression (1))")), super::evaluate("(1))", &constants));
+        assert_eq!(Err(String::from("unexpected character '+' in expression 1++2")), super::evaluate("1++2", &constants));
+        assert_eq!(Err(String::from("unexpected end of expression ")), super::evaluate("", &constants));
+        assert_eq!(Err(String::from("0xg is not a valid number")), super::evaluate("0xg", &constants));
+        assert_eq!(Err(String::from("12a is not a valid number")), super::evaluate("12a", &constants));
+        assert_eq!(
+            Err(format!("value {} must be smaller than {}", field::MODULUS, field::MODULUS)),
+            super::evaluate(&field::MODULUS.to_string(), &constants));
+    }
+}
@@ -0,0 +1,168 @@
+use std::iter::Peekable;
+use std::str::CharIndices;
+use super::AssemblyError;
+
+// TYPES AND INTERFACES
+// ================================================================================================
+
+/// A single instruction (or block head) of assembly source code together with the location
+/// at which it starts in the source; lines and columns are 1-based, and columns are counted
+/// in characters.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Token<'a> {
+    pub text    : &'a str,
+    pub line    : usize,
+    pub column  : usize,
+}
+
+// LEXER
+// ================================================================================================
+
+/// Breaks assembly source code into tokens separated by any combination of whitespace
+/// characters, and records the location of each token. Comments are skipped: a line comment
+/// starts with `//` and runs until the end of the line, while a block comment starts with `/*`
+/// and ends with the first following `*/` (block comments cannot be nested). A comment also
+/// terminates the token which precedes it.
+pub fn tokenize(source: &str) -> Result<Vec<Token<'_>>, AssemblyError> {
+    let mut tokens = Vec::new();
+
+    // start of the current token as (offset, line, column)
+    let mut start: Option<(usize, usize, usize)> = None;
+    let mut line = 1;
+    let mut column = 1;
+
+    let mut chars = source.char_indices().peekable();
+    while let Some((offset, c)) = chars.next() {
+        let is_comment = c == '/' && matches!(chars.peek(), Some((_, '/')) | Some((_, '*')));
+
+        if c.is_whitespace() || is_comment {
+            if let Some((token_offset, token_line, token_column)) = start.take() {
+                tokens.push(Token {
+                    text    : &source[token_offset..offset],
+                    line    : token_line,
+                    column  : token_column,
+                });
+            }
+        }
+        else if start.is_none() {
+            start = Some((offset, line, column));
+        }
+
+        if is_comment {
+            let (comment_line, comment_column) = (line, column);
+            advance(c, &mut line, &mut column);
+            let (_, next) = chars.next().unwrap();
+            advance(next, &mut line, &mut column);
+
+            if next == '/' {
+                // skip everything up to (but not including) the end of the line
+                while let Some(&(_, c)) = chars.peek() {
+                    if c == '\n' { break; }
+                    chars.next();
+                    advance(c, &mut line, &mut column);
+                }
+            }
+            else if !skip_block_comment(&mut chars, &mut line, &mut column) {
+                let token = Token {
+                    text    : &source[offset..(offset + 2)],
+                    line    : comment_line,
+                    column  : comment_column,
+                };
+                return Err(AssemblyError::unterminated_comment(tokens.len()).with_location(source, &token));
+            }
+        }
+        else {
+            advance(c, &mut line, &mut column);
+        }
+    }
+
+    // add the last token if the source does not end with whitespace
+    if let Some((token_offset, token_line, token_column)) = start {
+        tokens.push(Token { text: &source[token_offset..], line: token_line, column: token_column });
+    }
+
+    return Ok(tokens);
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Updates `line` and `column` to point to the character following `c`.
+fn advance(c: char, line: &mut usize, column: &mut usize) {
+    if c == '\n' {
+        *line += 1;
+        *column = 1;
+    }
+    else {
+        *column += 1;
+    }
+}
+
+/// Consumes characters up to and including the `*/` which closes a block comment; returns
+/// false if the source ends before the comment is closed.
+fn skip_block_comment(chars: &mut Peekable<CharIndices>, line: &mut usize, column: &mut usize) -> bool {
+    while let Some((_, c)) = chars.next() {
+        advance(c, line, column);
+        if c == '*' && matches!(chars.peek(), Some((_, '/'))) {
+            chars.next();
+            advance('/', line, column);
+            return true;
+        }
+    }
+    return false;
+}
+
+// TESTS
+// ================================================================================================
+#[cfg(test)]
+mod tests {
+
+    use super::Token;
+
+    #[test]
+    fn tokenize() {
+        let source = "begin\n    push.1  push.2\n\tadd\r\nend";
+        let tokens = super::tokenize(source).unwrap();
+        assert_eq!(vec![
+            Token { text: "begin",  line: 1, column: 1 },
+            Token { text: "push.1", line: 2, column: 5 },
+            Token { text: "push.2", line: 2, column: 13 },
+            Token { text: "add",    line: 3, column: 2 },
+            Token { text: "end",    line: 4, column: 1 },
+        ], tokens);
+
+        // columns are counted in characters rather than bytes
+        let tokens = super::tokenize("é push.1").unwrap();
+        assert_eq!(Token { text: "push.1", line: 1, column: 3 }, tokens[1]);
+
+        assert!(super::tokenize(" \n\t ").unwrap().is_empty());
+    }
+
+    #[test]
+    fn tokenize_comments() {
+        let source = "begin // start\n  push.1/* one */push.2\n  /* multi\n  line */ add //\nend//";
+        let tokens = super::tokenize(source).unwrap();
+        assert_eq!(vec![
+            Token { text: "begin",  line: 1, column: 1 },
+            Token { text: "push.1", line: 2, column: 3 },
+            Token { text: "push.2", line: 2, column: 18 },
+            Token { text: "add",    line: 4, column: 11 },
+            Token { text: "end",    line: 5, column: 1 },
+        ], tokens);
+
+        // a block comment ends at the first */, and a lone / is a part of a token
+        let tokens = super::tokenize("/* a /* b */ c */ x/y").unwrap();
+        assert_eq!(vec!["c", "*/", "x/y"], tokens.iter().map(|t| t.text).collect::<Vec<_>>());
+
+        assert!(super::tokenize("// nothing here\n/**/").unwrap().is_empty());
+    }
+
+    #[test]
+    fn tokenize_unterminated_comment() {
+        let error = super::tokenize("begin\n  push.1 /* push.2\nend").unwrap_err();
+        assert_eq!("block comment without matching */", error.message());
+        assert_eq!(2, error.step());
+        let location = error.location().unwrap();
+        assert_eq!((2, 10), (location.line(), location.column()));
+    }
+}
@@ -1,57 +1,249 @@
-use std::collections::HashMap;
+use std::collections::{ HashMap, HashSet };
 use super::{ Program, ProgramBlock, Span, Group, Switch, Loop, OpCode, OpHint, BASE_CYCLE_LENGTH };
 
 mod parsers;
 use parsers::*;
 
+mod lexer;
+use lexer::Token;
+
+mod expressions;
+
+mod modules;
+use modules::ModuleImporter;
+pub use modules::ModuleLoader;
+
+mod stdlib;
+
 mod errors;
-use errors::{ AssemblyError };
+pub use errors::{ AssemblyError, SourceLocation };
 
 #[cfg(test)]
 mod tests;
 
 type HintMap = HashMap<usize, OpHint>;
+type ProcMap = HashMap<String, Vec<ProgramBlock>>;
+
+/// Instructions whose parameter is a number; the parameter of these instructions can be
+/// specified with a constant expression.
+const NUMERIC_PARAM_OPS: [&str; 15] = [
+    "push", "dup", "pad", "pick", "drop", "swap", "roll", "gt", "lt", "rc", "isodd", "choose",
+    "hash", "mpath", "repeat",
+];
+
+/// Definitions which are visible to the code being parsed.
+struct ParserContext {
+    /// Aliases of all modules imported so far.
+    modules     : HashSet<String>,
+    /// Values of all constants defined so far.
+    constants   : HashMap<String, u128>,
+    /// Compiled bodies of all procedures defined or imported so far; imported procedures are
+    /// named as `alias::name`.
+    procedures  : ProcMap,
+    /// Name of the procedure whose body is being parsed, if any.
+    current_proc: Option<String>,
+}
+
+impl ParserContext {
+    fn new() -> ParserContext {
+        return ParserContext {
+            modules     : HashSet::new(),
+            constants   : HashMap::new(),
+            procedures  : HashMap::new(),
+            current_proc: None,
+        };
+    }
+}
 
 // ASSEMBLY COMPILER
 // ================================================================================================
 
-/// Compiles provided assembly code into a program.
+/// Compiles provided assembly code into a program. If the code cannot be compiled, the
+/// returned error refers to the line and column of the first erroneous instruction. Comments
+/// (`// ...` until the end of the line, and `/* ... */`) are ignored. The program can import
+/// modules of the standard library only; use `compile_with_loader()` to import other modules.
 pub fn compile(source: &str) -> Result<Program, AssemblyError> {
+    return compile_with_loader(source, &HashMap::<String, String>::new());
+}
+
+/// Compiles provided assembly code into a program, and resolves modules imported by the code
+/// with `use` statements via the `loader`; modules under `std::` are always loaded from the
+/// standard library. If an imported module cannot be compiled, the returned error refers to
+/// the location of the erroneous instruction in that module.
+pub fn compile_with_loader(source: &str, loader: &dyn ModuleLoader) -> Result<Program, AssemblyError> {
+
+    // break assembly string into tokens, skipping all comments
+    let tokens = lexer::tokenize(source)?;
+    let token_values: Vec<&str> = tokens.iter().map(|token| token.text).collect();
+
+    // compile the tokens and, in case of an error, attach the location of the token at which
+    // the error occurred
+    let mut importer = ModuleImporter::new(loader);
+    return compile_tokens(&token_values, &mut importer)
+        .map_err(|error| locate_error(error, source, &tokens));
+}
 
-    // break assembly string into tokens
-    let tokens: Vec<&str> = source.split_whitespace().collect();
+/// Compiles a program from a stream of tokens; errors returned from this function refer to
+/// tokens by their index in the stream.
+fn compile_tokens(tokens: &[&str], importer: &mut ModuleImporter) -> Result<Program, AssemblyError> {
 
     // perform basic validation
     if tokens.len() == 0 {
         return Err(AssemblyError::empty_program());
     }
-    else if tokens[0] != "begin" {
-        return Err(AssemblyError::invalid_program_start(tokens[0]));
-    }
     else if tokens[tokens.len() - 1] != "end" {
-        return Err(AssemblyError::invalid_program_end(tokens[tokens.len() - 1]));
+        return Err(AssemblyError::invalid_program_end(tokens[tokens.len() - 1], tokens.len() - 1));
+    }
+
+    // read imports and definitions which precede the program body
+    let mut context = ParserContext::new();
+    let i = parse_definitions(&mut context, tokens, importer)?;
+
+    // make sure the program body follows the definitions
+    if i == tokens.len() {
+        return Err(AssemblyError::missing_program_body(tokens.len() - 1));
+    }
+    else if tokens[i] != "begin" {
+        return Err(AssemblyError::invalid_program_start(tokens[i], i));
     }
 
     // read the program from the token stream
     let mut root_blocks = Vec::new();
-    let i = parse_branch(&mut root_blocks, &tokens, 0)?;
+    let i = parse_branch(&mut root_blocks, tokens, i, &context)?;
     let root = Group::new(root_blocks);
 
     // make sure there is nothing left after the last token
     if i < tokens.len() - 1 {
-        return Err(AssemblyError::dangling_instructions(i));
+        return Err(AssemblyError::dangling_instructions(i + 1));
     }
 
     // build and return the program
     return Ok(Program::new(root));
 }
 
+/// Compiles an assembly module and returns all procedures defined in it. A module consists of
+/// `use` statements, constant declarations, and procedure definitions only; errors returned
+/// from this function refer to locations in the module source.
+fn compile_module(path: &str, source: &str, importer: &mut ModuleImporter) -> Result<ProcMap, AssemblyError> {
+
+    // break module source into tokens, skipping all comments
+    let tokens = lexer::tokenize(source).map_err(|error| error.in_module(path))?;
+    let token_values: Vec<&str> = tokens.iter().map(|token| token.text).collect();
+
+    // read all definitions, and make sure nothing else is left in the module
+    let mut context = ParserContext::new();
+    let result = match parse_definitions(&mut context, &token_values, importer) {
+        Ok(i) if i < token_values.len() => Err(AssemblyError::invalid_module_content(token_values[i], i)),
+        Ok(_) => Ok(()),
+        Err(error) => Err(error),
+    };
+    if let Err(error) = result {
+        return Err(locate_error(error, source, &tokens).in_module(path));
+    }
+
+    // export procedures defined in the module, but not the procedures it imports
+    return Ok(context.procedures.into_iter().filter(|(name, _)| !name.contains("::")).collect());
+}
+
 // PARSER FUNCTIONS
 // ================================================================================================
 
+/// Parses `use` statements, constant declarations, and procedure definitions from the beginning
+/// of the `token` stream, and returns the index of the first token which follows them.
+fn parse_definitions(context: &mut ParserContext, tokens: &[&str], importer: &mut ModuleImporter) -> Result<usize, AssemblyError> {
+    let mut i = 0;
+    while i < tokens.len() {
+        i = match tokens[i].split(".").next() {
+            Some("use")   => parse_use(context, tokens, i, importer)?,
+            Some("const") => parse_const(context, tokens, i)?,
+            Some("proc")  => parse_proc(context, tokens, i)?,
+            _ => break,
+        };
+    }
+    return Ok(i);
+}
+
+/// Parses a `use.path` statement from the `token` stream, and adds all procedures of the
+/// imported module to the `context`. The procedures can then be invoked as `exec.alias::name`,
+/// where alias is the last component of the module path (e.g. `u64` for `std::u64`).
+fn parse_use(context: &mut ParserContext, tokens: &[&str], i: usize, importer: &mut ModuleImporter) -> Result<usize, AssemblyError> {
+
+    // read and validate module path
+    let head: Vec<&str> = tokens[i].split(".").collect();
+    if head.len() != 2 || !head[1].split("::").all(is_valid_name) {
+        return Err(AssemblyError::invalid_use(&head, i));
+    }
+    let path = head[1];
+    let alias = path.rsplit("::").next().unwrap();
+    if context.modules.contains(alias) {
+        return Err(AssemblyError::duplicate_module(&head, i));
+    }
+
+    // compile the module (if needed), and make its procedures available under the alias
+    let procedures = importer.import(path, &head, i)?;
+    for (name, body) in procedures.iter() {
+        context.procedures.insert(format!("{}::{}", alias, name), body.clone());
+    }
+    context.modules.insert(String::from(alias));
+
+    return Ok(i + 1);
+}
+
+/// Parses a constant declaration of the form `const.NAME=expression` from the `token` stream, and
+/// adds the value of the constant to the `context`. The expression can refer only to constants
+/// which are declared before it.
+fn parse_const(context: &mut ParserContext, tokens: &[&str], i: usize) -> Result<usize, AssemblyError> {
+
+    // split the declaration into constant name and value expression
+    let head: Vec<&str> = tokens[i].split(".").collect();
+    let (name, expr) = match head.get(1).and_then(|decl| decl.split_once("=")) {
+        Some(decl) if head.len() == 2 => decl,
+        _ => return Err(AssemblyError::invalid_const_declaration(&head, i,
+            String::from("declaration must be of the form const.NAME=value"))),
+    };
+
+    // validate the name and evaluate the value of the constant
+    if !is_valid_name(name) {
+        return Err(AssemblyError::invalid_const_declaration(&head, i,
+            String::from("constant name must start with a letter or an underscore, and can contain only letters, digits, and underscores")));
+    }
+    else if context.constants.contains_key(name) {
+        return Err(AssemblyError::duplicate_const(name, i));
+    }
+    let value = expressions::evaluate(expr, &context.constants)
+        .map_err(|reason| AssemblyError::invalid_const_declaration(&head, i, reason))?;
+    context.constants.insert(String::from(name), value);
+
+    return Ok(i + 1);
+}
+
+/// Parses a procedure definition from the `token` stream, and adds the compiled procedure body
+/// to the `context`. A procedure can invoke only procedures which are defined before it; thus,
+/// procedures cannot be recursive.
+fn parse_proc(context: &mut ParserContext, tokens: &[&str], i: usize) -> Result<usize, AssemblyError> {
+
+    // read and validate procedure name
+    let head: Vec<&str> = tokens[i].split(".").collect();
+    if head.len() != 2 || !is_valid_name(head[1]) {
+        return Err(AssemblyError::invalid_proc_declaration(&head, i));
+    }
+    else if context.procedures.contains_key(head[1]) {
+        return Err(AssemblyError::duplicate_proc(head[1], i));
+    }
+
+    // parse procedure body and save it in the context
+    context.current_proc = Some(String::from(head[1]));
+    let mut body = Vec::new();
+    let i = parse_branch(&mut body, tokens, i, context)?;
+    context.current_proc = None;
+    context.procedures.insert(String::from(head[1]), body);
+
+    return Ok(i + 1);
+}
+
 /// Parses a single program block from the `token` stream, and appends this block to the `parent`
 /// list of blocks.
-fn parse_block(parent: &mut Vec<ProgramBlock>, tokens: &[&str], mut i: usize) -> Result<usize, AssemblyError> {
+fn parse_block(parent: &mut Vec<ProgramBlock>, tokens: &[&str], mut i: usize, context: &ParserContext) -> Result<usize, AssemblyError> {
 
     // read the block header
     let head: Vec<&str> = tokens[i].split(".").collect();
@@ -65,7 +257,7 @@ fn parse_block(parent: &mut Vec<ProgramBlock>, tokens: &[&str], mut i: usize) ->
             }
             // then parse the body of the block, add the new block to the parent, and return
             let mut body = Vec::new();
-            i = parse_branch(&mut body, tokens, i)?;
+            i = parse_branch(&mut body, tokens, i, context)?;
             parent.push(Group::new_block(body));
             return Ok(i + 1);
         },
@@ -77,13 +269,13 @@ fn parse_block(parent: &mut Vec<ProgramBlock>, tokens: &[&str], mut i: usize) ->
 
             // parse the body of the true branch
             let mut t_branch = Vec::new();
-            i = parse_branch(&mut t_branch, tokens, i)?;
+            i = parse_branch(&mut t_branch, tokens, i, context)?;
 
             // if the false branch is present, parse it as well; otherwise
             // create an empty false branch
             let mut f_branch = Vec::new();
             if tokens[i] == "else" {
-                i = parse_branch(&mut f_branch, tokens, i)?;
+                i = parse_branch(&mut f_branch, tokens, i, context)?;
             }
             else {
                 f_branch.push(Span::new_block(vec![
@@ -100,6 +292,11 @@ fn parse_block(parent: &mut Vec<ProgramBlock>, tokens: &[&str], mut i: usize) ->
         },
         "repeat" => {
             // read and validate number of loop iterations
+            let param = resolve_param(&head, i, context)?;
+            let head: Vec<&str> = match &param {
+                Some(param) => vec![head[0], param.as_str()],
+                None => head,
+            };
             let num_iterations = read_param(&head, i)? as usize;
             if num_iterations < 2 {
                 return Err(AssemblyError::invalid_num_iterations(&head, i));
@@ -107,7 +304,7 @@ fn parse_block(parent: &mut Vec<ProgramBlock>, tokens: &[&str], mut i: usize) ->
 
             // parse loop body
             let mut body_template = Vec::new();
-            i = parse_branch(&mut body_template, tokens, i)?;
+            i = parse_branch(&mut body_template, tokens, i, context)?;
 
             // duplicate loop body as many times as needed
             let body = repeat_block_sequence(body_template, num_iterations);
@@ -123,7 +320,7 @@ fn parse_block(parent: &mut Vec<ProgramBlock>, tokens: &[&str], mut i: usize) ->
             }
             // then parse the body of the block, add the new block to the parent, and return
             let mut body = Vec::new();
-            i = parse_branch(&mut body, tokens, i)?;
+            i = parse_branch(&mut body, tokens, i, context)?;
             parent.push(Loop::new_block(body));
             return Ok(i + 1);
         },
@@ -133,7 +330,7 @@ fn parse_block(parent: &mut Vec<ProgramBlock>, tokens: &[&str], mut i: usize) ->
 
 /// Builds a body of a program block by parsing tokens from the stream and transforming
 /// them into program blocks.
-fn parse_branch(body: &mut Vec<ProgramBlock>, tokens: &[&str], mut i: usize) -> Result<usize, AssemblyError> {
+fn parse_branch(body: &mut Vec<ProgramBlock>, tokens: &[&str], mut i: usize, context: &ParserContext) -> Result<usize, AssemblyError> {
 
     // determine starting instructions of the branch based on branch head
     let mut head: Vec<&str> = tokens[i].split(".").collect();
@@ -144,6 +341,7 @@ fn parse_branch(body: &mut Vec<ProgramBlock>, tokens: &[&str], mut i: usize) ->
             vec![OpCode::Begin]
         },
         "block"  => vec![],
+        "proc"   => vec![],
         "if"     => vec![OpCode::Assert],
         "else"   => vec![OpCode::Not, OpCode::Assert],
         "repeat" => vec![],
@@ -164,8 +362,16 @@ fn parse_branch(body: &mut Vec<ProgramBlock>, tokens: &[&str], mut i: usize) ->
             "block" | "if" | "repeat" | "while" => {
                 let force_span = body.len() == 0;
                 add_span(body, &mut op_codes, &mut op_hints, force_span);
-                parse_block(body, tokens, i)?
+                parse_block(body, tokens, i, context)?
+            },
+            "exec" => {
+                let force_span = body.len() == 0;
+                add_span(body, &mut op_codes, &mut op_hints, force_span);
+                parse_exec(body, &op, i, context)?
             },
+            "proc" => return Err(AssemblyError::misplaced_proc(i)),
+            "const" => return Err(AssemblyError::misplaced_const(i)),
+            "use" => return Err(AssemblyError::misplaced_use(i)),
             "else" => {
                 if head[0] != "if" {
                     return Err(AssemblyError::dangling_else(i));
@@ -183,13 +389,14 @@ fn parse_branch(body: &mut Vec<ProgramBlock>, tokens: &[&str], mut i: usize) ->
                 add_span(body, &mut op_codes, &mut op_hints, false);
                 return Ok(i);
             },
-            _ => parse_op_token(op, &mut op_codes, &mut op_hints, i)?
+            _ => parse_op_token(op, &mut op_codes, &mut op_hints, i, context)?
         };
     }
 
     // if all tokens were consumed by block end was not found, return an error
     return match head[0] {
         "block"  => Err(AssemblyError::unmatched_block(first_step)),
+        "proc"   => Err(AssemblyError::unmatched_proc(first_step, &head)),
         "if"     => Err(AssemblyError::unmatched_if(first_step)),
         "else"   => Err(AssemblyError::unmatched_else(first_step)),
         "repeat" => Err(AssemblyError::unmatched_repeat(first_step, &head)),
@@ -198,8 +405,37 @@ fn parse_branch(body: &mut Vec<ProgramBlock>, tokens: &[&str], mut i: usize) ->
     };
 }
 
+/// Inlines the body of the invoked procedure into the `parent` list of blocks as a Group block.
+fn parse_exec(parent: &mut Vec<ProgramBlock>, op: &[&str], step: usize, context: &ParserContext) -> Result<usize, AssemblyError> {
+    if op.len() == 1 {
+        return Err(AssemblyError::missing_param(op, step));
+    }
+    else if op.len() > 2 {
+        return Err(AssemblyError::extra_param(op, step));
+    }
+
+    match context.procedures.get(op[1]) {
+        Some(proc_body) => parent.push(Group::new_block(proc_body.clone())),
+        None => {
+            if context.current_proc.as_deref() == Some(op[1]) {
+                return Err(AssemblyError::recursive_proc(op, step));
+            }
+            return Err(AssemblyError::undefined_proc(op, step));
+        }
+    }
+
+    return Ok(step + 1);
+}
+
 /// Transforms an assembly instruction into a sequence of one or more VM instructions.
-fn parse_op_token(op: Vec<&str>, op_codes: &mut Vec<OpCode>, op_hints: &mut HintMap, step: usize) -> Result<usize, AssemblyError> {
+fn parse_op_token(op: Vec<&str>, op_codes: &mut Vec<OpCode>, op_hints: &mut HintMap, step: usize, context: &ParserContext) -> Result<usize, AssemblyError> {
+
+    // replace a constant expression in the parameter of the instruction with its value
+    let param = resolve_param(&op, step, context)?;
+    let op: Vec<&str> = match &param {
+        Some(param) => vec![op[0], param.as_str()],
+        None => op,
+    };
 
     // based on the instruction, invoke the correct parser for the operation
     match op[0] {
@@ -208,6 +444,7 @@ fn parse_op_token(op: Vec<&str>, op_codes: &mut Vec<OpCode>, op_hints: &mut Hint
 
         "push"   => parse_push(op_codes, op_hints, &op, step),
         "read"   => parse_read(op_codes, &op, step),
+        "write"  => parse_write(op_codes, op_hints, &op, step),
 
         "dup"    => parse_dup(op_codes, &op, step),
         "pad"    => parse_pad(op_codes, &op, step),
@@ -248,6 +485,18 @@ fn parse_op_token(op: Vec<&str>, op_codes: &mut Vec<OpCode>, op_hints: &mut Hint
 // HELPER FUNCTIONS
 // ================================================================================================
 
+/// Attaches the location of the token at which the error occurred to the error, unless the
+/// error already refers to a location in an imported module.
+fn locate_error(error: AssemblyError, source: &str, tokens: &[Token]) -> AssemblyError {
+    if error.location().is_some() {
+        return error;
+    }
+    return match tokens.get(error.step()) {
+        Some(token) => error.with_location(source, token),
+        None => error,
+    };
+}
+
 /// Adds a new Span block to a program block body based on currently parsed instructions.
 fn add_span(body: &mut Vec<ProgramBlock>, op_codes: &mut Vec<OpCode>, op_hints: &mut HintMap, force: bool) {
 
@@ -267,6 +516,38 @@ fn add_span(body: &mut Vec<ProgramBlock>, op_codes: &mut Vec<OpCode>, op_hints:
     op_hints.clear();
 }
 
+/// If the instruction takes a numeric parameter, and the parameter is not a decimal literal,
+/// evaluates the parameter as a constant expression and returns its value as a decimal literal.
+/// Only the value pushed by `push` can be any field element; parameters of other instructions
+/// are counts and indexes, and so division in their expressions must be exact.
+fn resolve_param(op: &[&str], step: usize, context: &ParserContext) -> Result<Option<String>, AssemblyError> {
+    if op.len() != 2 || !NUMERIC_PARAM_OPS.contains(&op[0]) || op[1].bytes().all(|b| b.is_ascii_digit()) {
+        return Ok(None);
+    }
+
+    let value = if op[0] == "push" {
+        expressions::evaluate(op[1], &context.constants)
+    }
+    else {
+        expressions::evaluate_exact(op[1], &context.constants)
+    };
+
+    return match value {
+        Ok(value) => Ok(Some(value.to_string())),
+        Err(reason) => Err(AssemblyError::invalid_param_reason(op, step, reason)),
+    };
+}
+
+/// Names of procedures, constants, and modules must start with a letter or an underscore, and can contain
+/// only letters, digits, and underscores.
+fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    return match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => chars.all(|c| c.is_ascii_alphanumeric() || c == '_'),
+        _ => false,
+    };
+}
+
 fn repeat_block_sequence(template: Vec<ProgramBlock>, num_iterations: usize) -> Vec<ProgramBlock> {
     let mut body = Vec::with_capacity(template.len() * num_iterations);
 
@@ -303,7 +584,10 @@ fn merge_spans(span1: &ProgramBlock, span2: &ProgramBlock) -> ProgramBlock {
 }
 
 fn read_param(op: &[&str], step: usize) -> Result<u32, AssemblyError> {
-    if op.len() > 2 {
+    if op.len() == 1 {
+        return Err(AssemblyError::missing_param(op, step));
+    }
+    else if op.len() > 2 {
         return Err(AssemblyError::extra_param(op, step));
     }
 
@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+use super::{ AssemblyError, ProcMap, stdlib };
+
+// TYPES AND INTERFACES
+// ================================================================================================
+
+/// Source of assembly modules which can be imported into programs with `use` statements.
+pub trait ModuleLoader {
+    /// Returns source code of the module at the specified `path` (e.g. `crypto::merkle`), or
+    /// None if the loader does not have such a module.
+    fn load_module(&self, path: &str) -> Option<String>;
+}
+
+/// A loader which serves modules from a map of module paths to module sources.
+impl ModuleLoader for HashMap<String, String> {
+    fn load_module(&self, path: &str) -> Option<String> {
+        return self.get(path).cloned();
+    }
+}
+
+/// Loads and compiles modules imported by a program. Each module is compiled only once, even
+/// if it is imported by several other modules.
+pub struct ModuleImporter<'a> {
+    loader  : &'a dyn ModuleLoader,
+    modules : HashMap<String, ProcMap>,
+    loading : Vec<String>,
+}
+
+// MODULE IMPORTER IMPLEMENTATION
+// ================================================================================================
+impl <'a> ModuleImporter<'a> {
+
+    pub fn new(loader: &'a dyn ModuleLoader) -> ModuleImporter<'a> {
+        return ModuleImporter { loader, modules: HashMap::new(), loading: Vec::new() };
+    }
+
+    /// Returns procedures defined in the module at the specified `path`, compiling the module
+    /// if it has not been compiled yet. Modules under `std::` are loaded from the standard
+    /// library, all other modules are requested from the loader; `op` and `step` identify the
+    /// `use` statement which imports the module.
+    pub fn import(&mut self, path: &str, op: &[&str], step: usize) -> Result<&ProcMap, AssemblyError> {
+        if !self.modules.contains_key(path) {
+            // a module which is being compiled cannot be imported again
+            if self.loading.iter().any(|p| p == path) {
+                return Err(AssemblyError::cyclic_import(op, step));
+            }
+
+            let source = if path.starts_with("std::") {
+                stdlib::get_module_source(path).map(String::from)
+            }
+            else {
+                self.loader.load_module(path)
+            };
+            let source = source.ok_or_else(|| AssemblyError::module_not_found(op, step))?;
+
+            self.loading.push(String::from(path));
+            let procedures = super::compile_module(path, &source, self);
+            self.loading.pop();
+            self.modules.insert(String::from(path), procedures?);
+        }
+
+        return Ok(&self.modules[path]);
+    }
+}
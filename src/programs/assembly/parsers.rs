@@ -80,6 +80,14 @@ pub fn parse_read(program: &mut Vec<OpCode>, op: &[&str], step: usize) -> Result
     return Ok(true);
 }
 
+/// Appends a DROP operation to the program which writes the dropped value to the output tape.
+pub fn parse_write(program: &mut Vec<OpCode>, hints: &mut HintMap, op: &[&str], step: usize) -> Result<bool, AssemblyError> {
+    if op.len() > 1 { return Err(AssemblyError::extra_param(op, step)); }
+    hints.insert(program.len(), OpHint::WriteOutput);
+    program.push(OpCode::Drop);
+    return Ok(true);
+}
+
 // STACK MANIPULATION OPERATIONS
 // ================================================================================================
 
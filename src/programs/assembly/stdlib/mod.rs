@@ -0,0 +1,23 @@
+// STANDARD LIBRARY
+// ================================================================================================
+
+/// Paths and sources of all modules of the standard library; the sources are compiled only when
+/// a program imports them.
+const MODULES: [(&str, &str); 5] = [
+    ("std::bits",   include_str!("bits.dasm")),
+    ("std::io",     include_str!("io.dasm")),
+    ("std::math",   include_str!("math.dasm")),
+    ("std::merkle", include_str!("merkle.dasm")),
+    ("std::u64",    include_str!("u64.dasm")),
+];
+
+/// Returns source code of the standard library module at the specified `path`, or None if
+/// there is no such module in the standard library.
+pub fn get_module_source(path: &str) -> Option<&'static str> {
+    return MODULES.iter().find(|(module_path, _)| *module_path == path).map(|(_, source)| *source);
+}
+
+// TESTS
+// ================================================================================================
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,204 @@
+use crate::{ ProgramInputs, ExecutionError, math::field, utils::hasher };
+use super::super::compile;
+
+// MODULES
+// ================================================================================================
+
+#[test]
+fn all_modules_compile() {
+    for (path, _) in super::MODULES.iter() {
+        let source = format!("use.{} begin noop end", path);
+        assert!(compile(&source).is_ok(), "module {} failed to compile", path);
+    }
+}
+
+#[test]
+fn unknown_module() {
+    assert_eq!(None, super::get_module_source("std::foo"));
+    assert_eq!(None, super::get_module_source("bits"));
+}
+
+// BITS
+// ================================================================================================
+
+#[test]
+fn split_lsb() {
+    let stack = execute("use.std::bits begin exec.bits::split_lsb end", &[13], &[], &[]).unwrap();
+    assert_eq!([6, 1], stack[..2]);
+
+    let stack = execute("use.std::bits begin exec.bits::split_lsb end", &[u64::MAX as u128 - 1], &[], &[]).unwrap();
+    assert_eq!([u64::MAX as u128 / 2, 0], stack[..2]);
+}
+
+#[test]
+fn split8() {
+    let stack = execute("use.std::bits begin exec.bits::split8 end", &[0b10110010, 7], &[], &[]).unwrap();
+    assert_eq!([1, 0, 1, 1, 0, 0, 1, 0, 7], stack[..9]);
+
+    // values which do not fit into 8 bits are rejected
+    assert!(execute("use.std::bits begin exec.bits::split8 end", &[256], &[], &[]).is_err());
+}
+
+#[test]
+fn split16() {
+    let stack = execute("use.std::bits begin exec.bits::split16 end", &[0xbeef], &[], &[]).unwrap();
+    assert_eq!([1, 0, 1, 1, 1, 1, 1, 0, 1, 1, 1, 0, 1, 1, 1, 1], stack[..16]);
+
+    assert!(execute("use.std::bits begin exec.bits::split16 end", &[0x10000], &[], &[]).is_err());
+}
+
+// IO
+// ================================================================================================
+
+#[test]
+fn read_absorb() {
+    let values = [3, 5, 7, 11, 13];
+    let source = "use.std::io begin push.5 pad repeat.3 exec.io::read_absorb end end";
+    let mut tape = values.to_vec();
+    tape.push(0);
+    let stack = execute(source, &[], &tape, &[]).unwrap();
+
+    let digest = hasher::digest_sequence(&values);
+    assert_eq!([digest[1], digest[0], 0, 13, 11, 7, 5, 3], stack[..8]);
+
+    // the digest depends on the order of the values
+    let stack = execute(source, &[], &[3, 5, 11, 7, 13, 0], &[]).unwrap();
+    assert!(stack[..2] != [digest[1], digest[0]]);
+
+    // sequences which differ only by trailing zeros have different digests
+    assert!(hasher::digest_sequence(&[1, 2, 0]) != hasher::digest_sequence(&[1, 2]));
+}
+
+#[test]
+fn write_absorb() {
+    let values = [3, 5, 7, 11, 13];
+    let source = "use.std::io begin
+        push.5 pad
+        push.3 push.5 exec.io::write_absorb
+        push.7 push.11 exec.io::write_absorb
+        push.13 exec.io::write_absorb_last
+    end";
+    let program = compile(source).unwrap();
+    let result = crate::run(&program, &ProgramInputs::none()).unwrap();
+
+    // values are written in the order in which they are absorbed
+    let digest = hasher::digest_sequence(&values);
+    assert_eq!([digest[1], digest[0]], result.stack[..2]);
+    assert_eq!(values.to_vec(), result.output_tape);
+}
+
+// MATH
+// ================================================================================================
+
+#[test]
+fn min_max() {
+    let max = field::MODULUS - 1;
+    for (a, b) in [(3, 5), (5, 3), (4, 4), (max, 1), (0, max)].iter() {
+        let stack = execute("use.std::math begin exec.math::min end", &[*a, *b, 9], &[], &[]).unwrap();
+        assert_eq!([u128::min(*a, *b), 9], stack[..2]);
+
+        let stack = execute("use.std::math begin exec.math::max end", &[*a, *b, 9], &[], &[]).unwrap();
+        assert_eq!([u128::max(*a, *b), 9], stack[..2]);
+    }
+}
+
+// U64
+// ================================================================================================
+
+#[test]
+fn checked_arithmetic() {
+    let max = u64::MAX as u128;
+
+    let stack = execute("use.std::u64 begin exec.u64::assert_u64 end", &[max], &[], &[]).unwrap();
+    assert_eq!([max], stack[..1]);
+    assert!(execute("use.std::u64 begin exec.u64::assert_u64 end", &[max + 1], &[], &[]).is_err());
+
+    let stack = execute("use.std::u64 begin exec.u64::checked_add end", &[max - 5, 5], &[], &[]).unwrap();
+    assert_eq!([max], stack[..1]);
+    assert!(execute("use.std::u64 begin exec.u64::checked_add end", &[max - 5, 6], &[], &[]).is_err());
+
+    let stack = execute("use.std::u64 begin exec.u64::checked_sub end", &[3, 10], &[], &[]).unwrap();
+    assert_eq!([7], stack[..1]);
+    let stack = execute("use.std::u64 begin exec.u64::checked_sub end", &[10, 10], &[], &[]).unwrap();
+    assert_eq!([0], stack[..1]);
+    assert!(execute("use.std::u64 begin exec.u64::checked_sub end", &[10, 3], &[], &[]).is_err());
+}
+
+#[test]
+fn wrapping_arithmetic() {
+    let max = u64::MAX as u128;
+
+    let stack = execute("use.std::u64 begin exec.u64::overflowing_add end", &[max, 2], &[], &[]).unwrap();
+    assert_eq!([1, 1], stack[..2]);
+    let stack = execute("use.std::u64 begin exec.u64::overflowing_add end", &[max - 2, 2], &[], &[]).unwrap();
+    assert_eq!([max, 0], stack[..2]);
+
+    let stack = execute("use.std::u64 begin exec.u64::wrapping_add end", &[max, max, 9], &[], &[]).unwrap();
+    assert_eq!([max - 1, 9], stack[..2]);
+    let stack = execute("use.std::u64 begin exec.u64::wrapping_add end", &[3, 4, 9], &[], &[]).unwrap();
+    assert_eq!([7, 9], stack[..2]);
+}
+
+// MERKLE
+// ================================================================================================
+
+#[test]
+fn assert_root() {
+    let result = execute("use.std::merkle begin exec.merkle::assert_root end", &[1, 2, 1, 2, 9], &[], &[]);
+    assert_eq!([9], result.unwrap()[..1]);
+
+    assert!(execute("use.std::merkle begin exec.merkle::assert_root end", &[1, 2, 2, 1], &[], &[]).is_err());
+}
+
+#[test]
+fn verify_merkle_path() {
+    let source = "use.std::merkle begin exec.merkle::verify8 push.9 end";
+
+    for &index in [0, 1, 77, 127].iter() {
+        let (path, root, a, b) = build_merkle_path(8, index);
+        let stack = execute(source, &[path[1][0], path[0][0], root[1], root[0]], &a, &b).unwrap();
+        assert_eq!([9, 0], stack[..2]);
+
+        // a path which does not resolve to the root is rejected
+        assert!(execute(source, &[path[1][0], path[0][0], root[0], root[1]], &a, &b).is_err());
+        assert!(execute(source, &[path[0][0], path[1][0], root[1], root[0]], &a, &b).is_err());
+    }
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+fn execute(source: &str, stack: &[u128], a: &[u128], b: &[u128]) -> Result<Vec<u128>, ExecutionError> {
+    let program = compile(source).unwrap();
+    let inputs = ProgramInputs::new(stack, a, b);
+    return crate::run(&program, &inputs).map(|result| result.stack);
+}
+
+/// Builds a pseudo-random authentication path for the leaf at the specified `index` of a Merkle
+/// tree of depth `n`; returns the path, the root of the tree, and contents of tapes A and B
+/// expected by the mpath instruction.
+fn build_merkle_path(n: usize, index: usize) -> ([Vec<u128>; 2], Vec<u128>, Vec<u128>, Vec<u128>) {
+    let path = [field::prng_vector([1; 32], n), field::prng_vector([2; 32], n)];
+
+    let mut a = Vec::new();
+    let mut b = Vec::new();
+    let mut node = vec![path[0][0], path[1][0]];
+    let mut index = index;
+    for i in 1..n {
+        a.push(path[0][i]);
+        b.push(path[1][i]);
+        a.push(field::ZERO);
+        b.push((index & 1) as u128);
+
+        let buf = if index & 1 == 0 {
+            [node[0], node[1], path[0][i], path[1][i]]
+        }
+        else {
+            [path[0][i], path[1][i], node[0], node[1]]
+        };
+        node = hasher::digest(&buf).to_vec();
+        index >>= 1;
+    }
+
+    return (path, node, a, b);
+}
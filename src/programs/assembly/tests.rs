@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 // GROUP BLOCKS
 // ================================================================================================
 #[test]
@@ -14,6 +16,20 @@ fn single_block() {
     assert_eq!(expected, format!("{:?}", program));
 }
 
+#[test]
+fn write_to_output_tape() {
+    let source = "begin push.1 dup write drop end";
+    let program = super::compile(source).unwrap();
+
+    // write is a DROP operation with a hint; the hint does not affect the program hash
+    let expected = "\
+        begin noop noop noop noop noop noop noop \
+        push(1) dup drop::write drop noop noop noop end";
+
+    assert_eq!(expected, format!("{:?}", program));
+    assert_eq!(program.hash(), super::compile("begin push.1 dup drop drop end").unwrap().hash());
+}
+
 #[test]
 fn sequence_of_blocks() {
     let source = "begin block push.1 push.2 add end block push.3 push.4 add end end";
@@ -395,4 +411,326 @@ fn repeat_2_blocks_with_suffix() {
     end";
 
     assert_eq!(expected, format!("{:?}", program));
-}
\ No newline at end of file
+}
+// PROCEDURES
+// ================================================================================================
+#[test]
+fn procedures() {
+    // procedure bodies are inlined as Group blocks at every call site
+    let source = "\
+        proc.fib_step swap dup.2 drop add end
+        proc.fib_step2 exec.fib_step exec.fib_step end
+        begin push.1 exec.fib_step2 if.true exec.fib_step end end";
+    let program = super::compile(source).unwrap();
+
+    let expected = super::compile("\
+        begin
+            push.1
+            block block swap dup.2 drop add end block swap dup.2 drop add end end
+            if.true block swap dup.2 drop add end end
+        end").unwrap();
+
+    assert_eq!(format!("{:?}", expected), format!("{:?}", program));
+    assert_eq!(expected.hash(), program.hash());
+
+    // inlined procedures are executed as expected
+    let program = super::compile("\
+        proc.fib_step swap dup.2 drop add end
+        begin exec.fib_step exec.fib_step exec.fib_step end").unwrap();
+    let inputs = crate::ProgramInputs::from_public(&[1, 0]);
+    let result = crate::run(&program, &inputs).unwrap();
+    assert_eq!([3, 2], result.stack[..2]);
+}
+
+#[test]
+fn procedure_errors() {
+    let error = super::compile("begin exec.foo end").err().unwrap();
+    assert_eq!("procedure foo is not defined; procedures must be defined before they are invoked", error.message());
+    assert_eq!(1, error.step());
+
+    // procedures can invoke only procedures defined before them
+    let error = super::compile("proc.foo exec.bar end proc.bar add end begin exec.foo end").err().unwrap();
+    assert_eq!("procedure bar is not defined; procedures must be defined before they are invoked", error.message());
+    assert_eq!(1, error.step());
+
+    let error = super::compile("proc.foo add if.true exec.foo end end begin exec.foo end").err().unwrap();
+    assert_eq!("procedure foo cannot invoke itself: recursive procedures are not supported", error.message());
+    assert_eq!(3, error.step());
+
+    let error = super::compile("proc.foo add end proc.foo mul end begin exec.foo end").err().unwrap();
+    assert_eq!("procedure foo is already defined", error.message());
+    assert_eq!(3, error.step());
+
+    let error = super::compile("proc.1foo add end begin exec.1foo end").err().unwrap();
+    assert_eq!("invalid procedure declaration 'proc.1foo'", error.message());
+
+    let error = super::compile("begin proc.foo add end end").err().unwrap();
+    assert_eq!("procedures can be defined only before the 'begin' instruction", error.message());
+    assert_eq!(1, error.step());
+
+    let error = super::compile("proc.foo add end").err().unwrap();
+    assert_eq!("constant and procedure definitions must be followed by a 'begin' instruction", error.message());
+    assert_eq!(2, error.step());
+
+    let error = super::compile("proc.foo end begin add end").err().unwrap();
+    assert_eq!("a program block must contain at least one instruction", error.message());
+    assert_eq!(0, error.step());
+
+    let error = super::compile("begin exec end").err().unwrap();
+    assert_eq!("malformed instruction exec: parameter is missing", error.message());
+}
+
+// CONSTANTS
+// ================================================================================================
+#[test]
+fn constants() {
+    // constants can be used in place of any numeric parameter
+    let source = "\
+        const.DEPTH=4
+        const.BITS=DEPTH*8
+        const.a=0xff
+        proc.step push.a*2 dup.DEPTH/2 drop.(DEPTH-2) end
+        begin
+            exec.step push.-1 read.a gt.BITS mpath.DEPTH
+            repeat.DEPTH-1 swap dup.2 drop add end
+        end";
+    let program = super::compile(source).unwrap();
+
+    let expected = super::compile(&format!("\
+        begin
+            block push.510 dup.2 drop.2 end push.{} read.a gt.32 mpath.4
+            repeat.3 swap dup.2 drop add end
+        end", crate::math::field::MODULUS - 1)).unwrap();
+
+    assert_eq!(format!("{:?}", expected), format!("{:?}", program));
+    assert_eq!(expected.hash(), program.hash());
+}
+
+#[test]
+fn constant_errors() {
+    let error = super::compile("begin push.N end").err().unwrap();
+    assert_eq!("malformed instruction push: constant N is not defined", error.message());
+    assert_eq!(1, error.step());
+
+    let error = super::compile("const.N=1 begin repeat.N add end end").err().unwrap();
+    assert_eq!("invalid repeat statement 'repeat.1': 2 or more iterations must be specified", error.message());
+
+    let error = super::compile("const.N=2 begin dup.N- end").err().unwrap();
+    assert_eq!("malformed instruction dup: unexpected end of expression N-", error.message());
+
+    // only push accepts field division
+    let error = super::compile("const.N=7 begin dup.N/2 end").err().unwrap();
+    assert_eq!("malformed instruction dup: 7 is not divisible by 2 in expression N/2; only push accepts field division", error.message());
+    assert_eq!(2, error.step());
+
+    let error = super::compile("begin repeat.9/2 add end end").err().unwrap();
+    assert_eq!("malformed instruction repeat: 9 is not divisible by 2 in expression 9/2; only push accepts field division", error.message());
+    assert!(super::compile("const.N=7 begin push.N/2 end").is_ok());
+
+    let error = super::compile("const.N=2 const.N=3 begin add end").err().unwrap();
+    assert_eq!("constant N is already defined", error.message());
+    assert_eq!(1, error.step());
+
+    let error = super::compile("const.N begin add end").err().unwrap();
+    assert_eq!("invalid constant declaration 'const.N': declaration must be of the form const.NAME=value", error.message());
+
+    let error = super::compile("const.2N=2 begin add end").err().unwrap();
+    assert!(error.message().starts_with("invalid constant declaration 'const.2N=2': constant name must start"));
+
+    // constants can refer only to constants declared before them
+    let error = super::compile("const.A=B+1 const.B=1 begin add end").err().unwrap();
+    assert_eq!("invalid constant declaration 'const.A=B+1': constant B is not defined", error.message());
+    assert_eq!(0, error.step());
+
+    let error = super::compile("begin const.N=1 end").err().unwrap();
+    assert_eq!("constants can be defined only before the 'begin' instruction", error.message());
+
+    let error = super::compile("begin repeat add end end").err().unwrap();
+    assert_eq!("malformed instruction repeat: parameter is missing", error.message());
+}
+
+// MODULES
+// ================================================================================================
+
+#[test]
+fn modules() {
+    let mut loader = HashMap::new();
+    loader.insert(String::from("fib"), String::from("\
+        const.STEPS=3
+        proc.fib_step swap dup.2 drop add end
+        proc.fib repeat.STEPS exec.fib_step end end"));
+    loader.insert(String::from("utils::fib_twice"), String::from("\
+        use.fib
+        proc.run exec.fib::fib exec.fib::fib end"));
+
+    // imported procedures are inlined in the same way as local procedures
+    let program = super::compile_with_loader("use.fib begin push.1 exec.fib::fib_step end", &loader).unwrap();
+    let expected = super::compile("\
+        proc.fib_step swap dup.2 drop add end
+        begin push.1 exec.fib_step end").unwrap();
+    assert_eq!(expected.hash(), program.hash());
+
+    // modules can import other modules
+    let program = super::compile_with_loader("\
+        use.fib
+        use.utils::fib_twice
+        begin exec.fib_twice::run exec.fib::fib_step end", &loader).unwrap();
+    let inputs = crate::ProgramInputs::from_public(&[1, 0]);
+    let result = crate::run(&program, &inputs).unwrap();
+    assert_eq!([21, 13], result.stack[..2]);
+
+    // standard library is available without a loader
+    let program = super::compile("use.std::math begin exec.math::max end").unwrap();
+    let inputs = crate::ProgramInputs::from_public(&[3, 5]);
+    let result = crate::run(&program, &inputs).unwrap();
+    assert_eq!([5], result.stack[..1]);
+}
+
+#[test]
+fn module_errors() {
+    let mut loader = HashMap::new();
+    loader.insert(String::from("a"), String::from("use.b proc.foo add end"));
+    loader.insert(String::from("b"), String::from("use.a proc.bar add end"));
+    loader.insert(String::from("d"), String::from("proc.foo add end"));
+    loader.insert(String::from("e::d"), String::from("proc.bar add end"));
+    loader.insert(String::from("f"), String::from("use.d proc.bar exec.d::foo end"));
+    loader.insert(String::from("g"), String::from("use.f proc.foo exec.d::foo end"));
+
+    let error = super::compile_with_loader("use.foo begin add end", &loader).err().unwrap();
+    assert_eq!("module foo could not be found", error.message());
+    assert_eq!(None, error.location().unwrap().module());
+
+    let error = super::compile("use.std::foo begin add end").err().unwrap();
+    assert_eq!("module std::foo could not be found", error.message());
+
+    let error = super::compile_with_loader("use.a begin add end", &loader).err().unwrap();
+    assert_eq!("module a cannot be imported: cyclic imports are not supported", error.message());
+    assert_eq!(Some("b"), error.location().unwrap().module());
+
+    let error = super::compile_with_loader("use.d use.e::d begin add end", &loader).err().unwrap();
+    assert_eq!("a module named d is already imported", error.message());
+    assert_eq!(1, error.step());
+
+    // procedures imported into a module are not exported from it
+    let error = super::compile_with_loader("use.g begin add end", &loader).err().unwrap();
+    assert_eq!("procedure d::foo is not defined; procedures must be defined before they are invoked", error.message());
+    assert_eq!(Some("g"), error.location().unwrap().module());
+
+    let error = super::compile_with_loader("use.f begin exec.f::d::foo end", &loader).err().unwrap();
+    assert_eq!("procedure f::d::foo is not defined; procedures must be defined before they are invoked", error.message());
+
+    let error = super::compile("use.std::math begin exec.math::foo end").err().unwrap();
+    assert_eq!("procedure math::foo is not defined; procedures must be defined before they are invoked", error.message());
+
+    let error = super::compile("use.std:math begin add end").err().unwrap();
+    assert_eq!("invalid use statement 'use.std:math': module path must consist of names separated by ::", error.message());
+
+    let error = super::compile("begin use.std::math add end").err().unwrap();
+    assert_eq!("modules can be imported only before the 'begin' instruction", error.message());
+    assert_eq!(1, error.step());
+}
+
+#[test]
+fn module_error_location() {
+    let mut loader = HashMap::new();
+    loader.insert(String::from("c"), String::from("proc.foo add end\nbegin add end"));
+
+    // errors in modules are reported at their location in the module
+    let error = super::compile_with_loader("\nuse.c begin add end", &loader).err().unwrap();
+    assert_eq!("a module can contain only use statements, constants, and procedures", error.message());
+    let location = error.location().unwrap();
+    assert_eq!(Some("c"), location.module());
+    assert_eq!((2, 1), (location.line(), location.column()));
+
+    let expected = "\
+        assembly error in module c at line 2, column 1: a module can contain only use statements, constants, and procedures\n  \
+          |\n\
+        2 | begin add end\n  \
+          | ^^^^^";
+    assert_eq!(expected, format!("{}", error));
+}
+
+// COMMENTS
+// ================================================================================================
+#[test]
+fn comments() {
+    let source = "\
+        // computes 1 + 2\n\
+        begin\n\
+            push.1  // stack state: 1\n\
+            push.2  /* stack state: 2 1 */ add\n\
+            /* the result is\n\
+               left on the stack */\n\
+        end // done";
+    let program = super::compile(source).unwrap();
+    let expected = super::compile("begin push.1 push.2 add end").unwrap();
+    assert_eq!(format!("{:?}", expected), format!("{:?}", program));
+    assert_eq!(expected.hash(), program.hash());
+}
+
+#[test]
+fn comments_in_error_location() {
+    // locations of errors account for skipped comments
+    let source = "begin /* a\n */ push.1 // b\n  pushh.2 end";
+    let error = super::compile(source).err().unwrap();
+    assert_eq!(2, error.step());
+    let location = error.location().unwrap();
+    assert_eq!((3, 3), (location.line(), location.column()));
+
+    // unterminated block comments are reported at the comment start
+    let error = super::compile("begin push.1 /* add\nend").err().unwrap();
+    assert_eq!("block comment without matching */", error.message());
+    let location = error.location().unwrap();
+    assert_eq!((1, 14), (location.line(), location.column()));
+}
+
+// ERRORS
+// ================================================================================================
+#[test]
+fn error_location() {
+    let source = "begin\n    push.1\n    pushh.2 add\nend";
+    let error = super::compile(source).err().unwrap();
+    assert_eq!("instruction pushh.2 is invalid", error.message());
+    assert_eq!(2, error.step());
+
+    let location = error.location().unwrap();
+    assert_eq!(3, location.line());
+    assert_eq!(5, location.column());
+    assert_eq!("    pushh.2 add", location.source_line());
+
+    let expected = "\
+        assembly error at line 3, column 5: instruction pushh.2 is invalid\n  \
+          |\n\
+        3 |     pushh.2 add\n  \
+          |     ^^^^^^^";
+    assert_eq!(expected, format!("{}", error));
+}
+
+#[test]
+fn error_location_of_block() {
+    // empty blocks are reported at the block head
+    let source = "begin\n    push.1\n\tif.true\n\tend\nend";
+    let error = super::compile(source).err().unwrap();
+    assert_eq!("a program block must contain at least one instruction", error.message());
+    let location = error.location().unwrap();
+    assert_eq!((3, 2), (location.line(), location.column()));
+
+    // tabs are preserved in front of the carets
+    assert!(format!("{}", error).ends_with("3 | \tif.true\n  | \t^^^^^^^"));
+
+    // dangling instructions are reported at the first instruction after the program end
+    let error = super::compile("begin add end\nadd end").err().unwrap();
+    let location = error.location().unwrap();
+    assert_eq!((2, 1), (location.line(), location.column()));
+}
+
+#[test]
+fn error_without_location() {
+    let error = super::compile(" \n ").err().unwrap();
+    assert!(error.location().is_none());
+    assert_eq!("assembly error: a program must contain at least one instruction", format!("{}", error));
+
+    // assembly errors can be used as standard errors
+    let error: Box<dyn std::error::Error> = Box::new(error);
+    assert_eq!("assembly error: a program must contain at least one instruction", error.to_string());
+}
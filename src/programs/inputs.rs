@@ -24,6 +24,19 @@ impl ProgramInputs {
         };
     }
 
+    /// Returns `ProgramInputs` for a program which reads a public `sequence` of any length from
+    /// input tape A. The stack is initialized with [0, n], where n is the length of the sequence
+    /// (this is the initial digest of the sequence absorbed by `std::io::read_absorb`), and the
+    /// sequence, followed by a 0 if its length is odd, is placed onto tape A before `secret_a`.
+    pub fn from_sequence(sequence: &[u128], secret_a: &[u128], secret_b: &[u128]) -> ProgramInputs {
+        let mut tape_a = sequence.to_vec();
+        if tape_a.len() % 2 == 1 {
+            tape_a.push(0);
+        }
+        tape_a.extend_from_slice(secret_a);
+        return ProgramInputs::new(&[0, sequence.len() as u128], &tape_a, secret_b);
+    }
+
     /// Returns `ProgramInputs` with public and secret input tapes set to empty vectors.
     pub fn none() -> ProgramInputs {
         return ProgramInputs {
@@ -13,6 +13,9 @@ use blocks::{ ProgramBlock, Span, Group, Switch, Loop };
 mod inputs;
 pub use inputs::{ ProgramInputs };
 
+mod outputs;
+pub use outputs::{ OutputSpec };
+
 mod hashing;
 use hashing::{ hash_op, hash_acc, hash_seq };
 
@@ -0,0 +1,33 @@
+use serde::{ Serialize, Deserialize };
+
+/// Specifies which items of the final stack state are returned as program outputs. Stack
+/// positions are counted from the top of the stack; the top of the stack is at position 0.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum OutputSpec {
+    /// The specified number of items from the top of the stack.
+    Top(usize),
+    /// Items located at the specified stack positions, in the specified order; a position can
+    /// be specified only once.
+    Positions(Vec<usize>),
+    /// All items of the stack. The number of returned items is equal to the number of items on
+    /// the stack at the end of execution, and the verifier binds both the values of the items
+    /// and the number of items on the stack.
+    Stack,
+}
+
+impl OutputSpec {
+
+    /// Returns stack positions of the outputs for the final stack which holds `stack_size` items.
+    pub fn positions(&self, stack_size: usize) -> Vec<usize> {
+        return match self {
+            OutputSpec::Top(num_outputs) => (0..*num_outputs).collect(),
+            OutputSpec::Positions(positions) => positions.clone(),
+            OutputSpec::Stack => (0..stack_size).collect(),
+        };
+    }
+
+    /// Returns true if the outputs bind the number of items on the final stack.
+    pub fn binds_stack_size(&self) -> bool {
+        return *self == OutputSpec::Stack;
+    }
+}
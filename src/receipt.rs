@@ -0,0 +1,153 @@
+use serde::{ Serialize, Deserialize };
+use crate::stark::{ StarkProof, ByteWriter, ByteReader, DeserializationError, VerificationError };
+use crate::OutputSpec;
+
+// CONSTANTS
+// ================================================================================================
+
+/// Version of the execution receipt format; this should be incremented whenever a change to
+/// the receipt or to any of its components (including `StarkProof`) changes their encoding.
+pub const RECEIPT_FORMAT_VERSION: u8 = 2;
+
+// tags of output spec variants in the binary receipt format
+const OUTPUT_SPEC_TOP       : u8 = 0;
+const OUTPUT_SPEC_POSITIONS : u8 = 1;
+const OUTPUT_SPEC_STACK     : u8 = 2;
+
+// TYPES AND INTERFACES
+// ================================================================================================
+
+/// A self-contained record of program execution which can be archived and verified later. In
+/// addition to the proof, a receipt carries the statement being proven (program hash, public
+/// inputs, output spec, and outputs), as well as the version of the format with which it was
+/// created. Stack positions of the outputs, and whether the outputs bind the final stack size,
+/// are derived from the output spec.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ExecutionReceipt {
+    version         : u8,
+    program_hash    : [u8; 32],
+    public_inputs   : Vec<u128>,
+    output_spec     : OutputSpec,
+    outputs         : Vec<u128>,
+    proof           : StarkProof,
+}
+
+// EXECUTION RECEIPT IMPLEMENTATION
+// ================================================================================================
+impl ExecutionReceipt {
+
+    /// Returns a new receipt for the execution of a program with the specified `program_hash`
+    /// against `public_inputs` which resulted in `outputs` taken from the top of the stack; the
+    /// receipt is tagged with the current version of the receipt format.
+    pub fn new(program_hash: &[u8; 32], public_inputs: &[u128], outputs: &[u128], proof: StarkProof) -> ExecutionReceipt {
+        let output_spec = OutputSpec::Top(outputs.len());
+        return ExecutionReceipt::with_outputs(program_hash, public_inputs, &output_spec, outputs, proof);
+    }
+
+    /// Returns a new receipt in the same way as `new()`, but for `outputs` selected from the
+    /// final stack by `output_spec`.
+    pub fn with_outputs(
+        program_hash    : &[u8; 32],
+        public_inputs   : &[u128],
+        output_spec     : &OutputSpec,
+        outputs         : &[u128],
+        proof           : StarkProof) -> ExecutionReceipt
+    {
+        return ExecutionReceipt {
+            version         : RECEIPT_FORMAT_VERSION,
+            program_hash    : *program_hash,
+            public_inputs   : public_inputs.to_vec(),
+            output_spec     : output_spec.clone(),
+            outputs         : outputs.to_vec(),
+            proof,
+        };
+    }
+
+    pub fn version(&self) -> u8 {
+        return self.version;
+    }
+
+    pub fn program_hash(&self) -> &[u8; 32] {
+        return &self.program_hash;
+    }
+
+    pub fn public_inputs(&self) -> &[u128] {
+        return &self.public_inputs;
+    }
+
+    pub fn output_spec(&self) -> &OutputSpec {
+        return &self.output_spec;
+    }
+
+    pub fn outputs(&self) -> &[u128] {
+        return &self.outputs;
+    }
+
+    pub fn proof(&self) -> &StarkProof {
+        return &self.proof;
+    }
+
+    // SERIALIZATION
+    // -------------------------------------------------------------------------------------------
+
+    /// Serializes the receipt into a binary format. The version of the receipt is written first,
+    /// and the proof is written using the format of `StarkProof::to_bytes()`; returns an error
+    /// if the proof does not pass `StarkProof::validate()`.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, VerificationError> {
+        let mut target = ByteWriter::new();
+        target.write_u8(self.version);
+        target.write_digest(&self.program_hash);
+        target.write_usize(self.public_inputs.len());
+        target.write_elements(&self.public_inputs);
+        match &self.output_spec {
+            OutputSpec::Top(num_outputs) => {
+                target.write_u8(OUTPUT_SPEC_TOP);
+                target.write_usize(*num_outputs);
+            },
+            OutputSpec::Positions(positions) => {
+                target.write_u8(OUTPUT_SPEC_POSITIONS);
+                target.write_usize(positions.len());
+                positions.iter().for_each(|&position| target.write_usize(position));
+            },
+            OutputSpec::Stack => target.write_u8(OUTPUT_SPEC_STACK),
+        }
+        target.write_usize(self.outputs.len());
+        target.write_elements(&self.outputs);
+        self.proof.write_into(&mut target)?;
+        return Ok(target.into_bytes());
+    }
+
+    /// Parses a receipt serialized with `ExecutionReceipt::to_bytes()`. The version of the
+    /// receipt is checked before anything else is decoded, and so receipts created with other
+    /// versions of the format are rejected with `DeserializationError::UnsupportedVersion`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<ExecutionReceipt, DeserializationError> {
+        let mut source = ByteReader::new(bytes);
+        let version = source.read_u8()?;
+        if version != RECEIPT_FORMAT_VERSION {
+            return Err(DeserializationError::UnsupportedVersion { version, expected: RECEIPT_FORMAT_VERSION });
+        }
+
+        let program_hash = source.read_digest()?;
+        let num_inputs = source.read_length(16)?;
+        let public_inputs = source.read_elements(num_inputs)?;
+        let output_spec = match source.read_u8()? {
+            OUTPUT_SPEC_TOP => OutputSpec::Top(source.read_usize()?),
+            OUTPUT_SPEC_POSITIONS => {
+                let num_positions = source.read_length(1)?;
+                let mut positions = Vec::with_capacity(num_positions);
+                for _ in 0..num_positions {
+                    positions.push(source.read_usize()?);
+                }
+                OutputSpec::Positions(positions)
+            },
+            OUTPUT_SPEC_STACK => OutputSpec::Stack,
+            tag => return Err(DeserializationError::InvalidValue(format!("output spec {}", tag))),
+        };
+        let num_outputs = source.read_length(16)?;
+        let outputs = source.read_elements(num_outputs)?;
+        let proof = StarkProof::read_from(&mut source)?;
+        source.finish()?;
+
+        return Ok(ExecutionReceipt { version, program_hash, public_inputs, output_spec, outputs, proof });
+    }
+}
@@ -0,0 +1,41 @@
+// TYPES AND INTERFACES
+// ================================================================================================
+
+/// Settings which affect how a proof is generated, but not the proof itself. Unlike
+/// `ProofOptions`, these settings are not recorded in the proof, and proofs generated with
+/// different configurations (but the same options) are identical.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ProverConfig {
+    num_threads : usize,
+}
+
+// PROVER CONFIG IMPLEMENTATION
+// ================================================================================================
+impl ProverConfig {
+
+    /// Returns a configuration which uses up to `num_threads` threads in all stages of proof
+    /// generation: trace extension, Merkle tree hashing, constraint evaluation, composition
+    /// polynomial construction, FRI layer reduction, and proof-of-work grinding.
+    pub fn new(num_threads: usize) -> ProverConfig {
+        assert!(num_threads > 0, "num_threads must be greater than 0");
+        return ProverConfig { num_threads };
+    }
+
+    /// Returns a configuration which generates proofs in the current thread only.
+    pub fn single_threaded() -> ProverConfig {
+        return ProverConfig::new(1);
+    }
+
+    pub fn num_threads(&self) -> usize {
+        return self.num_threads;
+    }
+}
+
+impl Default for ProverConfig {
+
+    /// Returns a configuration which uses as many threads as there are available CPU cores.
+    fn default() -> ProverConfig {
+        let num_threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        return ProverConfig::new(num_threads);
+    }
+}
@@ -1,42 +1,46 @@
 use crate::math::{ field, polynom, parallel };
-use crate::stark::{ MAX_CONSTRAINT_DEGREE, utils::CompositionCoefficients };
+use crate::stark::{ utils::CompositionCoefficients };
 
 // TYPES AND INTERFACES
 // ================================================================================================
 pub struct ConstraintPoly {
-    poly: Vec<u128>
+    poly        : Vec<u128>,
+    trace_length: usize,
 }
 
 // CONSTRAINT POLY IMPLEMENTATION
 // ================================================================================================
 impl ConstraintPoly {
-    pub fn new(poly: Vec<u128>) -> ConstraintPoly {
+    pub fn new(poly: Vec<u128>, trace_length: usize) -> ConstraintPoly {
 
         assert!(poly.len().is_power_of_two(), "poly length must be a power of two");
-        debug_assert!(get_expected_degree(&poly) == polynom::degree_of(&poly),
+        debug_assert!(get_expected_degree(&poly, trace_length) == polynom::degree_of(&poly),
             "expected polynomial of degree {} but received degree {}",
-            get_expected_degree(&poly),
+            get_expected_degree(&poly, trace_length),
             polynom::degree_of(&poly));
 
-        return ConstraintPoly { poly };
+        return ConstraintPoly { poly, trace_length };
     }
 
     pub fn degree(&self) -> usize {
-        return get_expected_degree(&self.poly);
+        return get_expected_degree(&self.poly, self.trace_length);
     }
 
-    pub fn eval(&self, twiddles: &[u128]) -> Vec<u128> {
+    /// Evaluates the polynomial over the domain defined by `twiddles` using up to `num_threads`
+    /// threads.
+    pub fn eval(&self, twiddles: &[u128], num_threads: usize) -> Vec<u128> {
         let domain_size = twiddles.len() * 2;
         assert!(domain_size > self.poly.len(), "domain size must be greater than poly length");
 
         let mut evaluations = vec![field::ZERO; domain_size];
         evaluations[..self.poly.len()].copy_from_slice(&self.poly);
-        polynom::eval_fft_twiddles(&mut evaluations, twiddles, true);
+        let num_threads = parallel::get_num_batches(domain_size, num_threads);
+        polynom::eval_fft_twiddles_par(&mut evaluations, twiddles, true, num_threads);
 
         return evaluations;
     }
 
-    pub fn merge_into(mut self, result: &mut Vec<u128>, z: u128, cc: &CompositionCoefficients) -> u128 {
+    pub fn merge_into(mut self, result: &mut Vec<u128>, z: u128, cc: &CompositionCoefficients, num_threads: usize) -> u128 {
 
         // evaluate the polynomial at point z
         let z_value = polynom::eval(&self.poly, z);
@@ -46,7 +50,8 @@ impl ConstraintPoly {
         polynom::syn_div_in_place(&mut self.poly, z);
 
         // add C(x) * cc into the result
-        parallel::mul_acc(result, &self.poly, cc.constraints, 1);
+        let num_threads = parallel::get_num_batches(result.len(), num_threads);
+        parallel::mul_acc(result, &self.poly, cc.constraints, num_threads);
 
         return z_value;
     }
@@ -55,7 +60,8 @@ impl ConstraintPoly {
 
 // HELPER FUNCTIONS
 // ================================================================================================
-fn get_expected_degree(poly: &[u128]) -> usize {
-    let trace_length = poly.len() / MAX_CONSTRAINT_DEGREE;
+/// Constraint polynomial is evaluated over a domain of MAX_CONSTRAINT_DEGREE * poly_length
+/// elements, and its degree is reduced by the degree of the trace domain divisor.
+fn get_expected_degree(poly: &[u128], trace_length: usize) -> usize {
     return poly.len() - trace_length;
 }
\ No newline at end of file
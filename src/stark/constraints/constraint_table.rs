@@ -10,19 +10,46 @@ pub struct ConstraintTable {
     i_evaluations   : Vec<u128>,    // combined evaluations of boundary constraints at the first step
     f_evaluations   : Vec<u128>,    // combined evaluations of boundary constraints at the last step
     t_evaluations   : Vec<u128>,    // combined evaluations of transition constraints
+    c_evaluations   : Vec<Vec<u128>>,   // evaluations of individual transition constraints (debug mode only)
+}
+
+/// A range of consecutive steps of a `ConstraintTable`; fragments of the same table can be
+/// evaluated independently (e.g. in separate threads), and their evaluations are written
+/// directly into the table they were split from.
+pub struct ConstraintTableFragment<'a> {
+    evaluator       : &'a ConstraintEvaluator,
+    offset          : usize,
+    i_evaluations   : &'a mut [u128],
+    f_evaluations   : &'a mut [u128],
+    t_evaluations   : &'a mut [u128],
+    c_evaluations   : Vec<&'a mut [u128]>,
+    t_buffer        : Vec<u128>,        // evaluations of individual transition constraints at one step
 }
 
 // CONSTRAINT TABLE IMPLEMENTATION
 // ================================================================================================
 impl ConstraintTable {
-    pub fn new(trace: &TraceTable, trace_root: &[u8; 32], inputs: &[u128], outputs: &[u128]) -> ConstraintTable {
-        let evaluator = ConstraintEvaluator::from_trace(trace, trace_root, inputs, outputs);
+    pub fn new(trace: &TraceTable, seed: &[u8; 32], hide_op_count: bool, inputs: &[u128], outputs: &[u128], output_positions: &[usize], stack_size: Option<usize>)
+        -> ConstraintTable
+    {
+        let evaluator = ConstraintEvaluator::from_trace(trace, seed, hide_op_count, inputs, outputs, output_positions, stack_size);
         let evaluation_domain_size = evaluator.domain_size();
+
+        // if we are in debug mode, initialize vectors to hold individual evaluations
+        // of transition constraints
+        let c_evaluations = if cfg!(debug_assertions) {
+            (0..evaluator.transition_constraint_count()).map(|_| uninit_vector(evaluation_domain_size)).collect()
+        }
+        else {
+            Vec::new()
+        };
+
         return ConstraintTable {
             evaluator       : evaluator,
             i_evaluations   : uninit_vector(evaluation_domain_size),
             f_evaluations   : uninit_vector(evaluation_domain_size),
             t_evaluations   : uninit_vector(evaluation_domain_size),
+            c_evaluations   : c_evaluations,
         };
     }
 
@@ -31,7 +58,7 @@ impl ConstraintTable {
         return self.evaluator.constraint_count();
     }
 
-    /// Returns the size of the evaluation domain = trace_length * MAX_CONSTRAINT_DEGREE
+    /// Returns the size of the evaluation domain = poly_length * MAX_CONSTRAINT_DEGREE
     pub fn evaluation_domain_size(&self) -> usize {
         return self.evaluator.domain_size();
     }
@@ -41,17 +68,41 @@ impl ConstraintTable {
         return self.evaluator.trace_length();
     }
 
-    /// Evaluates transition and boundary constraints at the specified step.
-    pub fn evaluate(&mut self, current: &TraceState, next: &TraceState, x: u128, step: usize) {
-        let (init_bound, last_bound) = self.evaluator.evaluate_boundaries(current, x);
-        self.i_evaluations[step] = init_bound;
-        self.f_evaluations[step] = last_bound;
-        self.t_evaluations[step] = self.evaluator.evaluate_transition(current, next, x, step);
+    /// Splits the table into `num_fragments` fragments of equal size covering consecutive
+    /// ranges of steps.
+    pub fn fragments(&mut self, num_fragments: usize) -> Vec<ConstraintTableFragment<'_>> {
+        let domain_size = self.evaluation_domain_size();
+        assert!(num_fragments > 0, "number of fragments must be greater than 0");
+        assert!(domain_size % num_fragments == 0, "domain size must be divisible by number of fragments");
+        let fragment_size = domain_size / num_fragments;
+
+        // split evaluations of individual transition constraints so that every fragment gets
+        // the same range of steps from each constraint
+        let mut c_evaluations: Vec<Vec<&mut [u128]>> = (0..num_fragments).map(|_| Vec::new()).collect();
+        for column in self.c_evaluations.iter_mut() {
+            for (i, chunk) in column.chunks_mut(fragment_size).enumerate() {
+                c_evaluations[i].push(chunk);
+            }
+        }
+
+        let evaluator = &self.evaluator;
+        let t_constraint_count = evaluator.transition_constraint_count();
+        return self.i_evaluations.chunks_mut(fragment_size)
+            .zip(self.f_evaluations.chunks_mut(fragment_size))
+            .zip(self.t_evaluations.chunks_mut(fragment_size))
+            .zip(c_evaluations)
+            .enumerate()
+            .map(|(i, (((i_evaluations, f_evaluations), t_evaluations), c_evaluations))| ConstraintTableFragment {
+                evaluator, offset: i * fragment_size, i_evaluations, f_evaluations, t_evaluations, c_evaluations,
+                t_buffer: vec![field::ZERO; t_constraint_count],
+            })
+            .collect();
     }
 
     /// Interpolates all constraint evaluations into polynomials and combines all these 
-    /// polynomials into a single polynomial using pseudo-random linear combination.
-    pub fn combine_polys(mut self) -> ConstraintPoly
+    /// polynomials into a single polynomial using pseudo-random linear combination. The work
+    /// is distributed across up to `num_threads` threads.
+    pub fn combine_polys(mut self, num_threads: usize) -> ConstraintPoly
     {
         let combination_root = field::get_root_of_unity(self.evaluation_domain_size());
         let inv_twiddles = fft::get_inv_twiddles(combination_root, self.evaluation_domain_size());
@@ -60,43 +111,44 @@ impl ConstraintTable {
         self.validate_transition_degrees();
         
         let mut combined_poly = uninit_vector(self.evaluation_domain_size());
+        let num_threads = parallel::get_num_batches(self.evaluation_domain_size(), num_threads);
         
         // 1 ----- boundary constraints for the initial step --------------------------------------
         // interpolate initial step boundary constraint combination into a polynomial, divide the 
         // polynomial by Z(x) = (x - 1), and add it to the result
-        polynom::interpolate_fft_twiddles(&mut self.i_evaluations, &inv_twiddles, true);
+        polynom::interpolate_fft_twiddles_par(&mut self.i_evaluations, &inv_twiddles, true, num_threads);
         polynom::syn_div_in_place(&mut self.i_evaluations, field::ONE);
         combined_poly.copy_from_slice(&self.i_evaluations);
 
         // 2 ----- boundary constraints for the final step ----------------------------------------
         // interpolate final step boundary constraint combination into a polynomial, divide the 
         // polynomial by Z(x) = (x - x_at_last_step), and add it to the result
-        polynom::interpolate_fft_twiddles(&mut self.f_evaluations, &inv_twiddles, true);
+        polynom::interpolate_fft_twiddles_par(&mut self.f_evaluations, &inv_twiddles, true, num_threads);
         let x_at_last_step = self.evaluator.get_x_at_last_step();
         polynom::syn_div_in_place(&mut self.f_evaluations, x_at_last_step);
-        parallel::add_in_place(&mut combined_poly, &self.f_evaluations, 1);
+        parallel::add_in_place(&mut combined_poly, &self.f_evaluations, num_threads);
 
         // 3 ----- transition constraints ---------------------------------------------------------
         // interpolate transition constraint combination into a polynomial, divide the polynomial
         // by Z(x) = (x^steps - 1) / (x - x_at_last_step), and add it to the result
         let trace_length = self.trace_length();
-        polynom::interpolate_fft_twiddles(&mut self.t_evaluations, &inv_twiddles, true);
+        polynom::interpolate_fft_twiddles_par(&mut self.t_evaluations, &inv_twiddles, true, num_threads);
         polynom::syn_div_expanded_in_place(&mut self.t_evaluations, trace_length, &[x_at_last_step]);
-        parallel::add_in_place(&mut combined_poly, &self.t_evaluations, 1);
+        parallel::add_in_place(&mut combined_poly, &self.t_evaluations, num_threads);
 
-        return ConstraintPoly::new(combined_poly);
+        return ConstraintPoly::new(combined_poly, trace_length);
     }
 
     #[cfg(debug_assertions)]
     fn validate_transition_degrees(&self) {
-        let trace_degree = self.evaluator.trace_length() - 1;
+        let trace_degree = self.evaluator.poly_length() - 1;
         let mut expected_degrees = self.evaluator.get_transition_degrees();
         for i in 0..expected_degrees.len() {
             expected_degrees[i] = expected_degrees[i] * trace_degree;
         }
 
         let mut actual_degrees = Vec::new();
-        let transition_evaluations = self.evaluator.get_transition_evaluations();
+        let transition_evaluations = &self.c_evaluations;
         for i in 0..transition_evaluations.len() {
             let degree = crate::math::polynom::infer_degree(&transition_evaluations[i]);
             actual_degrees.push(degree);
@@ -110,4 +162,35 @@ impl ConstraintTable {
             }
         }
     }
-}
\ No newline at end of file
+}
+// CONSTRAINT TABLE FRAGMENT IMPLEMENTATION
+// ================================================================================================
+impl <'a> ConstraintTableFragment<'a> {
+
+    /// Returns the first step of the table covered by this fragment.
+    pub fn offset(&self) -> usize {
+        return self.offset;
+    }
+
+    /// Returns the number of steps covered by this fragment.
+    pub fn size(&self) -> usize {
+        return self.t_evaluations.len();
+    }
+
+    /// Evaluates transition and boundary constraints at the specified step of the table; the
+    /// step must be within the range covered by this fragment.
+    pub fn evaluate(&mut self, current: &TraceState, next: &TraceState, x: u128, step: usize) {
+        let (init_bound, last_bound) = self.evaluator.evaluate_boundaries(current, x);
+        let i = step - self.offset;
+        self.i_evaluations[i] = init_bound;
+        self.f_evaluations[i] = last_bound;
+
+        self.t_evaluations[i] = self.evaluator.evaluate_transition(current, next, x, step, &mut self.t_buffer);
+
+        // when in debug mode, save evaluations of individual transition constraints before
+        // they are combined
+        for (column, &evaluation) in self.c_evaluations.iter_mut().zip(self.t_buffer.iter()) {
+            column[i] = evaluation;
+        }
+    }
+}
@@ -456,6 +456,7 @@ mod tests {
 
         state.extend_from_slice(ctx_stack);
         state.extend_from_slice(loop_stack);
+        state.push(1);   // stack size
         state.push(101); // single value for user stack
 
         return TraceState::from_vec(ctx_depth, loop_depth, 1, &state);
@@ -56,6 +56,7 @@ pub const NUM_STATIC_DECODER_CONSTRAINTS: usize =
 
 // TYPES AND INTERFACES
 // ================================================================================================
+#[derive(Clone)]
 pub struct Decoder {
     ctx_depth           : usize,
     loop_depth          : usize,
@@ -74,15 +75,6 @@ impl Decoder {
 
     pub fn new(trace_length: usize, extension_factor: usize, ctx_depth: usize, loop_depth: usize) -> Decoder 
     {
-        // build an array of constraint degrees for the decoder
-        let mut degrees = Vec::from(&OP_CONSTRAINT_DEGREES[..]);
-        degrees.extend_from_slice(&SPONGE_CONSTRAINT_DEGREES[..]);
-        degrees.push(LOOP_IMAGE_CONSTRAINT_DEGREE);
-        degrees.resize(degrees.len()
-            + cmp::max(ctx_depth, MIN_CONTEXT_DEPTH)
-            + cmp::max(loop_depth, MIN_LOOP_DEPTH),
-            STACK_CONSTRAINT_DEGREE);
-
         // determine extended cycle length
         let cycle_length = BASE_CYCLE_LENGTH * extension_factor;
 
@@ -99,7 +91,17 @@ impl Decoder {
             trace_length, cycle_length,
             ark_values, ark_polys,
             mask_values, mask_polys,
-            constraint_degrees: degrees,
+            constraint_degrees: build_constraint_degrees(ctx_depth, loop_depth),
+        };
+    }
+
+    /// Returns a decoder evaluator for traces of the specified shape which re-uses the extended
+    /// constants of this evaluator; the constants depend only on the extension factor.
+    pub fn with_shape(&self, trace_length: usize, ctx_depth: usize, loop_depth: usize) -> Decoder {
+        return Decoder {
+            ctx_depth, loop_depth, trace_length,
+            constraint_degrees: build_constraint_degrees(ctx_depth, loop_depth),
+            ..self.clone()
         };
     }
 
@@ -190,6 +192,17 @@ impl Decoder {
 
 // HELPER FUNCTIONS
 // ================================================================================================
+fn build_constraint_degrees(ctx_depth: usize, loop_depth: usize) -> Vec<usize> {
+    let mut degrees = Vec::from(&OP_CONSTRAINT_DEGREES[..]);
+    degrees.extend_from_slice(&SPONGE_CONSTRAINT_DEGREES[..]);
+    degrees.push(LOOP_IMAGE_CONSTRAINT_DEGREE);
+    degrees.resize(degrees.len()
+        + cmp::max(ctx_depth, MIN_CONTEXT_DEPTH)
+        + cmp::max(loop_depth, MIN_LOOP_DEPTH),
+        STACK_CONSTRAINT_DEGREE);
+    return degrees;
+}
+
 fn transpose_ark_constants(constants: Vec<Vec<u128>>, cycle_length: usize) -> Vec<[u128; 2 * SPONGE_WIDTH]>
 {
     let mut values = Vec::new();
@@ -1,7 +1,6 @@
 use crate::{
     math::field,
-    utils::uninit_vector,
-    stark::{ StarkProof, TraceTable, TraceState, ConstraintCoefficients },
+    stark::{ StarkProof, TraceTable, TraceState, ConstraintCoefficients, utils },
     PROGRAM_DIGEST_SIZE,
 };
 use super::{ decoder::Decoder, stack::Stack, super::MAX_CONSTRAINT_DEGREE };
@@ -15,31 +14,52 @@ pub struct Evaluator {
     coefficients    : ConstraintCoefficients,
     domain_size     : usize,
     extension_factor: usize,
+    poly_length     : usize,
 
     t_constraint_num: usize,
     t_degree_groups : Vec<(u128, Vec<usize>)>,
-    t_evaluations   : Vec<Vec<u128>>,
 
     b_constraint_num: usize,
     program_hash    : Vec<u128>,
-    op_count        : u128,
+    op_count        : Option<u128>,
     inputs          : Vec<u128>,
     outputs         : Vec<u128>,
+    output_positions: Vec<usize>,
+    stack_size      : Option<u128>,
     b_degree_adj    : u128,
 }
 
+/// Decoder and stack constraint evaluators for execution traces of a given shape. Building these
+/// requires extending round constants over the constraint evaluation domain; since the result
+/// depends only on the shape of the trace, it can be shared between proofs of the same shape.
+#[derive(Clone)]
+pub struct EvaluatorTemplate {
+    decoder         : Decoder,
+    stack           : Stack,
+    shape           : TraceShape,
+}
+
+/// Trace length, extension factor, and context, loop, and stack depths of an execution trace.
+pub type TraceShape = (usize, usize, usize, usize, usize);
+
 // EVALUATOR IMPLEMENTATION
 // ================================================================================================
 impl Evaluator {
 
-    pub fn from_trace(trace: &TraceTable, trace_root: &[u8; 32], inputs: &[u128], outputs: &[u128]) -> Evaluator
+    pub fn from_trace(trace: &TraceTable, seed: &[u8; 32], hide_op_count: bool, inputs: &[u128], outputs: &[u128], output_positions: &[usize], stack_size: Option<usize>)
+        -> Evaluator
     {
         let last_state = trace.get_last_state();
         let ctx_depth = trace.ctx_depth();
         let loop_depth = trace.loop_depth();
         let stack_depth = trace.stack_depth();
         let trace_length = trace.unextended_length();
-        let extension_factor = MAX_CONSTRAINT_DEGREE;
+        let poly_length = trace.poly_length();
+        let op_count = if hide_op_count { None } else { Some(last_state.op_counter()) };
+
+        // constraints are evaluated over a domain large enough to hold constraint polynomials
+        // of degree MAX_CONSTRAINT_DEGREE (this domain is larger for randomized traces)
+        let extension_factor = MAX_CONSTRAINT_DEGREE * poly_length / trace_length;
 
         // instantiate decoder and stack constraint evaluators 
         let decoder = Decoder::new(trace_length, extension_factor, ctx_depth, loop_depth);
@@ -50,45 +70,44 @@ impl Evaluator {
             decoder.constraint_degrees(), stack.constraint_degrees()
         ].concat();
 
-        // if we are in debug mode, initialize vectors to hold individual evaluations
-        // of transition constraints
         let domain_size = trace_length * extension_factor;
-        let t_evaluations = if cfg!(debug_assertions) {
-            t_constraint_degrees.iter().map(|_| uninit_vector(domain_size)).collect()
-        }
-        else {
-            Vec::new()
-        };
-
         return Evaluator {
             decoder         : decoder,
             stack           : stack,
-            coefficients    : ConstraintCoefficients::new(*trace_root, ctx_depth, loop_depth, stack_depth),
+            coefficients    : ConstraintCoefficients::new(*seed, ctx_depth, loop_depth, stack_depth),
             domain_size     : domain_size,
             extension_factor: extension_factor,
+            poly_length     : poly_length,
             t_constraint_num: t_constraint_degrees.len(),
-            t_degree_groups : group_transition_constraints(t_constraint_degrees, trace_length),
-            t_evaluations   : t_evaluations,
-            b_constraint_num: get_boundary_constraint_num(&inputs, &outputs),
+            t_degree_groups : group_transition_constraints(t_constraint_degrees, trace_length, poly_length),
+            b_constraint_num: get_boundary_constraint_num(&inputs, &outputs, op_count.is_some(), stack_size.is_some()),
             program_hash    : last_state.program_hash().to_vec(),
-            op_count        : last_state.op_counter(),
+            op_count        : op_count,
             inputs          : inputs.to_vec(),
             outputs         : outputs.to_vec(),
-            b_degree_adj    : get_boundary_constraint_adjustment_degree(trace_length),
+            output_positions: output_positions.to_vec(),
+            stack_size      : stack_size.map(|size| size as u128),
+            b_degree_adj    : get_boundary_constraint_adjustment_degree(trace_length, poly_length),
         };
     }
 
-    pub fn from_proof(proof: &StarkProof, program_hash: &[u8; 32], inputs: &[u128], outputs: &[u128]) -> Evaluator
+    /// Returns an evaluator for the `proof`; decoder and stack constraint evaluators are copied
+    /// from the `template`, which must have been built for the shape of the proof's trace.
+    pub fn from_proof(proof: &StarkProof, template: &EvaluatorTemplate, seed: &[u8; 32], program_hash: &[u8; 32], inputs: &[u128], outputs: &[u128], output_positions: &[usize], stack_size: Option<usize>)
+        -> Evaluator
     {
         let ctx_depth = proof.ctx_depth();
         let loop_depth = proof.loop_depth();
         let stack_depth = proof.stack_depth();
         let trace_length = proof.trace_length();
         let extension_factor = proof.options().extension_factor();
-        
-        // instantiate decoder and stack constraint evaluators 
-        let decoder = Decoder::new(trace_length, extension_factor, ctx_depth, loop_depth);
-        let stack = Stack::new(trace_length, extension_factor, stack_depth);
+        let poly_length = utils::get_trace_poly_length(trace_length, proof.options().zero_knowledge());
+        let op_count = if proof.options().hides_op_count() { None } else { Some(proof.op_count()) };
+
+        // copy decoder and stack constraint evaluators from the template
+        debug_assert!(template.shape == EvaluatorTemplate::get_shape(proof), "inconsistent evaluator template");
+        let decoder = template.decoder.clone();
+        let stack = template.stack.clone();
 
         // build a list of transition constraint degrees
         let t_constraint_degrees = [
@@ -98,18 +117,20 @@ impl Evaluator {
         return Evaluator {
             decoder         : decoder,
             stack           : stack,
-            coefficients    : ConstraintCoefficients::new(*proof.trace_root(), ctx_depth, loop_depth, stack_depth),
+            coefficients    : ConstraintCoefficients::new(*seed, ctx_depth, loop_depth, stack_depth),
             domain_size     : proof.domain_size(),
             extension_factor: extension_factor,
+            poly_length     : poly_length,
             t_constraint_num: t_constraint_degrees.len(),
-            t_degree_groups : group_transition_constraints(t_constraint_degrees, trace_length),
-            t_evaluations   : Vec::new(),
-            b_constraint_num: get_boundary_constraint_num(&inputs, &outputs),
+            t_degree_groups : group_transition_constraints(t_constraint_degrees, trace_length, poly_length),
+            b_constraint_num: get_boundary_constraint_num(&inputs, &outputs, op_count.is_some(), stack_size.is_some()),
             program_hash    : parse_program_hash(program_hash),
-            op_count        : proof.op_count(),
+            op_count        : op_count,
             inputs          : inputs.to_vec(),
             outputs         : outputs.to_vec(),
-            b_degree_adj    : get_boundary_constraint_adjustment_degree(trace_length),
+            output_positions: output_positions.to_vec(),
+            stack_size      : stack_size.map(|size| size as u128),
+            b_degree_adj    : get_boundary_constraint_adjustment_degree(trace_length, poly_length),
         };
     }
 
@@ -117,6 +138,10 @@ impl Evaluator {
         return self.t_constraint_num + self.b_constraint_num;
     }
 
+    pub fn transition_constraint_count(&self) -> usize {
+        return self.t_constraint_num;
+    }
+
     pub fn domain_size(&self) -> usize {
         return self.domain_size;
     }
@@ -125,6 +150,11 @@ impl Evaluator {
         return self.domain_size / self.extension_factor;
     }
 
+    /// Returns the number of coefficients in trace polynomials.
+    pub fn poly_length(&self) -> usize {
+        return self.poly_length;
+    }
+
     pub fn get_x_at_last_step(&self) -> u128 {
         let trace_root = field::get_root_of_unity(self.trace_length());
         return field::exp(trace_root, (self.trace_length() - 1) as u128);
@@ -136,17 +166,16 @@ impl Evaluator {
     /// Computes pseudo-random linear combination of transition constraints D_i at point x as:
     /// cc_{i * 2} * D_i + cc_{i * 2 + 1} * D_i * x^p for all i, where cc_j are the coefficients
     /// used in the linear combination and x^p is a degree adjustment factor (different for each degree).
-    pub fn evaluate_transition(&self, current: &TraceState, next: &TraceState, x: u128, step: usize) -> u128 {
-        
+    /// Evaluations of individual constraints are left in `evaluations`, which must have one
+    /// element per transition constraint; this lets callers re-use the buffer across steps.
+    pub fn evaluate_transition(&self, current: &TraceState, next: &TraceState, x: u128, step: usize, evaluations: &mut [u128]) -> u128 {
+
         // evaluate transition constraints
-        let mut evaluations = vec![field::ZERO; self.t_constraint_num];
-        self.decoder.evaluate(&current, &next, step, &mut evaluations);
+        debug_assert!(evaluations.len() == self.t_constraint_num, "inconsistent number of transition constraints");
+        evaluations.iter_mut().for_each(|evaluation| *evaluation = field::ZERO);
+        self.decoder.evaluate(&current, &next, step, evaluations);
         self.stack.evaluate(&current, &next, step, &mut evaluations[self.decoder.constraint_count()..]);
 
-        // when in debug mode, save transition evaluations before they are combined
-        #[cfg(debug_assertions)]
-        self.save_transition_evaluations(&evaluations, step);
-
         // if the constraints should evaluate to all zeros at this step,
         // make sure they do, and return
         if self.should_evaluate_to_zero_at(step) {
@@ -158,7 +187,7 @@ impl Evaluator {
         }
 
         // compute a pseudo-random linear combination of all transition constraints
-        return self.combine_transition_constraints(&evaluations, x);
+        return self.combine_transition_constraints(evaluations, x);
     }
 
     /// Computes pseudo-random liner combination of transition constraints at point x. This function
@@ -240,6 +269,11 @@ impl Evaluator {
             result_adj = field::add(result_adj, field::mul(loop_stack[i], cc.loop_stack[i * 2 + 1]));
         }
 
+        // make sure stack size is equal to the number of inputs
+        let val = field::sub(current.stack_size(), self.inputs.len() as u128);
+        i_result = field::add(i_result, field::mul(val, cc.stack_size[0]));
+        result_adj = field::add(result_adj, field::mul(val, cc.stack_size[1]));
+
         // make sure stack registers are set to inputs
         let user_stack = current.user_stack();
         for i in 0..self.inputs.len() {
@@ -257,10 +291,13 @@ impl Evaluator {
 
         let cc = &self.coefficients.f_boundary;
         
-        // make sure op_counter register is set to the claimed value of operations
-        let val = field::sub(current.op_counter(), self.op_count);
-        f_result = field::add(f_result, field::mul(val, cc.op_counter[0]));
-        result_adj = field::add(result_adj, field::mul(val, cc.op_counter[1]));
+        // make sure op_counter register is set to the claimed value of operations; when the
+        // op count is hidden, transition constraints still bound it by the trace length
+        if let Some(op_count) = self.op_count {
+            let val = field::sub(current.op_counter(), op_count);
+            f_result = field::add(f_result, field::mul(val, cc.op_counter[0]));
+            result_adj = field::add(result_adj, field::mul(val, cc.op_counter[1]));
+        }
 
         // make sure operation sponge contains program hash
         let program_hash = current.program_hash();
@@ -312,12 +349,19 @@ impl Evaluator {
             result_adj = field::add(result_adj, field::mul(loop_stack[i], cc.loop_stack[i * 2 + 1]));
         }
 
-        // make sure user stack registers are set to outputs
-        for i in 0..self.outputs.len() {
-            let val = field::sub(user_stack[i], self.outputs[i]);
+        // make sure stack size is set to the claimed number of items, if it was claimed
+        if let Some(stack_size) = self.stack_size {
+            let val = field::sub(current.stack_size(), stack_size);
+            f_result = field::add(f_result, field::mul(val, cc.stack_size[0]));
+            result_adj = field::add(result_adj, field::mul(val, cc.stack_size[1]));
+        }
+
+        // make sure user stack registers at output positions are set to outputs
+        for (&i, &output) in self.output_positions.iter().zip(self.outputs.iter()) {
+            let val = field::sub(user_stack[i], output);
             f_result = field::add(f_result, field::mul(val, cc.user_stack[i * 2]));
             result_adj = field::add(result_adj, field::mul(val, cc.user_stack[i * 2 + 1]));
-        }        
+        }
 
         // raise the degree of adjusted terms and sum all the terms together
         f_result = field::add(f_result, field::mul(result_adj, xp));
@@ -332,7 +376,7 @@ impl Evaluator {
             && (step != self.domain_size - self.extension_factor);
     }
 
-    fn combine_transition_constraints(&self, evaluations: &Vec<u128>, x: u128) -> u128 {
+    fn combine_transition_constraints(&self, evaluations: &[u128], x: u128) -> u128 {
         let cc = &self.coefficients.transition;
         let mut result = field::ZERO;
 
@@ -357,21 +401,6 @@ impl Evaluator {
         return result;
     }
 
-    #[cfg(debug_assertions)]
-    fn save_transition_evaluations(&self, evaluations: &[u128], step: usize) {
-        unsafe {
-            let mutable_self = &mut *(self as *const _ as *mut Evaluator);
-            for i in 0..evaluations.len() {
-                mutable_self.t_evaluations[i][step] = evaluations[i];
-            }
-        }
-    }
-
-    #[cfg(debug_assertions)]
-    pub fn get_transition_evaluations(&self) -> &Vec<Vec<u128>> {
-        return &self.t_evaluations;
-    }
-
     #[cfg(debug_assertions)]
     pub fn get_transition_degrees(&self) -> Vec<usize> {
         return [
@@ -380,9 +409,47 @@ impl Evaluator {
     }
 }
 
+// EVALUATOR TEMPLATE IMPLEMENTATION
+// ================================================================================================
+impl EvaluatorTemplate {
+
+    pub fn new(shape: TraceShape) -> EvaluatorTemplate {
+        let (trace_length, extension_factor, ctx_depth, loop_depth, stack_depth) = shape;
+        return EvaluatorTemplate {
+            decoder : Decoder::new(trace_length, extension_factor, ctx_depth, loop_depth),
+            stack   : Stack::new(trace_length, extension_factor, stack_depth),
+            shape,
+        };
+    }
+
+    /// Returns a template for traces of the specified shape which re-uses constants extended by
+    /// this template; this is much cheaper than building a new template, but the shape must have
+    /// the same extension factor as the shape of this template.
+    pub fn with_shape(&self, shape: TraceShape) -> EvaluatorTemplate {
+        let (trace_length, extension_factor, ctx_depth, loop_depth, stack_depth) = shape;
+        assert!(extension_factor == self.shape.1, "extension factor of the shape must be {}", self.shape.1);
+        return EvaluatorTemplate {
+            decoder : self.decoder.with_shape(trace_length, ctx_depth, loop_depth),
+            stack   : self.stack.with_shape(trace_length, stack_depth),
+            shape,
+        };
+    }
+
+    /// Returns the shape of the execution trace attested to by the `proof`.
+    pub fn get_shape(proof: &StarkProof) -> TraceShape {
+        return (
+            proof.trace_length(),
+            proof.options().extension_factor(),
+            proof.ctx_depth(),
+            proof.loop_depth(),
+            proof.stack_depth(),
+        );
+    }
+}
+
 // HELPER FUNCTIONS
 // ================================================================================================
-fn group_transition_constraints(degrees: Vec<usize>, trace_length: usize) -> Vec<(u128, Vec<usize>)> {
+fn group_transition_constraints(degrees: Vec<usize>, trace_length: usize, poly_length: usize) -> Vec<(u128, Vec<usize>)> {
     let mut groups = [
         Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new(),
         Vec::new(), Vec::new(), Vec::new(), Vec::new(),
@@ -392,12 +459,12 @@ fn group_transition_constraints(degrees: Vec<usize>, trace_length: usize) -> Vec
         groups[degree].push(i);
     }
 
-    let target_degree = get_transition_constraint_target_degree(trace_length);
+    let target_degree = get_transition_constraint_target_degree(trace_length, poly_length);
 
     let mut result = Vec::new();
     for (degree, constraints) in groups.iter().enumerate() {
         if constraints.len() == 0 { continue; }
-        let constraint_degree = (trace_length - 1) * degree;    
+        let constraint_degree = (poly_length - 1) * degree;
         let incremental_degree = (target_degree - constraint_degree) as u128;
         result.push((incremental_degree, constraints.clone()));
     }
@@ -405,17 +472,17 @@ fn group_transition_constraints(degrees: Vec<usize>, trace_length: usize) -> Vec
     return result;
 }
 
-fn get_boundary_constraint_adjustment_degree(trace_length: usize) -> u128 {
-    let target_degree = get_boundary_constraint_target_degree(trace_length);
-    let boundary_constraint_degree = trace_length - 1;
+fn get_boundary_constraint_adjustment_degree(trace_length: usize, poly_length: usize) -> u128 {
+    let target_degree = get_boundary_constraint_target_degree(trace_length, poly_length);
+    let boundary_constraint_degree = poly_length - 1;
     return (target_degree - boundary_constraint_degree) as u128;
 }
 
 /// target degree for boundary constraints is set so that when divided by boundary
 /// constraint divisor (degree 1 polynomial), the degree will be equal to
 /// deg(combination domain) - deg(trace)
-fn get_boundary_constraint_target_degree(trace_length: usize) -> usize {
-    let combination_degree = (MAX_CONSTRAINT_DEGREE - 1) * trace_length;
+fn get_boundary_constraint_target_degree(trace_length: usize, poly_length: usize) -> usize {
+    let combination_degree = MAX_CONSTRAINT_DEGREE * poly_length - trace_length;
     let divisor_degree = 1;
     return combination_degree + divisor_degree;
 }
@@ -423,8 +490,8 @@ fn get_boundary_constraint_target_degree(trace_length: usize) -> usize {
 /// target degree for transition constraints is set so when divided transition 
 /// constraint divisor (deg(trace) - 1 polynomial), the degree will be equal to
 /// deg(combination domain) - deg(trace)
-fn get_transition_constraint_target_degree(trace_length: usize) -> usize {
-    let combination_degree = (MAX_CONSTRAINT_DEGREE - 1) * trace_length;
+fn get_transition_constraint_target_degree(trace_length: usize, poly_length: usize) -> usize {
+    let combination_degree = MAX_CONSTRAINT_DEGREE * poly_length - trace_length;
     let divisor_degree = trace_length - 1;
     return combination_degree + divisor_degree;
 }
@@ -436,9 +503,11 @@ fn parse_program_hash(program_hash: &[u8; 32]) -> Vec<u128> {
     ];
 }
 
-fn get_boundary_constraint_num(inputs: &[u128], outputs: &[u128]) -> usize {
+fn get_boundary_constraint_num(inputs: &[u128], outputs: &[u128], with_op_count: bool, with_stack_size: bool) -> usize {
     return
         PROGRAM_DIGEST_SIZE 
         + inputs.len() + outputs.len()
-        + 1 /* for op_count */;
+        + 1 // for initial stack size
+        + with_op_count as usize
+        + with_stack_size as usize;
 }
\ No newline at end of file
@@ -6,7 +6,8 @@ mod constraint_poly;
 mod utils;
 
 pub use decoder::{ NUM_STATIC_DECODER_CONSTRAINTS };
-pub use stack::{ NUM_AUX_CONSTRAINTS as NUM_AUX_STACK_CONSTRAINTS };
-pub use evaluator::{ Evaluator as ConstraintEvaluator};
-pub use constraint_table::{ ConstraintTable };
+pub use stack::{
+    NUM_AUX_CONSTRAINTS as NUM_AUX_STACK_CONSTRAINTS, NUM_SIZE_CONSTRAINTS as NUM_STACK_SIZE_CONSTRAINTS };
+pub use evaluator::{ Evaluator as ConstraintEvaluator, EvaluatorTemplate, TraceShape };
+pub use constraint_table::{ ConstraintTable, ConstraintTableFragment };
 pub use constraint_poly::{ ConstraintPoly };
\ No newline at end of file
@@ -38,10 +38,23 @@ use hash::{ enforce_rescr };
 // ================================================================================================
 pub const NUM_AUX_CONSTRAINTS: usize = 2;
 const AUX_CONSTRAINT_DEGREES: [usize; NUM_AUX_CONSTRAINTS] = [7, 7];
+pub const NUM_SIZE_CONSTRAINTS: usize = 1;
+const SIZE_CONSTRAINT_DEGREE: usize = 6;  // degree of ASSERT op flag
 const STACK_TRANSITION_DEGREE: usize = 7; // degree for all stack register transition constraints
 
+/// Operations which change the number of items on the stack, and the number of items each
+/// operation adds to the stack (positive) or removes from the stack (negative).
+const SIZE_CHANGING_OPS: [(OpCode, i8); 17] = [
+    (OpCode::Assert,  -1), (OpCode::AssertEq, -2), (OpCode::Eq,   -2), (OpCode::Drop,  -1),
+    (OpCode::Drop4,   -4), (OpCode::Choose,   -2), (OpCode::Choose2, -4),
+    (OpCode::Add,     -1), (OpCode::Mul,      -1), (OpCode::And,  -1), (OpCode::Or,    -1),
+    (OpCode::Read,     1), (OpCode::Read2,     2), (OpCode::Dup,   1), (OpCode::Dup2,   2),
+    (OpCode::Dup4,     4), (OpCode::Pad2,      2),
+];
+
 // TYPES AND INTERFACES
 // ================================================================================================
+#[derive(Clone)]
 pub struct Stack {
     trace_length        : usize,
     cycle_length        : usize,
@@ -56,10 +69,6 @@ impl Stack {
 
     pub fn new(trace_length: usize, extension_factor: usize, stack_depth: usize) -> Stack 
     {
-        // build an array of constraint degrees for the stack
-        let mut degrees = Vec::from(&AUX_CONSTRAINT_DEGREES[..]);
-        degrees.resize(stack_depth + NUM_AUX_CONSTRAINTS, STACK_TRANSITION_DEGREE);
-
         // determine extended cycle length
         let cycle_length = BASE_CYCLE_LENGTH * extension_factor;
 
@@ -70,7 +79,17 @@ impl Stack {
         return Stack {
             trace_length, cycle_length,
             ark_values, ark_polys,
-            constraint_degrees: degrees,
+            constraint_degrees: build_constraint_degrees(stack_depth),
+        };
+    }
+
+    /// Returns a stack evaluator for traces of the specified shape which re-uses the extended
+    /// constants of this evaluator; the constants depend only on the extension factor.
+    pub fn with_shape(&self, trace_length: usize, stack_depth: usize) -> Stack {
+        return Stack {
+            trace_length,
+            constraint_degrees: build_constraint_degrees(stack_depth),
+            ..self.clone()
         };
     }
 
@@ -114,10 +133,19 @@ impl Stack {
 
 // HELPER FUNCTIONS
 // ================================================================================================
+fn build_constraint_degrees(stack_depth: usize) -> Vec<usize> {
+    let mut degrees = Vec::from(&AUX_CONSTRAINT_DEGREES[..]);
+    degrees.push(SIZE_CONSTRAINT_DEGREE);
+    degrees.resize(stack_depth + NUM_AUX_CONSTRAINTS + NUM_SIZE_CONSTRAINTS, STACK_TRANSITION_DEGREE);
+    return degrees;
+}
+
 fn enforce_constraints(current: &TraceState, next: &TraceState, ark: &[u128], result: &mut [u128])
 {
-    // split constraint evaluation result into aux constraints and stack constraints
+    // split constraint evaluation result into aux constraints, stack size constraint,
+    // and stack constraints
     let (aux, result) = result.split_at_mut(NUM_AUX_CONSTRAINTS);
+    let (size, result) = result.split_at_mut(NUM_SIZE_CONSTRAINTS);
 
     // get user stack registers from current and next steps
     let old_stack = current.user_stack();
@@ -189,10 +217,35 @@ fn enforce_constraints(current: &TraceState, next: &TraceState, ark: &[u128], re
     enforce_stack_copy(&mut evaluations, old_stack, new_stack, 0, current.begin_flag());
     enforce_stack_copy(&mut evaluations, old_stack, new_stack, 0, current.noop_flag());
     
-    // 4 ----- copy evaluations into the result ---------------------------------------------------
+    // 4 ----- enforce stack size constraint ----------------------------------------------------
+    size[0] = enforce_size_change(current, next, &ld_flags, &hd_flags);
+
+    // 5 ----- copy evaluations into the result ---------------------------------------------------
     result.copy_from_slice(&evaluations[..result.len()]);
 }
 
+/// Enforces that the stack size changes by the number of items added to or removed from the
+/// stack by the current operation; PUSH is the only high-degree operation which changes the
+/// size of the stack, and BEGIN, NOOP and all other operations leave it unchanged.
+fn enforce_size_change(current: &TraceState, next: &TraceState, ld_flags: &[u128], hd_flags: &[u128]) -> u128
+{
+    let mut growth = hd_flags[OpCode::Push.hd_index()];
+    let mut shrinkage = field::ZERO;
+    for &(op_code, change) in SIZE_CHANGING_OPS.iter() {
+        let flag = ld_flags[op_code.ld_index()];
+        if change > 0 {
+            growth = field::add(growth, field::mul(flag, change as u128));
+        }
+        else {
+            shrinkage = field::add(shrinkage, field::mul(flag, -change as u128));
+        }
+    }
+
+    return are_equal(
+        field::add(next.stack_size(), shrinkage),
+        field::add(current.stack_size(), growth));
+}
+
 fn transpose_ark_constants(constants: Vec<Vec<u128>>, cycle_length: usize) -> Vec<[u128; 2 * HASH_STATE_WIDTH]>
 {
     let mut values = Vec::new();
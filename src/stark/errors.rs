@@ -0,0 +1,201 @@
+// TYPES AND INTERFACES
+// ================================================================================================
+
+/// Describes the reason for which a STARK proof was rejected by the verifier.
+#[derive(Clone, PartialEq)]
+pub enum VerificationError {
+    /// The proof was generated for a different statement: program hash, public inputs, outputs,
+    /// output positions, or stack size did not match the ones the proof was generated for.
+    StatementMismatch,
+    /// Proof-of-work nonce did not satisfy the grinding factor of the proof.
+    ProofOfWorkNotVerified,
+    /// The proof attests to fewer operations than the minimum allowed.
+    InsufficientOpCount { op_count: u128, min: usize },
+    /// Public inputs or outputs do not fit into the user stack of the execution trace; `outputs`
+    /// is the number of stack items spanned by the outputs.
+    StackDepthMismatch { inputs: usize, outputs: usize, stack_depth: usize },
+    /// The number of claimed outputs did not match the number of outputs selected for binding.
+    OutputCountMismatch { expected: usize, actual: usize },
+    /// More than one output was bound to the same stack position.
+    DuplicateOutputPosition { position: usize },
+    /// Trace evaluations could not be authenticated against the trace commitment.
+    TraceCommitmentMismatch,
+    /// Constraint evaluations could not be authenticated against the constraint commitment.
+    ConstraintCommitmentMismatch,
+    /// Composition polynomial evaluations derived from DEEP values did not match the first FRI
+    /// layer.
+    CompositionMismatch,
+    /// The proof was generated in zero-knowledge mode, but did not contain a commitment to the
+    /// composition mask polynomial.
+    CompositionMaskMissing,
+    /// Composition mask evaluations could not be authenticated against the mask commitment.
+    CompositionMaskCommitmentMismatch,
+    /// Values of FRI layer `layer` were inconsistent with the values of the previous layer.
+    FriLayerMismatch { layer: usize },
+    /// Values of FRI layer `layer` could not be authenticated against the layer commitment.
+    FriLayerCommitmentMismatch { layer: usize },
+    /// Remainder of the FRI proof was inconsistent with the values of the last FRI layer.
+    FriRemainderMismatch,
+    /// Remainder of the FRI proof did not describe a polynomial of the expected degree.
+    FriRemainderDegreeMismatch { degree: usize },
+    /// Proof options did not satisfy the policy of the verifier.
+    OptionsPolicyViolation(String),
+    /// Shape of the proof was inconsistent with its options and trace info; the string
+    /// describes the first inconsistent component.
+    MalformedProof(String),
+    /// The execution receipt was created with a version of the receipt format which is not
+    /// supported by the verifier.
+    UnsupportedReceiptVersion { version: u8, expected: u8 },
+}
+
+// COMMON TRAIT IMPLEMENTATIONS
+// ================================================================================================
+
+impl std::fmt::Display for VerificationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use VerificationError::*;
+        return match self {
+            StatementMismatch => {
+                write!(f, "proof was generated for a different program, inputs, or outputs")
+            },
+            ProofOfWorkNotVerified => {
+                write!(f, "seed proof-of-work verification failed")
+            },
+            InsufficientOpCount { op_count, min } => {
+                write!(f, "verification of minimum operation count failed: {} operations is fewer than {}", op_count, min)
+            },
+            StackDepthMismatch { inputs, outputs, stack_depth } => {
+                write!(f, "{} inputs and {} outputs do not fit into execution trace stack of depth {}", inputs, outputs, stack_depth)
+            },
+            OutputCountMismatch { expected, actual } => {
+                write!(f, "expected {} outputs, but received {}", expected, actual)
+            },
+            DuplicateOutputPosition { position } => {
+                write!(f, "more than one output is bound to stack position {}", position)
+            },
+            TraceCommitmentMismatch => {
+                write!(f, "verification of trace Merkle proof failed")
+            },
+            ConstraintCommitmentMismatch => {
+                write!(f, "verification of constraint Merkle proof failed")
+            },
+            CompositionMismatch => {
+                write!(f, "verification of low-degree proof failed: evaluations did not match column value at depth 0")
+            },
+            CompositionMaskMissing => {
+                write!(f, "zero-knowledge proof does not contain composition mask commitment")
+            },
+            CompositionMaskCommitmentMismatch => {
+                write!(f, "verification of composition mask Merkle proof failed")
+            },
+            FriLayerMismatch { layer } => {
+                write!(f, "verification of low-degree proof failed: evaluations did not match column value at depth {}", layer)
+            },
+            FriLayerCommitmentMismatch { layer } => {
+                write!(f, "verification of low-degree proof failed: verification of Merkle proof failed at layer {}", layer)
+            },
+            FriRemainderMismatch => {
+                write!(f, "verification of low-degree proof failed: remainder values are inconsistent with values of the last column")
+            },
+            FriRemainderDegreeMismatch { degree } => {
+                write!(f, "verification of low-degree proof failed: remainder is not a valid degree {} polynomial", degree)
+            },
+            OptionsPolicyViolation(reason) => {
+                write!(f, "proof options do not satisfy verifier policy: {}", reason)
+            },
+            MalformedProof(reason) => {
+                write!(f, "proof is malformed: invalid {}", reason)
+            },
+            UnsupportedReceiptVersion { version, expected } => {
+                write!(f, "receipt format version {} is not supported; expected version {}", version, expected)
+            },
+        };
+    }
+}
+
+impl std::fmt::Debug for VerificationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "verification error: {}", self)
+    }
+}
+
+impl std::error::Error for VerificationError {}
+
+// DESERIALIZATION ERROR
+// ================================================================================================
+
+/// Describes the reason for which a STARK proof or an execution receipt could not be decoded
+/// from bytes.
+#[derive(Clone, PartialEq)]
+pub enum DeserializationError {
+    /// The input was encoded using a version of the format which is not supported.
+    UnsupportedVersion { version: u8, expected: u8 },
+    /// The input ended before it was fully decoded.
+    UnexpectedEnd,
+    /// The input contained `count` bytes after the end of the encoded value.
+    TrailingBytes { count: usize },
+    /// A value in the input was malformed; the string describes the value.
+    InvalidValue(String),
+}
+
+impl std::fmt::Display for DeserializationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use DeserializationError::*;
+        return match self {
+            UnsupportedVersion { version, expected } => {
+                write!(f, "format version {} is not supported; expected version {}", version, expected)
+            },
+            UnexpectedEnd => {
+                write!(f, "input ended before it was fully decoded")
+            },
+            TrailingBytes { count } => {
+                write!(f, "input contains {} bytes after the end of the encoded value", count)
+            },
+            InvalidValue(reason) => {
+                write!(f, "invalid {}", reason)
+            },
+        };
+    }
+}
+
+impl std::fmt::Debug for DeserializationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "deserialization error: {}", self)
+    }
+}
+
+impl std::error::Error for DeserializationError {}
+
+// POLICY ERROR
+// ================================================================================================
+
+/// Describes the reason for which a verifier policy could not be created.
+#[derive(Clone, PartialEq)]
+pub enum PolicyError {
+    /// The policy did not allow any hash function.
+    NoHashFunctions,
+    /// The minimum trace length of the policy was greater than the maximum trace length.
+    InvalidTraceLengthRange { min: usize, max: usize },
+}
+
+impl std::fmt::Display for PolicyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use PolicyError::*;
+        return match self {
+            NoHashFunctions => {
+                write!(f, "at least one hash function must be allowed")
+            },
+            InvalidTraceLengthRange { min, max } => {
+                write!(f, "minimum trace length {} is greater than maximum trace length {}", min, max)
+            },
+        };
+    }
+}
+
+impl std::fmt::Debug for PolicyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "policy error: {}", self)
+    }
+}
+
+impl std::error::Error for PolicyError {}
@@ -8,9 +8,9 @@ mod prover;
 pub use prover::{ reduce, build_proof };
 
 mod verifier;
-pub use verifier::{ verify };
+pub use verifier::{ verify, get_remainder_domain };
 
-const MAX_REMAINDER_LENGTH: usize = 256;
+pub const MAX_REMAINDER_LENGTH: usize = 256;
 
 // TYPES AND INTERFACES
 // ================================================================================================
@@ -34,7 +34,7 @@ pub struct FriLayer {
 #[cfg(test)]
 mod tests {
     use crate::math::{ field, polynom };
-    use crate::stark::{ ProofOptions, utils::compute_query_positions };
+    use crate::stark::{ ProofOptions, VerificationError, utils::compute_query_positions };
 
     #[test]
     fn prove_verify() {
@@ -42,18 +42,19 @@ mod tests {
         let domain_size: usize = 512;
         let root = field::get_root_of_unity(domain_size);
         let domain = field::get_power_series(root, domain_size);
+        let rem_domain = super::get_remainder_domain(domain_size);
         let options = ProofOptions::default();
 
         let evaluations = build_random_poly_evaluations(domain_size, degree);
 
         // generate proof
-        let (fri_trees, fri_values) = super::reduce(&evaluations, &domain, &options);
+        let (fri_trees, fri_values) = super::reduce(&evaluations, &domain, &options, 1);
         let positions = compute_query_positions(fri_trees[fri_trees.len() - 1].root(), domain_size, &options);
         let proof = super::build_proof(fri_trees, fri_values, &positions);
 
         // verify proof
         let sampled_evaluations = positions.iter().map(|&i| evaluations[i]).collect::<Vec<u128>>();
-        let result = super::verify(&proof, &sampled_evaluations, &positions, degree, &options);
+        let result = super::verify(&proof, &sampled_evaluations, &positions, degree, &rem_domain, &options);
         assert_eq!(Ok(true), result);
     }
 
@@ -63,35 +64,54 @@ mod tests {
         let domain_size: usize = 512;
         let root = field::get_root_of_unity(domain_size);
         let domain = field::get_power_series(root, domain_size);
+        let rem_domain = super::get_remainder_domain(domain_size);
         let options = ProofOptions::default();
 
         // degree too low 1
         let evaluations = build_random_poly_evaluations(domain_size, degree);
-        let (fri_trees, fri_values) = super::reduce(&evaluations, &domain, &options);
+        let (fri_trees, fri_values) = super::reduce(&evaluations, &domain, &options, 1);
         let positions = compute_query_positions(fri_trees[fri_trees.len() - 1].root(), domain_size, &options);
         let proof = super::build_proof(fri_trees, fri_values, &positions);
 
         let sampled_evaluations = positions.iter().map(|&i| evaluations[i]).collect::<Vec<u128>>();
-        let result = super::verify(&proof, &sampled_evaluations, &positions, degree - 1, &options);
-        let err_msg = format!("remainder is not a valid degree {} polynomial", 14);
-        assert_eq!(Err(err_msg), result);
+        let result = super::verify(&proof, &sampled_evaluations, &positions, degree - 1, &rem_domain, &options);
+        let error = VerificationError::FriRemainderDegreeMismatch { degree: 14 };
+        assert_eq!(Err(error), result);
 
         // degree too low 2
         let evaluations = build_random_poly_evaluations(domain_size, degree + 1);
-        let (fri_trees, fri_values) = super::reduce(&evaluations, &domain, &options);
+        let (fri_trees, fri_values) = super::reduce(&evaluations, &domain, &options, 1);
         let positions = compute_query_positions(fri_trees[fri_trees.len() - 1].root(), domain_size, &options);
         let proof = super::build_proof(fri_trees, fri_values, &positions);
 
         let sampled_evaluations = positions.iter().map(|&i| evaluations[i]).collect::<Vec<u128>>();
-        let result = super::verify(&proof, &sampled_evaluations, &positions, degree, &options);
-        let err_msg = format!("remainder is not a valid degree {} polynomial", 15);
-        assert_eq!(Err(err_msg), result);
+        let result = super::verify(&proof, &sampled_evaluations, &positions, degree, &rem_domain, &options);
+        let error = VerificationError::FriRemainderDegreeMismatch { degree: 15 };
+        assert_eq!(Err(error), result);
 
         // invalid evaluations
         let sampled_evaluations = sampled_evaluations[1..].to_vec();
-        let result = super::verify(&proof, &sampled_evaluations, &positions, degree, &options);
-        let err_msg = format!("evaluations did not match column value at depth 0");
-        assert_eq!(Err(err_msg), result);
+        let result = super::verify(&proof, &sampled_evaluations, &positions, degree, &rem_domain, &options);
+        assert_eq!(Err(VerificationError::CompositionMismatch), result);
+    }
+
+    #[test]
+    fn reduce_in_parallel() {
+        let domain_size: usize = 512;
+        let root = field::get_root_of_unity(domain_size);
+        let domain = field::get_power_series(root, domain_size);
+        let options = ProofOptions::default();
+        let evaluations = build_random_poly_evaluations(domain_size, 63);
+
+        // layers do not depend on the number of threads used to build them
+        let (expected_trees, expected_values) = super::reduce(&evaluations, &domain, &options, 1);
+        for &num_threads in [2, 3, 8].iter() {
+            let (fri_trees, fri_values) = super::reduce(&evaluations, &domain, &options, num_threads);
+            assert_eq!(expected_values, fri_values);
+            for (expected, tree) in expected_trees.iter().zip(fri_trees.iter()) {
+                assert_eq!(expected.root(), tree.root());
+            }
+        }
     }
 
     // TODO: add more tests
@@ -1,5 +1,6 @@
 use std::mem;
-use crate::math::{ field, quartic };
+use crossbeam_utils::thread;
+use crate::math::{ field, quartic, parallel };
 use crate::crypto::{ MerkleTree };
 use crate::stark::{ ProofOptions };
 
@@ -8,33 +9,34 @@ use super::{ FriProof, FriLayer, utils, MAX_REMAINDER_LENGTH};
 // PROVER FUNCTIONS
 // ================================================================================================
 
-pub fn reduce(evaluations: &[u128], domain: &[u128], options: &ProofOptions) -> (Vec<MerkleTree>, Vec<Vec<[u128; 4]>>) {
+/// Reduces the degree of the polynomial defined by `evaluations` over `domain` until it is small
+/// enough to be sent in the proof directly; hashing and folding of each layer is distributed
+/// across up to `num_threads` threads.
+pub fn reduce(evaluations: &[u128], domain: &[u128], options: &ProofOptions, num_threads: usize) -> (Vec<MerkleTree>, Vec<Vec<[u128; 4]>>) {
     let mut tree_results: Vec<MerkleTree> = Vec::new();
     let mut value_results: Vec<Vec<[u128; 4]>> = Vec::new();
 
     // transpose evaluations into a matrix with 4 columns and put its rows into a Merkle tree
     let mut p_values = quartic::transpose(evaluations, 1);
-    let hashed_values = utils::hash_values(&p_values, options.hash_fn());
-    let mut p_tree = MerkleTree::new(hashed_values, options.hash_fn());
+    let hashed_values = utils::hash_values(&p_values, options.hash_fn(), num_threads);
+    let mut p_tree = MerkleTree::new_parallel(hashed_values, options.hash_fn(), num_threads);
 
     // reduce the degree by 4 at each iteration until the remaining polynomial is small enough
     while p_tree.leaves().len() * 4 > MAX_REMAINDER_LENGTH {
 
-        // build polynomials from each row of the polynomial value matrix
+        // build polynomials from each row of the polynomial value matrix, select a pseudo-random
+        // x coordinate, and evaluate each row polynomial at that x
         let depth = tree_results.len() as u32;
         let xs = quartic::transpose(domain, usize::pow(4, depth));
-        let polys = quartic::interpolate_batch(&xs, &p_values);
-
-        // select a pseudo-random x coordinate and evaluate each row polynomial at that x
         let special_x = field::prng(*p_tree.root());
-        let column = quartic::evaluate_batch(&polys, special_x);
+        let column = fold_rows(&xs, &p_values, special_x, num_threads);
 
         // break the column in a polynomial value matrix for the next layer
         let mut c_values = quartic::transpose(&column, 1);
 
         // put the resulting matrix into a Merkle tree
-        let hashed_values = utils::hash_values(&c_values, options.hash_fn());
-        let mut c_tree = MerkleTree::new(hashed_values, options.hash_fn());
+        let hashed_values = utils::hash_values(&c_values, options.hash_fn(), num_threads);
+        let mut c_tree = MerkleTree::new_parallel(hashed_values, options.hash_fn(), num_threads);
 
         // set p_tree = c_tree and p_values = c_values for the next iteration of the loop
         mem::swap(&mut c_tree, &mut p_tree);
@@ -93,4 +95,23 @@ pub fn build_proof(trees: Vec<MerkleTree>, values: Vec<Vec<[u128; 4]>>, position
     }
 
     return FriProof { layers, rem_root: *last_tree.root(), rem_values: remainder };
-}
\ No newline at end of file
+}
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Interpolates each row of `ys` into a degree 3 polynomial, and evaluates these polynomials at
+/// `x`; rows are split into batches which are distributed across multiple threads.
+fn fold_rows(xs: &[[u128; 4]], ys: &[[u128; 4]], x: u128, num_threads: usize) -> Vec<u128> {
+    let num_batches = parallel::get_num_batches(ys.len(), num_threads);
+    let batch_size = ys.len() / num_batches;
+    let mut result = vec![field::ZERO; ys.len()];
+    thread::scope(|s| {
+        for ((xs, ys), result) in xs.chunks(batch_size).zip(ys.chunks(batch_size)).zip(result.chunks_mut(batch_size)) {
+            s.spawn(move |_| {
+                let polys = quartic::interpolate_batch(xs, ys);
+                result.copy_from_slice(&quartic::evaluate_batch(&polys, x));
+            });
+        }
+    }).unwrap();
+    return result;
+}
@@ -1,3 +1,5 @@
+use crossbeam_utils::thread;
+use crate::math::parallel;
 use crate::crypto::{ HashFunction };
 use crate::utils::{ uninit_vector, as_bytes };
 
@@ -13,10 +15,27 @@ pub fn get_augmented_positions(positions: &[usize], column_length: usize) -> Vec
     return result;
 }
 
-pub fn hash_values(values: &Vec<[u128; 4]>, hash: HashFunction) -> Vec<[u8; 32]> {
+/// Hashes each row of `values`; rows are split into batches which are distributed across up
+/// to `num_threads` threads.
+pub fn hash_values(values: &[[u128; 4]], hash_fn: HashFunction, num_threads: usize) -> Vec<[u8; 32]> {
     let mut result: Vec<[u8; 32]> = uninit_vector(values.len());
-    for i in 0..values.len() {
-        hash(as_bytes(&values[i]), &mut result[i]);
+    let num_batches = parallel::get_num_batches(values.len(), num_threads);
+    if num_batches == 1 {
+        for i in 0..values.len() {
+            hash_fn.hash(as_bytes(&values[i]), &mut result[i]);
+        }
+        return result;
     }
+
+    let batch_size = values.len() / num_batches;
+    thread::scope(|s| {
+        for (values, result) in values.chunks(batch_size).zip(result.chunks_mut(batch_size)) {
+            s.spawn(move |_| {
+                for (value, hash) in values.iter().zip(result.iter_mut()) {
+                    hash_fn.hash(as_bytes(value), hash);
+                }
+            });
+        }
+    }).unwrap();
     return result;
 }
\ No newline at end of file
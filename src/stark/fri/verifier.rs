@@ -1,22 +1,30 @@
 use std::mem;
 use crate::math::{ field, polynom, quartic };
 use crate::crypto::{ MerkleTree, BatchMerkleProof };
-use crate::stark::{ ProofOptions };
+use crate::stark::{ ProofOptions, VerificationError };
 
-use super::{ FriProof, FriLayer, utils };
+use super::{ FriProof, FriLayer, MAX_REMAINDER_LENGTH, utils };
 
 // VERIFIER
 // ================================================================================================
 
+/// Verifies that `evaluations` at `positions` belong to a polynomial of at most `max_degree`;
+/// `remainder_domain` must be the domain of the FRI remainder as returned by
+/// `get_remainder_domain()` for the size of the evaluation domain.
 pub fn verify(
-    proof       : &FriProof,
-    evaluations : &[u128],
-    positions   : &[usize],
-    max_degree  : usize,
-    options     : &ProofOptions) -> Result<bool, String>
+    proof           : &FriProof,
+    evaluations     : &[u128],
+    positions       : &[usize],
+    max_degree      : usize,
+    remainder_domain: &[u128],
+    options         : &ProofOptions) -> Result<bool, VerificationError>
 {
 
-    let domain_size = usize::pow(2, proof.layers[0].depth as u32) * 4;
+    // for the smallest domains, the proof consists of the remainder only
+    let domain_size = match proof.layers.first() {
+        Some(layer) => usize::pow(2, layer.depth as u32) * 4,
+        None => proof.rem_values.len(),
+    };
     let domain_root = field::get_root_of_unity(domain_size);
 
     // powers of the given root of unity 1, p, p^2, p^3 such that p^4 = 1
@@ -37,15 +45,22 @@ pub fn verify(
     for (depth, layer) in proof.layers.iter().enumerate() {
 
         let mut augmented_positions = utils::get_augmented_positions(&positions, domain_size);
+        if layer.values.len() != augmented_positions.len() {
+            return Err(VerificationError::MalformedProof(format!("number of values in FRI layer {}", depth)));
+        }
         let column_values = get_column_values(&layer.values, &positions, &augmented_positions, domain_size);
         if evaluations != column_values {
-            return Err(format!("evaluations did not match column value at depth {}", depth));
+            // at depth 0 column values are checked against composition polynomial evaluations
+            return Err(match depth {
+                0 => VerificationError::CompositionMismatch,
+                _ => VerificationError::FriLayerMismatch { layer: depth },
+            });
         }
 
         // verify Merkle proof for the layer
         let merkle_proof = build_layer_merkle_proof(&layer, options);
         if !MerkleTree::verify_batch(&layer.root, &augmented_positions, &merkle_proof, options.hash_fn()) {
-            return Err(format!("verification of Merkle proof failed at layer {}", depth));
+            return Err(VerificationError::FriLayerCommitmentMismatch { layer: depth });
         }
 
         // build a set of x for each row polynomial
@@ -80,17 +95,30 @@ pub fn verify(
     
     for (&position, evaluation) in positions.iter().zip(evaluations) {
         if proof.rem_values[position] != evaluation {
-            return Err(String::from("remainder values are inconsistent with values of the last column"));
+            return Err(VerificationError::FriRemainderMismatch);
         }
     }
 
     // make sure the remainder values satisfy the degree
-    return verify_remainder(&proof.rem_values, max_degree_plus_1, domain_root, options.extension_factor());
+    debug_assert!(remainder_domain.len() == proof.rem_values.len(), "inconsistent remainder domain");
+    return verify_remainder(&proof.rem_values, max_degree_plus_1, remainder_domain, options.extension_factor());
+}
+
+/// Returns x coordinates of the FRI remainder for an evaluation domain of `domain_size`; every
+/// FRI layer reduces the domain by a factor of 4 until the remainder is small enough.
+pub fn get_remainder_domain(domain_size: usize) -> Vec<u128> {
+    let mut rem_length = domain_size;
+    while rem_length > MAX_REMAINDER_LENGTH {
+        rem_length = rem_length / 4;
+    }
+    let domain_root = field::get_root_of_unity(domain_size);
+    let rem_root = field::exp(domain_root, (domain_size / rem_length) as u128);
+    return field::get_power_series(rem_root, rem_length);
 }
 
-fn verify_remainder(remainder: &[u128], max_degree_plus_1: usize, domain_root: u128, extension_factor: usize) -> Result<bool, String> {
+fn verify_remainder(remainder: &[u128], max_degree_plus_1: usize, domain: &[u128], extension_factor: usize) -> Result<bool, VerificationError> {
     if max_degree_plus_1 > remainder.len() {
-        return Err(String::from("remainder degree is greater than number of remainder values"));
+        return Err(VerificationError::FriRemainderDegreeMismatch { degree: max_degree_plus_1 - 1 });
     }
 
     // exclude points which should be skipped during evaluation
@@ -102,7 +130,6 @@ fn verify_remainder(remainder: &[u128], max_degree_plus_1: usize, domain_root: u
     }
 
     // pick a subset of points from the remainder and interpolate them into a polynomial
-    let domain = field::get_power_series(domain_root, remainder.len());
     let mut xs = Vec::with_capacity(max_degree_plus_1);
     let mut ys = Vec::with_capacity(max_degree_plus_1);
     for i in 0..max_degree_plus_1 {
@@ -116,7 +143,7 @@ fn verify_remainder(remainder: &[u128], max_degree_plus_1: usize, domain_root: u
     for i in max_degree_plus_1..positions.len() {
         let p = positions[i];
         if polynom::eval(&poly, domain[p]) != remainder[p] {
-            return Err(format!("remainder is not a valid degree {} polynomial", max_degree_plus_1 - 1));
+            return Err(VerificationError::FriRemainderDegreeMismatch { degree: max_degree_plus_1 - 1 });
         }
     }
 
@@ -140,7 +167,7 @@ fn get_column_values(values: &Vec<[u128; 4]>, positions: &[usize], augmented_pos
 
 fn build_layer_merkle_proof(layer: &FriLayer, options: &ProofOptions) -> BatchMerkleProof {
     return BatchMerkleProof {
-        values  : utils::hash_values(&layer.values, options.hash_fn()),
+        values  : utils::hash_values(&layer.values, options.hash_fn(), 1),
         nodes   : layer.nodes.clone(),
         depth   : layer.depth
     };
@@ -152,11 +179,13 @@ fn build_layer_merkle_proof(layer: &FriLayer, options: &ProofOptions) -> BatchMe
 mod tests {
     
     use crate::math::{ field, polynom };
+    use crate::stark::{ VerificationError };
 
     #[test]
     fn verify_remainder() {
         let degree_plus_1: usize = 32;
         let root = field::get_root_of_unity(degree_plus_1 * 2);
+        let domain = field::get_power_series(root, degree_plus_1 * 2);
         let extension_factor = 16;
 
         let mut remainder = field::rand_vector(degree_plus_1);
@@ -164,18 +193,18 @@ mod tests {
         polynom::eval_fft(&mut remainder, true);
 
         // check against exact degree
-        let result = super::verify_remainder(&remainder, degree_plus_1, root, extension_factor);
+        let result = super::verify_remainder(&remainder, degree_plus_1, &domain, extension_factor);
         assert_eq!(Ok(true), result);
 
         // check against higher degree
-        let result = super::verify_remainder(&remainder, degree_plus_1 + 1, root, extension_factor);
+        let result = super::verify_remainder(&remainder, degree_plus_1 + 1, &domain, extension_factor);
         assert_eq!(Ok(true), result);
 
         // check against lower degree
         let degree_plus_1 = degree_plus_1 - 1;
-        let result = super::verify_remainder(&remainder, degree_plus_1, root, extension_factor);
-        let err_msg = format!("remainder is not a valid degree {} polynomial", degree_plus_1 - 1);
-        assert_eq!(Err(err_msg), result);
+        let result = super::verify_remainder(&remainder, degree_plus_1, &domain, extension_factor);
+        let error = VerificationError::FriRemainderDegreeMismatch { degree: degree_plus_1 - 1 };
+        assert_eq!(Err(error), result);
     }
 
 }
\ No newline at end of file
@@ -1,11 +1,14 @@
 mod trace;
 mod constraints;
 mod options;
+mod config;
 mod prover;
 mod verifier;
 mod proof;
 mod fri;
 mod utils;
+mod errors;
+mod policy;
 
 pub use trace::{ TraceTable, TraceState };
 
@@ -16,11 +19,16 @@ pub use constraints::{
 
 pub use utils::{
     ConstraintCoefficients,
-    CompositionCoefficients };
+    CompositionCoefficients,
+    ByteWriter,
+    ByteReader };
 
 pub use options::ProofOptions;
-pub use proof::{ StarkProof, DeepValues };
+pub use config::ProverConfig;
+pub use proof::{ StarkProof, DeepValues, TraceInfo, ProofStats };
 pub use prover::{ prove };
-pub use verifier::{ verify };
+pub use verifier::{ verify, verify_batch };
+pub use errors::{ VerificationError, DeserializationError, PolicyError };
+pub use policy::{ VerifierPolicy };
 
 const MAX_CONSTRAINT_DEGREE : usize = 8;
\ No newline at end of file
@@ -1,6 +1,11 @@
+use std::convert::TryFrom;
 use serde::{ Serialize, Deserialize };
-use crate::crypto::{ HashFunction, hash };
-use super::MAX_CONSTRAINT_DEGREE;
+use crate::crypto::{ HashFunction };
+use crate::{
+    MIN_TRACE_LENGTH, MIN_CONTEXT_DEPTH, MAX_CONTEXT_DEPTH, MIN_LOOP_DEPTH, MAX_LOOP_DEPTH,
+    MIN_STACK_DEPTH, MAX_STACK_DEPTH,
+};
+use super::{ DeserializationError, utils::{ ByteWriter, ByteReader }, MAX_CONSTRAINT_DEGREE };
 
 // CONSTANTS
 // ================================================================================================
@@ -11,15 +16,32 @@ const DEFAULT_GRINDING_FACTOR : u8 = 20;
 // TYPES AND INTERFACES
 // ================================================================================================
 
-// TODO: validate field values on de-serialization
 #[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "RawProofOptions")]
 pub struct ProofOptions {
     extension_factor    : u8,   // stored as power of 2
     num_queries         : u8,
     grinding_factor     : u8,
+    zero_knowledge      : bool,
+    fixed_trace_length  : u8,   // stored as power of 2; 0 means trace length is not fixed
+    fixed_ctx_depth     : u8,   // 0 means trace width is not fixed
+    fixed_loop_depth    : u8,
+    fixed_stack_depth   : u8,
+    hash_fn             : HashFunction,
+}
 
-    #[serde(with = "hash_fn_serialization")]
-    hash_fn: HashFunction,
+/// De-serialized options which have not been validated yet.
+#[derive(Deserialize)]
+struct RawProofOptions {
+    extension_factor    : u8,
+    num_queries         : u8,
+    grinding_factor     : u8,
+    zero_knowledge      : bool,
+    fixed_trace_length  : u8,
+    fixed_ctx_depth     : u8,
+    fixed_loop_depth    : u8,
+    fixed_stack_depth   : u8,
+    hash_fn             : HashFunction,
 }
 
 // PROOF OPTIONS IMPLEMENTATION
@@ -45,10 +67,114 @@ impl ProofOptions {
             extension_factor    : extension_factor.trailing_zeros() as u8,
             num_queries         : num_queries as u8,
             grinding_factor     : grinding_factor as u8,
+            zero_knowledge      : false,
+            fixed_trace_length  : 0,
+            fixed_ctx_depth     : 0,
+            fixed_loop_depth    : 0,
+            fixed_stack_depth   : 0,
             hash_fn
         };
     }
 
+    /// Returns options for generating zero-knowledge proofs. Trace polynomials of such proofs
+    /// are randomized, and the composition polynomial is masked with a random polynomial, so
+    /// that the proof reveals nothing about secret inputs of the program. Since randomized trace
+    /// polynomials have twice the degree of regular trace polynomials, `extension_factor` must
+    /// be at least 32.
+    pub fn with_zero_knowledge(
+        extension_factor : usize,
+        num_queries      : usize,
+        grinding_factor  : u32,
+        hash_fn          : HashFunction) -> ProofOptions
+    {
+        assert!(extension_factor >= 32, "extension_factor cannot be smaller than 32 in zero-knowledge mode");
+        let mut options = ProofOptions::new(extension_factor, num_queries, grinding_factor, hash_fn);
+        options.zero_knowledge = true;
+        return options;
+    }
+
+    /// Returns a copy of these options which pads every execution trace to `trace_length` steps
+    /// and hides the number of executed operations. Proofs generated with such options reveal
+    /// only that the program executed fewer than `trace_length` operations, and thus proofs for
+    /// the same program look the same regardless of which branches were taken during execution.
+    /// Executions which require more than `trace_length` steps cannot be proven with these options.
+    /// 
+    /// Depths of context, loop, and user stacks are fixed as well, so that the width of the trace
+    /// does not reveal how deeply the executed branches were nested; unless they were fixed by
+    /// `with_fixed_trace_depths()`, they are fixed to the largest depths supported by the VM.
+    pub fn with_fixed_trace_length(&self, trace_length: usize) -> ProofOptions {
+        assert!(trace_length.is_power_of_two(), "trace_length must be a power of 2");
+        assert!(trace_length >= self.min_trace_length(),
+            "trace_length cannot be smaller than {}", self.min_trace_length());
+        let mut options = match self.fixed_trace_depths() {
+            Some(_) => self.clone(),
+            None => self.with_fixed_trace_depths(MAX_CONTEXT_DEPTH, MAX_LOOP_DEPTH, MAX_STACK_DEPTH),
+        };
+        options.fixed_trace_length = trace_length.trailing_zeros() as u8;
+        return options;
+    }
+
+    /// Returns a copy of these options which pads context, loop, and user stacks of every
+    /// execution trace to the specified depths, so that all traces have the same width; smaller
+    /// depths make proof generation and verification faster. Executions which need deeper stacks
+    /// cannot be proven with these options.
+    pub fn with_fixed_trace_depths(&self, ctx_depth: usize, loop_depth: usize, stack_depth: usize) -> ProofOptions {
+        assert!(ctx_depth >= MIN_CONTEXT_DEPTH && ctx_depth <= MAX_CONTEXT_DEPTH,
+            "ctx_depth must be between {} and {}", MIN_CONTEXT_DEPTH, MAX_CONTEXT_DEPTH);
+        assert!(loop_depth >= MIN_LOOP_DEPTH && loop_depth <= MAX_LOOP_DEPTH,
+            "loop_depth must be between {} and {}", MIN_LOOP_DEPTH, MAX_LOOP_DEPTH);
+        assert!(stack_depth >= MIN_STACK_DEPTH && stack_depth <= MAX_STACK_DEPTH,
+            "stack_depth must be between {} and {}", MIN_STACK_DEPTH, MAX_STACK_DEPTH);
+        let mut options = self.clone();
+        options.fixed_ctx_depth = ctx_depth as u8;
+        options.fixed_loop_depth = loop_depth as u8;
+        options.fixed_stack_depth = stack_depth as u8;
+        return options;
+    }
+
+    /// Returns options with the specified values if they could have been produced by the
+    /// constructors of `ProofOptions`; otherwise, returns a description of the invalid value.
+    pub fn try_new(
+        extension_factor    : usize,
+        num_queries         : usize,
+        grinding_factor     : u32,
+        zero_knowledge      : bool,
+        fixed_trace_length  : Option<usize>,
+        fixed_trace_depths  : Option<[usize; 3]>,
+        hash_fn             : HashFunction) -> Result<ProofOptions, String>
+    {
+        if !extension_factor.is_power_of_two() || extension_factor > 256 {
+            return Err(String::from("extension factor"));
+        }
+        if num_queries > 128 {
+            return Err(String::from("number of queries"));
+        }
+        if grinding_factor > 32 {
+            return Err(String::from("grinding factor"));
+        }
+        let fixed_trace_length = match fixed_trace_length {
+            Some(length) if !length.is_power_of_two() || length == 1 => {
+                return Err(String::from("fixed trace length"));
+            },
+            Some(length) => length.trailing_zeros() as u8,
+            None => 0,
+        };
+        let [fixed_ctx_depth, fixed_loop_depth, fixed_stack_depth] = match fixed_trace_depths {
+            Some(depths) if depths.iter().any(|&depth| depth > u8::MAX as usize) => {
+                return Err(String::from("fixed trace depths"));
+            },
+            Some(depths) => [depths[0] as u8, depths[1] as u8, depths[2] as u8],
+            None => [0, 0, 0],
+        };
+
+        return ProofOptions::try_from(RawProofOptions {
+            extension_factor    : extension_factor.trailing_zeros() as u8,
+            num_queries         : num_queries as u8,
+            grinding_factor     : grinding_factor as u8,
+            zero_knowledge, fixed_trace_length, fixed_ctx_depth, fixed_loop_depth, fixed_stack_depth, hash_fn
+        });
+    }
+
     pub fn extension_factor(&self) -> usize {
         return 1 << (self.extension_factor as usize)
     }
@@ -65,8 +191,57 @@ impl ProofOptions {
         return self.hash_fn;
     }
 
+    /// Returns true if proofs generated with these options are zero-knowledge.
+    pub fn zero_knowledge(&self) -> bool {
+        return self.zero_knowledge;
+    }
+
+    /// Returns the length to which all execution traces are padded, if trace length is fixed.
+    pub fn fixed_trace_length(&self) -> Option<usize> {
+        return match self.fixed_trace_length {
+            0 => None,
+            depth => Some(1 << (depth as usize)),
+        };
+    }
+
+    /// Returns depths of context, loop, and user stacks to which all execution traces are
+    /// padded, if trace width is fixed.
+    pub fn fixed_trace_depths(&self) -> Option<[usize; 3]> {
+        return match self.fixed_ctx_depth {
+            0 => None,
+            _ => Some([
+                self.fixed_ctx_depth as usize,
+                self.fixed_loop_depth as usize,
+                self.fixed_stack_depth as usize
+            ]),
+        };
+    }
+
+    /// Returns true if proofs generated with these options do not reveal the number of executed
+    /// operations; this is the case when trace length is fixed.
+    pub fn hides_op_count(&self) -> bool {
+        return self.fixed_trace_length != 0;
+    }
+
+    /// Returns the minimum length of an execution trace for proofs generated with these options;
+    /// shorter traces are padded to this length. In zero-knowledge mode, the trace must have more
+    /// steps than the number of trace polynomial evaluations revealed by the proof: up to 4 per
+    /// query (for queried positions and their neighbors), and 2 for DEEP points.
+    pub fn min_trace_length(&self) -> usize {
+        if let Some(trace_length) = self.fixed_trace_length() {
+            return trace_length;
+        }
+        if self.zero_knowledge {
+            let num_revealed_values = 4 * self.num_queries() + 2;
+            return std::cmp::max(num_revealed_values.next_power_of_two(), MIN_TRACE_LENGTH);
+        }
+        return MIN_TRACE_LENGTH;
+    }
+
     pub fn security_level(&self, optimistic: bool) -> u32 {
-        let one_over_rho = (self.extension_factor() / MAX_CONSTRAINT_DEGREE) as u32;
+        // in zero-knowledge mode, the degree of the composition polynomial is doubled
+        let zk_factor = if self.zero_knowledge { 2 } else { 1 };
+        let one_over_rho = (self.extension_factor() / (MAX_CONSTRAINT_DEGREE * zk_factor)) as u32;
         let security_factor = 31 - one_over_rho.leading_zeros(); // same as log2(one_over_rho)
         let num_queries = if optimistic == true { self.num_queries } else { self.num_queries / 2 };
 
@@ -77,45 +252,107 @@ impl ProofOptions {
 
         return result;
     }
-}
 
-impl Default for ProofOptions {
+    // SERIALIZATION
+    // --------------------------------------------------------------------------------------------
 
-    fn default() -> ProofOptions {
-        return ProofOptions {
-            extension_factor: DEFAULT_EXTENSION_FACTOR.trailing_zeros() as u8,
-            num_queries     : DEFAULT_NUM_QUERIES,
-            grinding_factor : DEFAULT_GRINDING_FACTOR,
-            hash_fn         : hash::blake3,
-        };
+    /// Writes these options into the binary proof encoding.
+    pub fn write_into(&self, target: &mut ByteWriter) {
+        target.write_u8(self.extension_factor);
+        target.write_u8(self.num_queries);
+        target.write_u8(self.grinding_factor);
+        target.write_bool(self.zero_knowledge);
+        target.write_u8(self.fixed_trace_length);
+        target.write_u8(self.fixed_ctx_depth);
+        target.write_u8(self.fixed_loop_depth);
+        target.write_u8(self.fixed_stack_depth);
+        target.write_u8(self.hash_fn.into());
     }
 
+    /// Reads options from the binary proof encoding; values which could not have been produced
+    /// by the constructors of `ProofOptions` are rejected.
+    pub fn read_from(source: &mut ByteReader) -> Result<ProofOptions, DeserializationError> {
+        let raw = RawProofOptions {
+            extension_factor    : source.read_u8()?,
+            num_queries         : source.read_u8()?,
+            grinding_factor     : source.read_u8()?,
+            zero_knowledge      : source.read_bool()?,
+            fixed_trace_length  : source.read_u8()?,
+            fixed_ctx_depth     : source.read_u8()?,
+            fixed_loop_depth    : source.read_u8()?,
+            fixed_stack_depth   : source.read_u8()?,
+            hash_fn             : HashFunction::try_from(source.read_u8()?)
+                .map_err(DeserializationError::InvalidValue)?,
+        };
+        return ProofOptions::try_from(raw).map_err(DeserializationError::InvalidValue);
+    }
 }
 
-// HASH FUNCTION SERIALIZATION / DE-SERIALIZATION
-// ================================================================================================
-mod hash_fn_serialization {
+impl TryFrom<RawProofOptions> for ProofOptions {
+    type Error = String;
 
-    use serde::{ Serializer, Deserializer, Deserialize, ser, de };
-    use crate::crypto::{ HashFunction, hash };
+    /// Makes sure the options could have been produced by the constructors of `ProofOptions`.
+    fn try_from(raw: RawProofOptions) -> Result<ProofOptions, String> {
+        let min_extension_factor = if raw.zero_knowledge { 5 } else { 4 };
+        if raw.extension_factor < min_extension_factor || raw.extension_factor > 8 {
+            return Err(String::from("extension factor"));
+        }
+        if raw.num_queries == 0 || raw.num_queries > 128 {
+            return Err(String::from("number of queries"));
+        }
+        if raw.grinding_factor > 32 {
+            return Err(String::from("grinding factor"));
+        }
 
-    pub fn serialize<S>(hf: &HashFunction, s: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer
-    {
-        match *hf as usize {
-            f if f == hash::blake3 as usize => s.serialize_u8(0),
-            _ => Err(ser::Error::custom("unsupported hash function"))?
+        let options = ProofOptions {
+            extension_factor    : raw.extension_factor,
+            num_queries         : raw.num_queries,
+            grinding_factor     : raw.grinding_factor,
+            zero_knowledge      : raw.zero_knowledge,
+            fixed_trace_length  : 0,
+            fixed_ctx_depth     : raw.fixed_ctx_depth,
+            fixed_loop_depth    : raw.fixed_loop_depth,
+            fixed_stack_depth   : raw.fixed_stack_depth,
+            hash_fn             : raw.hash_fn,
+        };
+        if raw.fixed_trace_length != 0
+            && (raw.fixed_trace_length > 32 || (1usize << raw.fixed_trace_length) < options.min_trace_length())
+        {
+            return Err(String::from("fixed trace length"));
         }
-    }
-    
-    pub fn deserialize<'de, D>(deserializer: D) -> Result<HashFunction, D::Error>
-    where
-        D: Deserializer<'de>
-    {
-        match Deserialize::deserialize(deserializer)? {
-            0u8 => Ok(hash::blake3),
-            _ => Err(de::Error::custom("unsupported hash function"))
+
+        // depths are either all fixed or all not fixed, and must be fixed when trace length is
+        let depths = [raw.fixed_ctx_depth as usize, raw.fixed_loop_depth as usize, raw.fixed_stack_depth as usize];
+        let depths_valid = if depths == [0, 0, 0] {
+            raw.fixed_trace_length == 0
         }
+        else {
+            depths[0] >= MIN_CONTEXT_DEPTH && depths[0] <= MAX_CONTEXT_DEPTH
+                && depths[1] >= MIN_LOOP_DEPTH && depths[1] <= MAX_LOOP_DEPTH
+                && depths[2] >= MIN_STACK_DEPTH && depths[2] <= MAX_STACK_DEPTH
+        };
+        if !depths_valid {
+            return Err(String::from("fixed trace depths"));
+        }
+
+        return Ok(ProofOptions { fixed_trace_length: raw.fixed_trace_length, ..options });
     }
-}
\ No newline at end of file
+}
+
+impl Default for ProofOptions {
+
+    fn default() -> ProofOptions {
+        return ProofOptions {
+            extension_factor    : DEFAULT_EXTENSION_FACTOR.trailing_zeros() as u8,
+            num_queries         : DEFAULT_NUM_QUERIES,
+            grinding_factor     : DEFAULT_GRINDING_FACTOR,
+            zero_knowledge      : false,
+            fixed_trace_length  : 0,
+            fixed_ctx_depth     : 0,
+            fixed_loop_depth    : 0,
+            fixed_stack_depth   : 0,
+            hash_fn             : HashFunction::Blake3,
+        };
+    }
+
+}
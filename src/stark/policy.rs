@@ -0,0 +1,107 @@
+use crate::crypto::{ HashFunction };
+use super::{ StarkProof, VerificationError, PolicyError };
+
+// TYPES AND INTERFACES
+// ================================================================================================
+
+/// Describes the minimum requirements a proof must satisfy to be accepted by a verifier. Proof
+/// options are chosen by the prover; a policy makes sure the prover cannot pick options which
+/// are weaker than what the verifier is willing to accept.
+#[derive(Clone)]
+pub struct VerifierPolicy {
+    min_security_level  : u32,
+    conjectured_security: bool,
+    hash_fns            : Vec<HashFunction>,
+    min_trace_length    : usize,
+    max_trace_length    : usize,
+}
+
+// VERIFIER POLICY IMPLEMENTATION
+// ================================================================================================
+impl VerifierPolicy {
+
+    /// Returns a new policy which accepts proofs with proven (non-optimistic) security level
+    /// of at least `min_security_level` bits, generated using one of the `hash_fns`, for
+    /// execution traces with length between `min_trace_length` and `max_trace_length`
+    /// (both inclusive); returns a `PolicyError` if no hash function is allowed, or if the
+    /// range of trace lengths is empty.
+    pub fn new(
+        min_security_level  : u32,
+        hash_fns            : &[HashFunction],
+        min_trace_length    : usize,
+        max_trace_length    : usize) -> Result<VerifierPolicy, PolicyError>
+    {
+        if hash_fns.is_empty() {
+            return Err(PolicyError::NoHashFunctions);
+        }
+        if min_trace_length > max_trace_length {
+            return Err(PolicyError::InvalidTraceLengthRange { min: min_trace_length, max: max_trace_length });
+        }
+
+        return Ok(VerifierPolicy {
+            min_security_level,
+            conjectured_security: false,
+            hash_fns: hash_fns.to_vec(),
+            min_trace_length,
+            max_trace_length,
+        });
+    }
+
+    /// Returns a copy of this policy which compares `min_security_level` against conjectured
+    /// (optimistic) security level of proofs rather than against their proven security level.
+    pub fn with_conjectured_security(&self) -> VerifierPolicy {
+        let mut policy = self.clone();
+        policy.conjectured_security = true;
+        return policy;
+    }
+
+    pub fn min_security_level(&self) -> u32 {
+        return self.min_security_level;
+    }
+
+    /// Returns true if security level of proofs is estimated optimistically.
+    pub fn uses_conjectured_security(&self) -> bool {
+        return self.conjectured_security;
+    }
+
+    pub fn min_trace_length(&self) -> usize {
+        return self.min_trace_length;
+    }
+
+    pub fn max_trace_length(&self) -> usize {
+        return self.max_trace_length;
+    }
+
+    /// Returns true if proofs generated with the specified hash function are allowed.
+    pub fn is_hash_fn_allowed(&self, hash_fn: HashFunction) -> bool {
+        return self.hash_fns.contains(&hash_fn);
+    }
+
+    /// Checks options and trace length of the `proof` against this policy; returns an error
+    /// describing the first requirement the proof fails to satisfy.
+    pub fn validate(&self, proof: &StarkProof) -> Result<(), VerificationError> {
+        let options = proof.options();
+
+        let security_level = options.security_level(self.conjectured_security);
+        if security_level < self.min_security_level {
+            let estimate = if self.conjectured_security { "conjectured" } else { "proven" };
+            return Err(VerificationError::OptionsPolicyViolation(format!(
+                "{} security level of {} bits is below the minimum of {} bits",
+                estimate, security_level, self.min_security_level)));
+        }
+
+        if !self.is_hash_fn_allowed(options.hash_fn()) {
+            return Err(VerificationError::OptionsPolicyViolation(
+                String::from("hash function is not allowed")));
+        }
+
+        let trace_length = proof.trace_length();
+        if trace_length < self.min_trace_length || trace_length > self.max_trace_length {
+            return Err(VerificationError::OptionsPolicyViolation(format!(
+                "trace length {} is outside of the allowed range [{}, {}]",
+                trace_length, self.min_trace_length, self.max_trace_length)));
+        }
+
+        return Ok(());
+    }
+}
@@ -0,0 +1,311 @@
+use serde::{ Serialize, Deserialize };
+use crate::crypto::{ BatchMerkleProof, HashFunction };
+use crate::math::field;
+use crate::stark::{ fri::{ FriProof, FriLayer }, ProofOptions, DeserializationError };
+use super::{ StarkProof, TraceInfo, DeepValues, PROOF_FORMAT_VERSION };
+
+// TYPES AND INTERFACES
+// ================================================================================================
+
+// The JSON representation mirrors the structure of the proof, but does not depend on the layout
+// of the internal types: field elements are written as decimal strings (they do not fit into
+// JSON numbers), hashes as hex strings, and depths of Merkle proofs are omitted since they are
+// implied by the trace domain size.
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JsonProof {
+    version             : u8,
+    options             : JsonOptions,
+    trace_info          : JsonTraceInfo,
+    statement_digest    : String,
+    trace               : JsonTraceQueries,
+    constraints         : JsonMerkleQueries,
+    composition_mask    : Option<JsonMerkleQueries>,
+    deep_values         : JsonDeepValues,
+    fri                 : JsonFriProof,
+    pow_nonce           : String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JsonOptions {
+    extension_factor    : usize,
+    num_queries         : usize,
+    grinding_factor     : u32,
+    zero_knowledge      : bool,
+    fixed_trace_length  : Option<usize>,
+    fixed_trace_depths  : Option<[usize; 3]>,
+    hash_fn             : String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JsonTraceInfo {
+    domain_size         : usize,
+    ctx_depth           : u8,
+    loop_depth          : u8,
+    stack_depth         : u8,
+    op_count            : u32,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JsonTraceQueries {
+    root                : String,
+    evaluations         : Vec<Vec<String>>,
+    nodes               : Vec<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JsonMerkleQueries {
+    root                : String,
+    values              : Vec<String>,
+    nodes               : Vec<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JsonDeepValues {
+    trace_at_z1         : Vec<String>,
+    trace_at_z2         : Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JsonFriProof {
+    layers              : Vec<JsonFriLayer>,
+    remainder_root      : String,
+    remainder           : Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JsonFriLayer {
+    root                : String,
+    values              : Vec<[String; 4]>,
+    nodes               : Vec<Vec<String>>,
+}
+
+// JSON ENCODING
+// ================================================================================================
+impl StarkProof {
+
+    /// Serializes the proof into JSON. Field elements are written as decimal strings, hashes
+    /// as hex strings, and the hash function by its name; the result can be parsed with
+    /// `StarkProof::from_json()`.
+    pub fn to_json(&self) -> String {
+        let options = &self.options;
+        let proof = JsonProof {
+            version             : PROOF_FORMAT_VERSION,
+            options             : JsonOptions {
+                extension_factor    : options.extension_factor(),
+                num_queries         : options.num_queries(),
+                grinding_factor     : options.grinding_factor(),
+                zero_knowledge      : options.zero_knowledge(),
+                fixed_trace_length  : options.fixed_trace_length(),
+                fixed_trace_depths  : options.fixed_trace_depths(),
+                hash_fn             : String::from(options.hash_fn().name()),
+            },
+            trace_info          : JsonTraceInfo {
+                domain_size         : self.domain_size(),
+                ctx_depth           : self.trace_info.ctx_depth,
+                loop_depth          : self.trace_info.loop_depth,
+                stack_depth         : self.trace_info.stack_depth,
+                op_count            : self.trace_info.op_count,
+            },
+            statement_digest    : hex::encode(self.statement_digest),
+            trace               : JsonTraceQueries {
+                root                : hex::encode(self.trace_root),
+                evaluations         : self.trace_evaluations.iter().map(|row| encode_elements(row)).collect(),
+                nodes               : encode_node_lists(&self.trace_nodes),
+            },
+            constraints         : encode_merkle_queries(&self.constraint_root, &self.constraint_proof),
+            composition_mask    : match (&self.mask_root, &self.mask_proof) {
+                (Some(root), Some(proof)) => Some(encode_merkle_queries(root, proof)),
+                _ => None,
+            },
+            deep_values         : JsonDeepValues {
+                trace_at_z1         : encode_elements(&self.deep_values.trace_at_z1),
+                trace_at_z2         : encode_elements(&self.deep_values.trace_at_z2),
+            },
+            fri                 : JsonFriProof {
+                layers              : self.degree_proof.layers.iter().map(|layer| JsonFriLayer {
+                    root                : hex::encode(layer.root),
+                    values              : layer.values.iter().map(|v| [
+                        v[0].to_string(), v[1].to_string(), v[2].to_string(), v[3].to_string()
+                    ]).collect(),
+                    nodes               : encode_node_lists(&layer.nodes),
+                }).collect(),
+                remainder_root      : hex::encode(self.degree_proof.rem_root),
+                remainder           : encode_elements(&self.degree_proof.rem_values),
+            },
+            pow_nonce           : self.pow_nonce.to_string(),
+        };
+        return serde_json::to_string(&proof).expect("failed to serialize proof into JSON");
+    }
+
+    /// Parses a proof serialized with `StarkProof::to_json()`; returns an error if the input
+    /// is not a well-formed JSON representation of a proof.
+    pub fn from_json(json: &str) -> Result<StarkProof, DeserializationError> {
+        let proof: JsonProof = serde_json::from_str(json)
+            .map_err(|error| DeserializationError::InvalidValue(format!("JSON encoding: {}", error)))?;
+        if proof.version != PROOF_FORMAT_VERSION {
+            return Err(DeserializationError::UnsupportedVersion {
+                version: proof.version, expected: PROOF_FORMAT_VERSION });
+        }
+
+        // proof options
+        let hash_fn = HashFunction::from_name(&proof.options.hash_fn)
+            .ok_or_else(|| DeserializationError::InvalidValue(format!("hash function {}", proof.options.hash_fn)))?;
+        let options = ProofOptions::try_new(
+            proof.options.extension_factor,
+            proof.options.num_queries,
+            proof.options.grinding_factor,
+            proof.options.zero_knowledge,
+            proof.options.fixed_trace_length,
+            proof.options.fixed_trace_depths,
+            hash_fn).map_err(DeserializationError::InvalidValue)?;
+
+        // trace info; depths of all Merkle proofs are derived from the trace domain size
+        let domain_size = proof.trace_info.domain_size;
+        if !domain_size.is_power_of_two() || domain_size < 2 {
+            return Err(DeserializationError::InvalidValue(String::from("trace domain size")));
+        }
+        let trace_info = TraceInfo {
+            domain_depth    : domain_size.trailing_zeros() as u8,
+            ctx_depth       : proof.trace_info.ctx_depth,
+            loop_depth      : proof.trace_info.loop_depth,
+            stack_depth     : proof.trace_info.stack_depth,
+            op_count        : proof.trace_info.op_count,
+        };
+        let constraint_depth = trace_info.domain_depth - 1;
+
+        let mask = match &proof.composition_mask {
+            Some(mask) => Some(decode_merkle_queries(mask, constraint_depth)?),
+            None => None,
+        };
+        let (mask_root, mask_proof) = match mask {
+            Some((root, proof)) => (Some(root), Some(proof)),
+            None => (None, None),
+        };
+
+        let mut layers = Vec::with_capacity(proof.fri.layers.len());
+        for (i, layer) in proof.fri.layers.iter().enumerate() {
+            let depth = (trace_info.domain_depth as usize).checked_sub(2 * (i + 1))
+                .ok_or_else(|| DeserializationError::InvalidValue(String::from("number of FRI layers")))?;
+            let mut values = Vec::with_capacity(layer.values.len());
+            for v in layer.values.iter() {
+                values.push([
+                    decode_element(&v[0])?, decode_element(&v[1])?,
+                    decode_element(&v[2])?, decode_element(&v[3])?
+                ]);
+            }
+            layers.push(FriLayer {
+                root    : decode_digest(&layer.root)?,
+                values  : values,
+                nodes   : decode_node_lists(&layer.nodes)?,
+                depth   : depth as u8,
+            });
+        }
+
+        let mut trace_evaluations = Vec::with_capacity(proof.trace.evaluations.len());
+        for row in proof.trace.evaluations.iter() {
+            trace_evaluations.push(decode_elements(row)?);
+        }
+        let (constraint_root, constraint_proof) = decode_merkle_queries(&proof.constraints, constraint_depth)?;
+        let pow_nonce = decode_decimal(&proof.pow_nonce)
+            .and_then(|value| value.parse::<u64>().ok())
+            .ok_or_else(|| DeserializationError::InvalidValue(format!("proof-of-work nonce {}", proof.pow_nonce)))?;
+
+        let result = StarkProof {
+            statement_digest    : decode_digest(&proof.statement_digest)?,
+            trace_root          : decode_digest(&proof.trace.root)?,
+            trace_info          : trace_info,
+            trace_nodes         : decode_node_lists(&proof.trace.nodes)?,
+            trace_evaluations   : trace_evaluations,
+            constraint_root     : constraint_root,
+            constraint_proof    : constraint_proof,
+            mask_root           : mask_root,
+            mask_proof          : mask_proof,
+            deep_values         : DeepValues {
+                trace_at_z1         : decode_elements(&proof.deep_values.trace_at_z1)?,
+                trace_at_z2         : decode_elements(&proof.deep_values.trace_at_z2)?,
+            },
+            degree_proof        : FriProof {
+                layers              : layers,
+                rem_root            : decode_digest(&proof.fri.remainder_root)?,
+                rem_values          : decode_elements(&proof.fri.remainder)?,
+            },
+            pow_nonce           : pow_nonce,
+            options             : options,
+        };
+        result.check_shape().map_err(DeserializationError::InvalidValue)?;
+        return Ok(result);
+    }
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+fn encode_elements(values: &[u128]) -> Vec<String> {
+    return values.iter().map(|v| v.to_string()).collect();
+}
+
+fn encode_node_lists(nodes: &[Vec<[u8; 32]>]) -> Vec<Vec<String>> {
+    return nodes.iter().map(|list| list.iter().map(hex::encode).collect()).collect();
+}
+
+fn encode_merkle_queries(root: &[u8; 32], proof: &BatchMerkleProof) -> JsonMerkleQueries {
+    return JsonMerkleQueries {
+        root    : hex::encode(root),
+        values  : proof.values.iter().map(hex::encode).collect(),
+        nodes   : encode_node_lists(&proof.nodes),
+    };
+}
+
+/// Returns `value` if it consists of decimal digits only; this makes sure every number has
+/// a single representation (e.g. "+1" is rejected).
+fn decode_decimal(value: &str) -> Option<&str> {
+    if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    return Some(value);
+}
+
+fn decode_element(value: &str) -> Result<u128, DeserializationError> {
+    return decode_decimal(value)
+        .and_then(|value| value.parse::<u128>().ok())
+        .filter(|&value| value < field::MODULUS)
+        .ok_or_else(|| DeserializationError::InvalidValue(format!("field element {}", value)));
+}
+
+fn decode_elements(values: &[String]) -> Result<Vec<u128>, DeserializationError> {
+    return values.iter().map(|v| decode_element(v)).collect();
+}
+
+fn decode_digest(value: &str) -> Result<[u8; 32], DeserializationError> {
+    let mut result = [0u8; 32];
+    match hex::decode(value) {
+        Ok(bytes) if bytes.len() == 32 => result.copy_from_slice(&bytes),
+        _ => return Err(DeserializationError::InvalidValue(format!("digest {}", value))),
+    }
+    return Ok(result);
+}
+
+fn decode_node_lists(nodes: &[Vec<String>]) -> Result<Vec<Vec<[u8; 32]>>, DeserializationError> {
+    return nodes.iter().map(|list| list.iter().map(|node| decode_digest(node)).collect()).collect();
+}
+
+fn decode_merkle_queries(queries: &JsonMerkleQueries, depth: u8)
+    -> Result<([u8; 32], BatchMerkleProof), DeserializationError>
+{
+    let root = decode_digest(&queries.root)?;
+    let proof = BatchMerkleProof {
+        values  : queries.values.iter().map(|v| decode_digest(v)).collect::<Result<_, _>>()?,
+        nodes   : decode_node_lists(&queries.nodes)?,
+        depth   : depth,
+    };
+    return Ok((root, proof));
+}
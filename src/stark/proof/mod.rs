@@ -0,0 +1,557 @@
+use serde::{ Serialize, Deserialize };
+use crate::crypto::{ BatchMerkleProof };
+use crate::math::{ polynom };
+use crate::stark::{
+    fri::{ FriProof, FriLayer, MAX_REMAINDER_LENGTH }, TraceState, ProofOptions,
+    VerificationError, DeserializationError,
+    utils::{ ByteWriter, ByteReader },
+};
+use crate::utils::{ uninit_vector, as_bytes };
+use crate::{ MIN_TRACE_LENGTH, MAX_CONTEXT_DEPTH, MAX_LOOP_DEPTH, MAX_STACK_DEPTH };
+
+mod json;
+
+mod stats;
+pub use stats::ProofStats;
+
+// CONSTANTS
+// ================================================================================================
+
+/// Version of the binary proof format produced by `StarkProof::to_bytes()`.
+pub const PROOF_FORMAT_VERSION: u8 = 3;
+
+/// Largest supported evaluation domain is 2^40 (the largest power-of-2 root of unity in the field).
+const MAX_DOMAIN_DEPTH: u8 = 40;
+
+// TYPES AND INTERFACES
+// ================================================================================================
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct StarkProof {
+    statement_digest    : [u8; 32],
+    trace_root          : [u8; 32],
+    trace_info          : TraceInfo,
+    trace_nodes         : Vec<Vec<[u8; 32]>>,
+    trace_evaluations   : Vec<Vec<u128>>,
+    constraint_root     : [u8; 32],
+    constraint_proof    : BatchMerkleProof,
+    mask_root           : Option<[u8; 32]>,
+    mask_proof          : Option<BatchMerkleProof>,
+    deep_values         : DeepValues,
+    degree_proof        : FriProof,
+    pow_nonce           : u64,
+    options             : ProofOptions
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DeepValues {
+    pub trace_at_z1     : Vec<u128>,
+    pub trace_at_z2     : Vec<u128>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TraceInfo {
+    pub domain_depth    : u8,
+    pub ctx_depth       : u8,
+    pub loop_depth      : u8,
+    pub stack_depth     : u8,
+    pub op_count        : u32,
+}
+
+// TRACE INFO IMPLEMENTATION
+// ================================================================================================
+impl TraceInfo {
+    pub fn new(domain_size: usize, ctx_depth: usize, loop_depth: usize, stack_depth: usize, op_count: u128) -> TraceInfo {
+        assert!(domain_size.is_power_of_two(), "domain size must be a power of 2");
+        return TraceInfo {
+            domain_depth    : domain_size.trailing_zeros() as u8,
+            ctx_depth       : ctx_depth as u8,
+            loop_depth      : loop_depth as u8,
+            stack_depth     : stack_depth as u8,
+            op_count        : op_count as u32,
+        };
+    }
+}
+
+// STARK PROOF IMPLEMENTATION
+// ================================================================================================
+impl StarkProof {
+    pub fn new(
+        statement_digest    : &[u8; 32],
+        trace_root          : &[u8; 32],
+        trace_proof         : BatchMerkleProof,
+        trace_evaluations   : Vec<Vec<u128>>,
+        constraint_root     : &[u8; 32],
+        constraint_proof    : BatchMerkleProof,
+        mask_tree           : Option<(&[u8; 32], BatchMerkleProof)>,
+        deep_values         : DeepValues,
+        degree_proof        : FriProof,
+        pow_nonce           : u64,
+        trace_info          : TraceInfo,
+        options             : &ProofOptions ) -> StarkProof
+    {
+        debug_assert!(trace_info.domain_depth == trace_proof.depth, "inconsistent trace domain depth");
+        let (mask_root, mask_proof) = match mask_tree {
+            Some((root, proof)) => (Some(*root), Some(proof)),
+            None => (None, None),
+        };
+
+        return StarkProof {
+            statement_digest    : *statement_digest,
+            trace_root          : *trace_root,
+            trace_info          : trace_info,
+            trace_nodes         : trace_proof.nodes,
+            trace_evaluations   : trace_evaluations,
+            constraint_root     : *constraint_root,
+            constraint_proof    : constraint_proof,
+            mask_root           : mask_root,
+            mask_proof          : mask_proof,
+            deep_values         : deep_values,
+            degree_proof        : degree_proof,
+            pow_nonce           : pow_nonce,
+            options             : options.clone()
+        };
+    }
+
+    /// Returns the seed of the public coin derived from the statement the proof was generated
+    /// for; the verifier compares it with the seed derived from the statement being verified.
+    pub fn statement_digest(&self) -> &[u8; 32] {
+        return &self.statement_digest;
+    }
+
+    pub fn trace_root(&self) -> &[u8; 32] {
+        return &self.trace_root;
+    }
+
+    pub fn options(&self) -> &ProofOptions {
+        return &self.options;
+    }
+
+    pub fn domain_size(&self) -> usize {
+        return usize::pow(2, self.trace_info.domain_depth as u32);
+    }
+
+    pub fn trace_proof(&self) -> BatchMerkleProof {
+
+        let hash_fn = self.options.hash_fn();
+        let mut hashed_states = uninit_vector::<[u8; 32]>(self.trace_evaluations.len());
+        for i in 0..self.trace_evaluations.len() {
+            hash_fn.hash(as_bytes(&self.trace_evaluations[i]), &mut hashed_states[i]);
+        }
+
+        return BatchMerkleProof {
+            nodes   : self.trace_nodes.clone(),
+            values  : hashed_states,
+            depth   : self.trace_info.domain_depth,
+         };
+    }
+
+    pub fn constraint_root(&self) -> &[u8; 32] {
+        return &self.constraint_root;
+    }
+
+    pub fn constraint_proof(&self) -> BatchMerkleProof {
+        return self.constraint_proof.clone();
+    }
+
+    /// Returns the root of the Merkle tree of composition mask polynomial evaluations; this is
+    /// present only in zero-knowledge proofs.
+    pub fn mask_root(&self) -> Option<&[u8; 32]> {
+        return self.mask_root.as_ref();
+    }
+
+    pub fn mask_proof(&self) -> Option<BatchMerkleProof> {
+        return self.mask_proof.clone();
+    }
+
+    pub fn degree_proof(&self) -> &FriProof {
+        return &self.degree_proof;
+    }
+
+    pub fn trace_evaluations(&self) -> &[Vec<u128>] {
+        return &self.trace_evaluations;
+    }
+
+    pub fn pow_nonce(&self) -> u64 {
+        return self.pow_nonce;
+    }
+
+    // TRACE INFO
+    // -------------------------------------------------------------------------------------------
+    pub fn trace_info(&self) -> &TraceInfo {
+        return &self.trace_info;
+    }
+
+    pub fn trace_length(&self) -> usize {
+        return self.domain_size() / self.options.extension_factor();
+    }
+
+    pub fn ctx_depth(&self) -> usize {
+        return self.trace_info.ctx_depth as usize;
+    }
+
+    pub fn loop_depth(&self) -> usize {
+        return self.trace_info.loop_depth as usize;
+    }
+
+    pub fn stack_depth(&self) -> usize {
+        return self.trace_info.stack_depth as usize;
+    }
+
+    /// Returns the number of operations executed by the program; this is 0 for proofs generated
+    /// with options which hide the op count.
+    pub fn op_count(&self) -> u128 {
+        return self.trace_info.op_count as u128;
+    }
+
+    // VALIDATION
+    // -------------------------------------------------------------------------------------------
+
+    /// Makes sure that the shape of every component of the proof is consistent with the options
+    /// and the trace info of the proof; this is done by the verifier before any other checks, so
+    /// that malformed proofs are rejected with an error rather than causing a panic.
+    pub fn validate(&self) -> Result<(), VerificationError> {
+        if self.options.zero_knowledge() && self.mask_root.is_none() {
+            return Err(VerificationError::CompositionMaskMissing);
+        }
+        return self.check_shape().map_err(VerificationError::MalformedProof);
+    }
+
+    fn check_shape(&self) -> Result<(), String> {
+        let info = &self.trace_info;
+        let options = &self.options;
+
+        // trace info
+        if info.ctx_depth as usize > MAX_CONTEXT_DEPTH {
+            return Err(format!("context depth {}", info.ctx_depth));
+        }
+        if info.loop_depth as usize > MAX_LOOP_DEPTH {
+            return Err(format!("loop depth {}", info.loop_depth));
+        }
+        if info.stack_depth as usize > MAX_STACK_DEPTH {
+            return Err(format!("stack depth {}", info.stack_depth));
+        }
+        let min_domain_depth = (options.extension_factor() * options.min_trace_length()).trailing_zeros() as u8;
+        if info.domain_depth < min_domain_depth || info.domain_depth > MAX_DOMAIN_DEPTH {
+            return Err(format!("trace domain size 2^{}", info.domain_depth));
+        }
+        let trace_length = self.trace_length();
+        if options.fixed_trace_length().map_or(false, |length| length != trace_length) {
+            return Err(format!("trace length {}", trace_length));
+        }
+        let depths = [self.ctx_depth(), self.loop_depth(), self.stack_depth()];
+        if options.fixed_trace_depths().map_or(false, |fixed_depths| fixed_depths != depths) {
+            return Err(format!("trace depths {:?}", depths));
+        }
+        // op counter is incremented at most once per step
+        if (options.hides_op_count() && info.op_count != 0) || info.op_count as usize >= trace_length {
+            return Err(format!("operation count {}", info.op_count));
+        }
+
+        // queried trace states and DEEP values must hold values for all trace registers
+        let num_registers = TraceState::compute_user_stack_offset(self.ctx_depth(), self.loop_depth())
+            + self.stack_depth();
+        if self.trace_evaluations.iter().any(|row| row.len() != num_registers) {
+            return Err(String::from("width of trace evaluations"));
+        }
+        if self.deep_values.trace_at_z1.len() != num_registers
+            || self.deep_values.trace_at_z2.len() != num_registers
+        {
+            return Err(String::from("width of DEEP values"));
+        }
+
+        // leaves of constraint and composition mask trees hold 2 evaluations each
+        if self.constraint_proof.depth != info.domain_depth - 1 {
+            return Err(format!("constraint Merkle proof depth {}", self.constraint_proof.depth));
+        }
+        match (&self.mask_root, &self.mask_proof) {
+            (Some(_), Some(mask_proof)) if options.zero_knowledge() => {
+                if mask_proof.depth != info.domain_depth - 1 {
+                    return Err(format!("composition mask Merkle proof depth {}", mask_proof.depth));
+                }
+            },
+            (None, None) if !options.zero_knowledge() => (),
+            _ => return Err(String::from("composition mask commitment")),
+        }
+
+        // every FRI layer reduces the domain by a factor of 4 until the remainder is small enough
+        let mut num_layers = 0;
+        let mut rem_length = self.domain_size();
+        while rem_length > MAX_REMAINDER_LENGTH {
+            rem_length = rem_length / 4;
+            num_layers += 1;
+        }
+        let layers = &self.degree_proof.layers;
+        if layers.len() != num_layers {
+            return Err(format!("number of FRI layers {}", layers.len()));
+        }
+        for (i, layer) in layers.iter().enumerate() {
+            if layer.depth != info.domain_depth - 2 * (i as u8 + 1) {
+                return Err(format!("depth {} of FRI layer {}", layer.depth, i));
+            }
+        }
+        if self.degree_proof.rem_values.len() != rem_length {
+            return Err(format!("FRI remainder length {}", self.degree_proof.rem_values.len()));
+        }
+
+        return Ok(());
+    }
+
+    // DEEP VALUES
+    // -------------------------------------------------------------------------------------------
+    pub fn get_state_at_z1(&self) -> TraceState {
+        return TraceState::from_vec(
+            self.ctx_depth(),
+            self.loop_depth(),
+            self.stack_depth(),
+            &self.deep_values.trace_at_z1);
+    }
+
+    pub fn get_state_at_z2(&self) -> TraceState {
+        return TraceState::from_vec(
+            self.ctx_depth(),
+            self.loop_depth(),
+            self.stack_depth(),
+            &self.deep_values.trace_at_z2);
+    }
+
+    // SERIALIZATION
+    // -------------------------------------------------------------------------------------------
+
+    /// Serializes the proof into a compact binary format. Compared to `bincode` encoding, lengths
+    /// are written as varints, lengths of rows of field elements are written once, depths of
+    /// Merkle proofs are omitted (they are implied by the trace domain size), Merkle nodes
+    /// repeated anywhere in the proof are replaced with references to their first occurrence,
+    /// and the FRI remainder is written as coefficients of a polynomial rather than evaluations.
+    /// Returns an error if the proof does not pass `StarkProof::validate()`; this can happen only
+    /// for proofs deserialized with `serde` (e.g. from `bincode`).
+    pub fn to_bytes(&self) -> Result<Vec<u8>, VerificationError> {
+        let mut target = ByteWriter::new();
+        self.write_into(&mut target)?;
+        return Ok(target.into_bytes());
+    }
+
+    /// Parses a proof serialized with `StarkProof::to_bytes()`; returns an error if the input
+    /// is not a well-formed encoding of a proof.
+    pub fn from_bytes(bytes: &[u8]) -> Result<StarkProof, DeserializationError> {
+        let mut source = ByteReader::new(bytes);
+        let proof = StarkProof::read_from(&mut source)?;
+        source.finish()?;
+        return Ok(proof);
+    }
+
+    /// Returns sizes of the components of the proof in the binary format produced by
+    /// `StarkProof::to_bytes()`; returns an error if the proof does not pass
+    /// `StarkProof::validate()`.
+    pub fn stats(&self) -> Result<ProofStats, VerificationError> {
+        return self.write_sections(&mut ByteWriter::new());
+    }
+
+    /// Writes the proof into the `target` using the format of `StarkProof::to_bytes()`; returns
+    /// an error and leaves the `target` unchanged if the proof does not pass
+    /// `StarkProof::validate()`.
+    pub fn write_into(&self, target: &mut ByteWriter) -> Result<(), VerificationError> {
+        self.write_sections(target)?;
+        return Ok(());
+    }
+
+    /// Writes the proof into the `target` and returns the number of bytes taken up by each
+    /// section of the proof. The shape of the proof is validated first because the encoding
+    /// relies on it (e.g. the FRI remainder must have a power-of-2 length to be interpolated).
+    fn write_sections(&self, target: &mut ByteWriter) -> Result<ProofStats, VerificationError> {
+        self.validate()?;
+
+        let mut stats = ProofStats::default();
+        let mut offset = target.len();
+
+        target.write_u8(PROOF_FORMAT_VERSION);
+        self.options.write_into(target);
+
+        // trace info
+        target.write_u8(self.trace_info.domain_depth);
+        target.write_u8(self.trace_info.ctx_depth);
+        target.write_u8(self.trace_info.loop_depth);
+        target.write_u8(self.trace_info.stack_depth);
+        target.write_varint(self.trace_info.op_count as u64);
+        target.write_digest(&self.statement_digest);
+        stats.header = section_size(target, &mut offset);
+
+        // trace commitment and queried trace states
+        target.write_digest(&self.trace_root);
+        target.write_usize(self.trace_evaluations.len());
+        target.write_usize(self.trace_evaluations.first().map_or(0, |row| row.len()));
+        self.trace_evaluations.iter().for_each(|row| target.write_elements(row));
+        target.write_node_lists(&self.trace_nodes);
+        stats.trace_queries = section_size(target, &mut offset);
+
+        // constraint and composition mask commitments
+        target.write_digest(&self.constraint_root);
+        write_merkle_proof(target, &self.constraint_proof);
+        target.write_bool(self.mask_root.is_some());
+        if let (Some(mask_root), Some(mask_proof)) = (&self.mask_root, &self.mask_proof) {
+            target.write_digest(mask_root);
+            write_merkle_proof(target, mask_proof);
+        }
+        stats.constraint_queries = section_size(target, &mut offset);
+
+        // DEEP values; trace states at z1 and z2 have the same width
+        target.write_usize(self.deep_values.trace_at_z1.len());
+        target.write_elements(&self.deep_values.trace_at_z1);
+        target.write_elements(&self.deep_values.trace_at_z2);
+        stats.deep_values = section_size(target, &mut offset);
+
+        // degree proof; the number of layers is counted towards the size of the remainder
+        target.write_usize(self.degree_proof.layers.len());
+        let mut remainder_size = section_size(target, &mut offset);
+        for layer in self.degree_proof.layers.iter() {
+            target.write_digest(&layer.root);
+            target.write_usize(layer.values.len());
+            layer.values.iter().for_each(|values| target.write_elements(values));
+            target.write_node_lists(&layer.nodes);
+            stats.fri_layers.push(section_size(target, &mut offset));
+        }
+        target.write_digest(&self.degree_proof.rem_root);
+
+        // the remainder is written as coefficients of its polynomial with trailing zeros removed;
+        // the polynomial has a low degree, and so it is much shorter than the evaluations are
+        let mut rem_poly = self.degree_proof.rem_values.clone();
+        polynom::interpolate_fft(&mut rem_poly, true);
+        let rem_poly_length = rem_poly.iter().rposition(|&c| c != 0).map_or(0, |i| i + 1);
+        target.write_usize(rem_poly_length);
+        target.write_elements(&rem_poly[..rem_poly_length]);
+        remainder_size += section_size(target, &mut offset);
+        stats.fri_remainder = remainder_size;
+
+        target.write_varint(self.pow_nonce);
+        stats.pow_nonce = section_size(target, &mut offset);
+
+        return Ok(stats);
+    }
+
+    /// Reads a proof written by `StarkProof::write_into()` from the `source`; returns an error if
+    /// the source does not contain a well-formed encoding of a proof.
+    pub fn read_from(source: &mut ByteReader) -> Result<StarkProof, DeserializationError> {
+        let version = source.read_u8()?;
+        if version != PROOF_FORMAT_VERSION {
+            return Err(DeserializationError::UnsupportedVersion { version, expected: PROOF_FORMAT_VERSION });
+        }
+        let options = ProofOptions::read_from(source)?;
+
+        // trace info
+        let trace_info = TraceInfo {
+            domain_depth    : source.read_u8()?,
+            ctx_depth       : source.read_u8()?,
+            loop_depth      : source.read_u8()?,
+            stack_depth     : source.read_u8()?,
+            op_count        : read_u32(source)?,
+        };
+        let min_domain_depth = (options.extension_factor() * MIN_TRACE_LENGTH).trailing_zeros() as u8;
+        if trace_info.domain_depth < min_domain_depth || trace_info.domain_depth > MAX_DOMAIN_DEPTH {
+            return Err(DeserializationError::InvalidValue(String::from("trace domain size")));
+        }
+        let statement_digest = source.read_digest()?;
+
+        // trace commitment and queried trace states
+        let trace_root = source.read_digest()?;
+        let num_rows = source.read_length(1)?;
+        let row_width = source.read_usize()?;
+        let mut trace_evaluations = Vec::with_capacity(num_rows);
+        for _ in 0..num_rows {
+            trace_evaluations.push(source.read_elements(row_width)?);
+        }
+        let trace_nodes = source.read_node_lists()?;
+
+        // constraint and composition mask commitments; leaves of these trees hold 2 evaluations
+        let constraint_depth = trace_info.domain_depth - 1;
+        let constraint_root = source.read_digest()?;
+        let constraint_proof = read_merkle_proof(source, constraint_depth)?;
+        let (mask_root, mask_proof) = if source.read_bool()? {
+            (Some(source.read_digest()?), Some(read_merkle_proof(source, constraint_depth)?))
+        }
+        else {
+            (None, None)
+        };
+
+        // DEEP values
+        let deep_width = source.read_length(32)?;
+        let deep_values = DeepValues {
+            trace_at_z1 : source.read_elements(deep_width)?,
+            trace_at_z2 : source.read_elements(deep_width)?,
+        };
+
+        // degree proof; every FRI layer reduces the domain by a factor of 4, and leaves of
+        // layer trees hold 4 evaluations
+        let num_layers = source.read_length(32)?;
+        if 2 * num_layers + 2 > trace_info.domain_depth as usize {
+            return Err(DeserializationError::InvalidValue(String::from("number of FRI layers")));
+        }
+        let mut layers = Vec::with_capacity(num_layers);
+        for i in 0..num_layers {
+            let root = source.read_digest()?;
+            let num_values = source.read_length(64)?;
+            let mut values = Vec::with_capacity(num_values);
+            for _ in 0..num_values {
+                let row = source.read_elements(4)?;
+                values.push([row[0], row[1], row[2], row[3]]);
+            }
+            let nodes = source.read_node_lists()?;
+            let depth = trace_info.domain_depth - 2 * (i as u8 + 1);
+            layers.push(FriLayer { root, values, nodes, depth });
+        }
+        let rem_root = source.read_digest()?;
+        let num_rem_values = 1usize << (trace_info.domain_depth as usize - 2 * num_layers);
+        if num_rem_values > MAX_REMAINDER_LENGTH {
+            return Err(DeserializationError::InvalidValue(String::from("number of FRI layers")));
+        }
+        let rem_poly_length = source.read_length(16)?;
+        if rem_poly_length > num_rem_values {
+            return Err(DeserializationError::InvalidValue(String::from("FRI remainder length")));
+        }
+        let mut rem_values = source.read_elements(rem_poly_length)?;
+        if rem_poly_length > 0 && rem_values[rem_poly_length - 1] == 0 {
+            return Err(DeserializationError::InvalidValue(String::from("FRI remainder coefficients")));
+        }
+        rem_values.resize(num_rem_values, 0);
+        polynom::eval_fft(&mut rem_values, true);
+        let degree_proof = FriProof { layers, rem_root, rem_values };
+
+        let pow_nonce = source.read_varint()?;
+
+        let proof = StarkProof {
+            statement_digest, trace_root, trace_info, trace_nodes, trace_evaluations,
+            constraint_root, constraint_proof, mask_root, mask_proof,
+            deep_values, degree_proof, pow_nonce, options
+        };
+        proof.check_shape().map_err(DeserializationError::InvalidValue)?;
+        return Ok(proof);
+    }
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+fn section_size(target: &ByteWriter, offset: &mut usize) -> usize {
+    let size = target.len() - *offset;
+    *offset = target.len();
+    return size;
+}
+
+fn write_merkle_proof(target: &mut ByteWriter, proof: &BatchMerkleProof) {
+    target.write_usize(proof.values.len());
+    target.write_digests(&proof.values);
+    target.write_node_lists(&proof.nodes);
+}
+
+fn read_merkle_proof(source: &mut ByteReader, depth: u8) -> Result<BatchMerkleProof, DeserializationError> {
+    let num_values = source.read_length(1)?;
+    let values = source.read_digests(num_values)?;
+    let nodes = source.read_node_lists()?;
+    return Ok(BatchMerkleProof { values, nodes, depth });
+}
+
+fn read_u32(source: &mut ByteReader) -> Result<u32, DeserializationError> {
+    let value = source.read_varint()?;
+    if value > u32::MAX as u64 {
+        return Err(DeserializationError::InvalidValue(String::from("operation count")));
+    }
+    return Ok(value as u32);
+}
\ No newline at end of file
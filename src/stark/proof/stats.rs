@@ -0,0 +1,53 @@
+// TYPES AND INTERFACES
+// ================================================================================================
+
+/// Sizes (in bytes) of the components of a proof encoded with `StarkProof::to_bytes()`. Merkle
+/// nodes repeated across the proof are counted only where they first occur.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProofStats {
+    /// Format version, proof options, and trace info.
+    pub header              : usize,
+    /// Trace commitment, queried trace states and their Merkle authentication paths.
+    pub trace_queries       : usize,
+    /// Constraint (and composition mask) commitments together with queried evaluations.
+    pub constraint_queries  : usize,
+    /// Trace states at the out-of-domain points z1 and z2.
+    pub deep_values         : usize,
+    /// Commitments and queried values of each FRI layer.
+    pub fri_layers          : Vec<usize>,
+    /// FRI remainder together with its commitment and the number of FRI layers.
+    pub fri_remainder       : usize,
+    /// Proof-of-work nonce.
+    pub pow_nonce           : usize,
+}
+
+// PROOF STATS IMPLEMENTATION
+// ================================================================================================
+impl ProofStats {
+
+    /// Returns the total size of the proof; this is the same as the length of `to_bytes()` output.
+    pub fn total(&self) -> usize {
+        return self.header
+            + self.trace_queries
+            + self.constraint_queries
+            + self.deep_values
+            + self.fri_layers.iter().sum::<usize>()
+            + self.fri_remainder
+            + self.pow_nonce;
+    }
+}
+
+impl std::fmt::Display for ProofStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "header:             {:>8} bytes", self.header)?;
+        writeln!(f, "trace queries:      {:>8} bytes", self.trace_queries)?;
+        writeln!(f, "constraint queries: {:>8} bytes", self.constraint_queries)?;
+        writeln!(f, "DEEP values:        {:>8} bytes", self.deep_values)?;
+        for (i, size) in self.fri_layers.iter().enumerate() {
+            writeln!(f, "FRI layer {:<2}:       {:>8} bytes", i, size)?;
+        }
+        writeln!(f, "FRI remainder:      {:>8} bytes", self.fri_remainder)?;
+        writeln!(f, "proof-of-work:      {:>8} bytes", self.pow_nonce)?;
+        write!(f, "total:              {:>8} bytes", self.total())
+    }
+}
@@ -1,30 +1,55 @@
 use std::time::Instant;
 use log::debug;
+use crossbeam_utils::thread;
 use crate::{
-    math::{ field, polynom, fft },
+    math::{ field, polynom, fft, parallel },
     crypto::MerkleTree,
+    utils::as_bytes,
 };
 use super::{
-    ProofOptions, StarkProof, CompositionCoefficients, DeepValues, fri, utils,
+    ProofOptions, ProverConfig, StarkProof, TraceInfo, CompositionCoefficients, DeepValues, fri, utils,
     trace::{ TraceTable, TraceState },
-    constraints::{ ConstraintTable, ConstraintPoly },
-    MAX_CONSTRAINT_DEGREE,
+    constraints::{ ConstraintTable, ConstraintTableFragment, ConstraintPoly },
 };
 
 // PROVER FUNCTION
 // ================================================================================================
 
-pub fn prove(trace: &mut TraceTable, inputs: &[u128], outputs: &[u128], options: &ProofOptions) -> StarkProof {
+pub fn prove(
+    trace           : &mut TraceTable,
+    inputs          : &[u128],
+    outputs         : &[u128],
+    output_positions: &[usize],
+    stack_size      : Option<usize>,
+    options         : &ProofOptions,
+    config          : &ProverConfig) -> StarkProof
+{
+    let num_threads = config.num_threads();
+
     // 1 ----- extend execution trace -------------------------------------------------------------
     let now = Instant::now();
 
+    // seed the public coin with the statement being proven; all pseudo-random values used by
+    // the protocol are drawn from this coin; if op count is hidden, it is recorded as 0
+    let op_count = if options.hides_op_count() { 0 } else { trace.get_last_state().op_counter() };
+    let trace_info = TraceInfo::new(
+        trace.domain_size(),
+        trace.ctx_depth(),
+        trace.loop_depth(),
+        trace.stack_depth(),
+        op_count);
+    let mut program_hash = [0u8; 32];
+    program_hash.copy_from_slice(as_bytes(trace.get_last_state().program_hash()));
+    let mut coin = utils::PublicCoin::new(&program_hash, inputs, outputs, output_positions, stack_size, &trace_info, options);
+    let statement_digest = coin.seed();
+
     // build LDE domain and LDE twiddles (for FFT evaluation over LDE domain)
     let lde_root = field::get_root_of_unity(trace.domain_size());
     let lde_domain = field::get_power_series(lde_root, trace.domain_size());
     let lde_twiddles = twiddles_from_domain(&lde_domain);
 
     // extend the execution trace registers to LDE domain
-    trace.extend(&lde_twiddles);
+    trace.extend(&lde_twiddles, num_threads);
     debug!("Extended execution trace from {} to {} steps in {} ms",
         trace.unextended_length(),
         trace.domain_size(), 
@@ -32,36 +57,31 @@ pub fn prove(trace: &mut TraceTable, inputs: &[u128], outputs: &[u128], options:
 
     // 2 ----- build Merkle tree from the extended execution trace ------------------------------------
     let now = Instant::now();
-    let trace_tree = trace.build_merkle_tree(options.hash_fn());
+    let trace_tree = trace.build_merkle_tree(options.hash_fn(), num_threads);
     debug!("Built trace Merkle tree in {} ms", 
         now.elapsed().as_millis());
 
     // 3 ----- evaluate constraints ---------------------------------------------------------------
     let now = Instant::now();
     
-    // initialize constraint evaluation table
-    let mut constraints = ConstraintTable::new(&trace, trace_tree.root(), inputs, outputs);
+    // initialize constraint evaluation table; constraint coefficients are drawn after the
+    // trace commitment is absorbed into the public coin
+    coin.reseed(trace_tree.root());
+    let mut constraints = ConstraintTable::new(&trace, &coin.seed(), options.hides_op_count(), inputs, outputs, output_positions, stack_size);
     
-    // allocate space to hold current and next states for constraint evaluations
-    let mut current = TraceState::new(trace.ctx_depth(), trace.loop_depth(), trace.stack_depth());
-    let mut next = TraceState::new(trace.ctx_depth(), trace.loop_depth(), trace.stack_depth());
-
     // we don't need to evaluate constraints over the entire extended execution trace; we need
     // to evaluate them over the domain extended to match max constraint degree - thus, we can
-    // skip most trace states for the purposes of constraint evaluation.
-    let stride = trace.extension_factor() / MAX_CONSTRAINT_DEGREE;
-    for i in (0..trace.domain_size()).step_by(stride) {
-        // TODO: this loop should be parallelized and also potentially optimized to avoid copying
-        // next state from the trace table twice
-
-        // copy current and next states from the trace table; next state may wrap around the
-        // execution trace (close to the end of the trace)
-        trace.fill_state(&mut current, i);
-        trace.fill_state(&mut next, (i + trace.extension_factor()) % trace.domain_size());
-
-        // evaluate the constraints
-        constraints.evaluate(&current, &next, lde_domain[i], i / stride);
-    }
+    // skip most trace states for the purposes of constraint evaluation; the evaluation domain
+    // is split into fragments of consecutive steps which are evaluated in separate threads
+    let stride = trace.domain_size() / constraints.evaluation_domain_size();
+    let num_fragments = parallel::get_num_batches(constraints.evaluation_domain_size(), num_threads);
+    let trace_ref = &*trace;
+    let lde_domain_ref = &lde_domain;
+    thread::scope(|s| {
+        for fragment in constraints.fragments(num_fragments) {
+            s.spawn(move |_| evaluate_fragment(fragment, trace_ref, lde_domain_ref, stride));
+        }
+    }).unwrap();
 
     debug!("Evaluated {} constraints over domain of {} elements in {} ms",
         constraints.constraint_count(),
@@ -70,7 +90,7 @@ pub fn prove(trace: &mut TraceTable, inputs: &[u128], outputs: &[u128], options:
 
     // 4 ----- convert constraint evaluations into a polynomial -----------------------------------
     let now = Instant::now();
-    let constraint_poly = constraints.combine_polys();
+    let constraint_poly = constraints.combine_polys(num_threads);
     debug!("Converted constraint evaluations into a single polynomial of degree {} in {} ms",
         constraint_poly.degree(),
         now.elapsed().as_millis());
@@ -79,26 +99,48 @@ pub fn prove(trace: &mut TraceTable, inputs: &[u128], outputs: &[u128], options:
     let now = Instant::now();
     
     // evaluate constraint polynomial over the evaluation domain
-    let constraint_evaluations = constraint_poly.eval(&lde_twiddles);
+    let constraint_evaluations = constraint_poly.eval(&lde_twiddles, num_threads);
 
     // put evaluations into a Merkle tree; 4 evaluations per leaf
     let constraint_evaluations = evaluations_to_leaves(constraint_evaluations);
-    let constraint_tree = MerkleTree::new(constraint_evaluations, options.hash_fn());
+    let constraint_tree = MerkleTree::new_parallel(constraint_evaluations, options.hash_fn(), num_threads);
     debug!("Evaluated constraint polynomial and built constraint Merkle tree in {} ms",
         now.elapsed().as_millis());
+    coin.reseed(constraint_tree.root());
+
+    // in zero-knowledge mode, commit to a random polynomial of the same degree as the
+    // composition polynomial; it is added to the composition polynomial to mask FRI layers
+    let composition_degree = utils::get_composition_degree(trace.unextended_length(), options.zero_knowledge());
+    let mask = if options.zero_knowledge() {
+        let now = Instant::now();
+        let mask_evaluations = build_mask_evaluations(composition_degree, &lde_twiddles, num_threads);
+        let mask_tree = MerkleTree::new_parallel(evaluations_to_leaves(mask_evaluations.clone()), options.hash_fn(), num_threads);
+        coin.reseed(mask_tree.root());
+        debug!("Built composition mask polynomial and its Merkle tree in {} ms",
+            now.elapsed().as_millis());
+        Some((mask_evaluations, mask_tree))
+    }
+    else {
+        None
+    };
 
     // 6 ----- build and evaluate deep composition polynomial -------------------------------------
     let now = Instant::now();
 
     // combine trace and constraint polynomials into the final deep composition polynomial
-    let seed = constraint_tree.root();
-    let (composition_poly, deep_values) = build_composition_poly(&trace, constraint_poly, seed);
+    let (composition_poly, deep_values) = build_composition_poly(&trace, constraint_poly, &coin.seed(), num_threads);
 
     // evaluate the composition polynomial over LDE domain
     let mut composed_evaluations = composition_poly;
     debug_assert!(composed_evaluations.capacity() == lde_domain.len(), "invalid composition polynomial capacity");
     unsafe { composed_evaluations.set_len(composed_evaluations.capacity()); }
-    polynom::eval_fft_twiddles(&mut composed_evaluations, &lde_twiddles, true);
+    let lde_threads = parallel::get_num_batches(lde_domain.len(), num_threads);
+    polynom::eval_fft_twiddles_par(&mut composed_evaluations, &lde_twiddles, true, lde_threads);
+
+    // mask the composition polynomial
+    if let Some((mask_evaluations, _)) = &mask {
+        parallel::add_in_place(&mut composed_evaluations, mask_evaluations, lde_threads);
+    }
 
     debug!("Built composition polynomial and evaluated it over domain of {} elements in {} ms",
         composed_evaluations.len(),
@@ -106,9 +148,8 @@ pub fn prove(trace: &mut TraceTable, inputs: &[u128], outputs: &[u128], options:
 
     // 7 ----- compute FRI layers for the composition polynomial ----------------------------------
     let now = Instant::now();
-    let composition_degree = utils::get_composition_degree(trace.unextended_length());
     debug_assert!(composition_degree == polynom::infer_degree(&composed_evaluations));
-    let (fri_trees, fri_values) = fri::reduce(&composed_evaluations, &lde_domain, options);
+    let (fri_trees, fri_values) = fri::reduce(&composed_evaluations, &lde_domain, options, num_threads);
     debug!("Computed {} FRI layers from composition polynomial evaluations in {} ms",
     fri_trees.len(),
         now.elapsed().as_millis());
@@ -122,12 +163,11 @@ pub fn prove(trace: &mut TraceTable, inputs: &[u128], outputs: &[u128], options:
         tree.root().iter().for_each(|&v| fri_roots.push(v));
     }
 
-    // derive a seed from the combined roots
-    let mut seed = [0u8; 32];
-    options.hash_fn()(&fri_roots, &mut seed);
+    // absorb the combined roots into the public coin
+    coin.reseed(&fri_roots);
 
     // apply proof-of-work to get a new seed
-    let (seed, pow_nonce) = utils::find_pow_nonce(seed, &options);
+    let (seed, pow_nonce) = utils::find_pow_nonce(coin.seed(), &options, num_threads);
 
     // generate pseudo-random query positions
     let positions = utils::compute_query_positions(&seed, lde_domain.len(), options);
@@ -150,18 +190,17 @@ pub fn prove(trace: &mut TraceTable, inputs: &[u128], outputs: &[u128], options:
 
     // build the proof object
     let proof = StarkProof::new(
+        &statement_digest,
         trace_tree.root(),
         trace_tree.prove_batch(&positions),
         trace_evaluations,
         constraint_tree.root(),
         constraint_tree.prove_batch(&constraint_positions),
+        mask.as_ref().map(|(_, tree)| (tree.root(), tree.prove_batch(&constraint_positions))),
         deep_values,
         fri_proof,
         pow_nonce,
-        trace.get_last_state().op_counter(),
-        trace.ctx_depth(),
-        trace.loop_depth(),
-        trace.stack_depth(),
+        trace_info,
         &options);
 
     debug!("Built proof object in {} ms", now.elapsed().as_millis());
@@ -170,6 +209,36 @@ pub fn prove(trace: &mut TraceTable, inputs: &[u128], outputs: &[u128], options:
 
 // HELPER FUNCTIONS
 // ================================================================================================
+
+/// Evaluates constraints at all steps covered by the `fragment`. The next state for step i is
+/// the current state for step i + extension_factor / stride, and so states are kept in a ring
+/// buffer which makes sure every state is copied from the trace table only once.
+fn evaluate_fragment(mut fragment: ConstraintTableFragment<'_>, trace: &TraceTable, lde_domain: &[u128], stride: usize) {
+    let domain_size = trace.domain_size();
+    let next_offset = trace.extension_factor() / stride;
+    let ring_size = next_offset + 1;
+
+    // fill the ring buffer with states for the first step of the fragment and the steps
+    // following it; states may wrap around the execution trace (close to the end of the trace)
+    let first_step = fragment.offset();
+    let mut states = Vec::with_capacity(ring_size);
+    for step in first_step..(first_step + ring_size) {
+        let mut state = TraceState::new(trace.ctx_depth(), trace.loop_depth(), trace.stack_depth());
+        trace.fill_state(&mut state, (step * stride) % domain_size);
+        states.push(state);
+    }
+
+    for step in first_step..(first_step + fragment.size()) {
+        let current = (step - first_step) % ring_size;
+        let next = (step - first_step + next_offset) % ring_size;
+        fragment.evaluate(&states[current], &states[next], lde_domain[step * stride], step);
+
+        // the current state is not needed anymore; replace it with the state which will
+        // be the next state for the following step
+        trace.fill_state(&mut states[current], ((step + ring_size) * stride) % domain_size);
+    }
+}
+
 fn twiddles_from_domain(domain: &[u128]) -> Vec<u128> {
     let mut twiddles = domain[..(domain.len() / 2)].to_vec();
     fft::permute(&mut twiddles);
@@ -186,16 +255,25 @@ fn evaluations_to_leaves(evaluations: Vec<u128>) -> Vec<[u8; 32]> {
     return unsafe { Vec::from_raw_parts(p as *mut [u8; 32], len, cap) };
 }
 
-fn build_composition_poly(trace: &TraceTable, constraint_poly: ConstraintPoly, seed: &[u8; 32]) -> (Vec<u128>, DeepValues) {
+/// Returns evaluations of a random polynomial of the specified degree over the LDE domain.
+fn build_mask_evaluations(degree: usize, twiddles: &[u128], num_threads: usize) -> Vec<u128> {
+    let mut evaluations = field::rand_vector(degree + 1);
+    evaluations.resize(twiddles.len() * 2, field::ZERO);
+    let num_threads = parallel::get_num_batches(evaluations.len(), num_threads);
+    polynom::eval_fft_twiddles_par(&mut evaluations, twiddles, true, num_threads);
+    return evaluations;
+}
+
+fn build_composition_poly(trace: &TraceTable, constraint_poly: ConstraintPoly, seed: &[u8; 32], num_threads: usize) -> (Vec<u128>, DeepValues) {
     // pseudo-randomly selection deep point z and coefficients for the composition
     let z = field::prng(*seed);
     let coefficients = CompositionCoefficients::new(*seed);
 
     // divide out deep point from trace polynomials and merge them into a single polynomial
-    let (mut result, s1, s2) = trace.get_composition_poly(z, &coefficients);
+    let (mut result, s1, s2) = trace.get_composition_poly(z, &coefficients, num_threads);
 
     // divide out deep point from constraint polynomial and merge it into the result
-    constraint_poly.merge_into(&mut result, z, &coefficients);
+    constraint_poly.merge_into(&mut result, z, &coefficients, num_threads);
 
     return (result, DeepValues { trace_at_z1: s1, trace_at_z2: s2 });
 }
\ No newline at end of file
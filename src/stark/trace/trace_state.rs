@@ -26,6 +26,7 @@ pub struct TraceState {
     hd_op_bits  : [u128; NUM_HD_OP_BITS],
     ctx_stack   : Vec<u128>,
     loop_stack  : Vec<u128>,
+    stack_size  : u128,
     user_stack  : Vec<u128>,
 
     ctx_depth   : usize,
@@ -57,6 +58,7 @@ impl TraceState {
             hd_op_bits  : [0; NUM_HD_OP_BITS],
             ctx_stack   : vec![0; cmp::max(ctx_depth, MIN_CONTEXT_DEPTH)],
             loop_stack  : vec![0; cmp::max(loop_depth, MIN_LOOP_DEPTH)],
+            stack_size  : 0,
             user_stack  : vec![0; cmp::max(stack_depth, MIN_STACK_DEPTH)],
             ctx_depth   : ctx_depth,
             loop_depth  : loop_depth,
@@ -94,13 +96,15 @@ impl TraceState {
         let loop_stack_end = ctx_stack_end + loop_depth;
         loop_stack[..loop_depth].copy_from_slice(&state[ctx_stack_end..loop_stack_end]);
 
+        let stack_size = state[loop_stack_end];
+
         let mut user_stack = vec![0; cmp::max(stack_depth, MIN_STACK_DEPTH)];
-        user_stack[..stack_depth].copy_from_slice(&state[loop_stack_end..]);
+        user_stack[..stack_depth].copy_from_slice(&state[(loop_stack_end + 1)..]);
 
         return TraceState {
             op_counter, sponge,
             cf_op_bits, ld_op_bits, hd_op_bits,
-            ctx_stack, loop_stack, user_stack,
+            ctx_stack, loop_stack, stack_size, user_stack,
             ctx_depth, loop_depth, stack_depth,
             cf_op_flags : [0; NUM_CF_OPS],
             ld_op_flags : [0; NUM_LD_OPS],
@@ -117,10 +121,16 @@ impl TraceState {
         return NUM_STATIC_DECODER_REGISTERS + ctx_depth + loop_depth;
     }
 
+    /// Returns the index of the first user stack register; user stack registers follow decoder
+    /// registers and the stack size register.
+    pub fn compute_user_stack_offset(ctx_depth: usize, loop_depth: usize) -> usize {
+        return Self::compute_decoder_width(ctx_depth, loop_depth) + 1;
+    }
+
     // PUBLIC ACCESSORS
     // --------------------------------------------------------------------------------------------
     pub fn width(&self) -> usize {
-        return HD_OP_BITS_RANGE.end + self.ctx_depth + self.loop_depth + self.stack_depth;
+        return HD_OP_BITS_RANGE.end + self.ctx_depth + self.loop_depth + 1 + self.stack_depth;
     }
 
     pub fn stack_depth(&self) -> usize {
@@ -229,6 +239,12 @@ impl TraceState {
         return &self.loop_stack;
     }
 
+    /// Returns the number of items on the user stack; unlike stack depth, which is the number
+    /// of user stack registers, this value changes from step to step.
+    pub fn stack_size(&self) -> u128 {
+        return self.stack_size;
+    }
+
     pub fn user_stack(&self) -> &[u128] {
         return &self.user_stack;
     }
@@ -244,6 +260,7 @@ impl TraceState {
         result.extend_from_slice(&self.hd_op_bits);
         result.extend_from_slice(&self.ctx_stack[..self.ctx_depth]);
         result.extend_from_slice(&self.loop_stack[..self.loop_depth]);
+        result.push(self.stack_size);
         result.extend_from_slice(&self.user_stack[..self.stack_depth]);
         return result;
     }
@@ -268,8 +285,11 @@ impl TraceState {
             self.loop_stack[i] = trace[j][step];
         }
 
-        let user_stack_end = loop_stack_end + self.stack_depth;
-        for (i, j) in (loop_stack_end..user_stack_end).enumerate() {
+        self.stack_size = trace[loop_stack_end][step];
+
+        let user_stack_start = loop_stack_end + 1;
+        let user_stack_end = user_stack_start + self.stack_depth;
+        for (i, j) in (user_stack_start..user_stack_end).enumerate() {
             self.user_stack[i] = trace[j][step];
         }
         
@@ -298,7 +318,7 @@ impl TraceState {
         let not_1 = binary_not(self.ld_op_bits[1]);
         self.ld_op_flags[0] = field::mul(not_0, not_1);
         self.ld_op_flags[1] = field::mul(self.ld_op_bits[0], not_1);
-        self.ld_op_flags[2] = field::mul(not_0, self.cf_op_bits[1]);
+        self.ld_op_flags[2] = field::mul(not_0, self.ld_op_bits[1]);
         self.ld_op_flags[3] = field::mul(self.ld_op_bits[0], self.ld_op_bits[1]);
         self.ld_op_flags.copy_within(0..4, 4);
 
@@ -352,7 +372,7 @@ impl TraceState {
 
 impl fmt::Debug for TraceState {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "[{:>4}] {:>32X?} {:?} {:?} {:?} {:>32X?} {:>32X?} {:?}",
+        write!(f, "[{:>4}] {:>32X?} {:?} {:?} {:?} {:>32X?} {:>32X?} {:>2} {:?}",
             self.op_counter,
             self.sponge, 
             self.cf_op_bits,
@@ -360,6 +380,7 @@ impl fmt::Debug for TraceState {
             self.hd_op_bits,
             self.ctx_stack,
             self.loop_stack,
+            self.stack_size,
             self.user_stack
         )
     }
@@ -367,7 +388,7 @@ impl fmt::Debug for TraceState {
 
 impl fmt::Display for TraceState {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "[{:>4}] {:>16X?} {:?} {:?} {:?} {:>16X?} {:>16X?} {:?}",
+        write!(f, "[{:>4}] {:>16X?} {:?} {:?} {:?} {:>16X?} {:>16X?} {:>2} {:?}",
             self.op_counter,
             self.sponge.iter().map(|x| x >> 64).collect::<Vec<u128>>(),
             self.cf_op_bits,
@@ -375,6 +396,7 @@ impl fmt::Display for TraceState {
             self.hd_op_bits,
             self.ctx_stack.iter().map(|x| x >> 64).collect::<Vec<u128>>(),
             self.loop_stack.iter().map(|x| x >> 64).collect::<Vec<u128>>(),
+            self.stack_size,
             &self.user_stack[..self.stack_depth]
         )
     }
@@ -399,7 +421,7 @@ mod tests {
 
         // empty context and loop stacks
         let state = TraceState::from_vec(0, 0, 2, &vec![
-            101,  1, 2, 3, 4,  5, 6, 7,  8, 9, 10, 11, 12,  13, 14,  15, 16
+            101,  1, 2, 3, 4,  5, 6, 7,  8, 9, 10, 11, 12,  13, 14,  2,  15, 16
         ]);
 
         assert_eq!(101, state.op_counter());
@@ -409,16 +431,17 @@ mod tests {
         assert_eq!([13, 14], state.hd_op_bits());
         assert_eq!([0], state.ctx_stack());
         assert_eq!([0], state.loop_stack());
+        assert_eq!(2, state.stack_size());
         assert_eq!([15, 16, 0, 0, 0, 0, 0, 0], state.user_stack());
-        assert_eq!(17, state.width());
+        assert_eq!(18, state.width());
         assert_eq!(2, state.stack_depth());
         assert_eq!(vec![
-            101, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16
+            101, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 2, 15, 16
         ], state.to_vec());
 
         // 1 item on context stack, empty loop stack
         let state = TraceState::from_vec(1, 0, 2, &vec![
-            101,  1, 2, 3, 4,  5, 6, 7,  8, 9, 10, 11, 12,  13, 14,  15,  16, 17
+            101,  1, 2, 3, 4,  5, 6, 7,  8, 9, 10, 11, 12,  13, 14,  15,  1,  16, 17
         ]);
 
         assert_eq!(101, state.op_counter());
@@ -428,16 +451,17 @@ mod tests {
         assert_eq!([13, 14], state.hd_op_bits());
         assert_eq!([15], state.ctx_stack());
         assert_eq!([0], state.loop_stack());
+        assert_eq!(1, state.stack_size());
         assert_eq!([16, 17, 0, 0, 0, 0, 0, 0], state.user_stack());
-        assert_eq!(18, state.width());
+        assert_eq!(19, state.width());
         assert_eq!(2, state.stack_depth());
         assert_eq!(vec![
-            101, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17
+            101, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 1, 16, 17
         ], state.to_vec());
 
         // non-empty loop stack
         let state = TraceState::from_vec(2, 1, 9, &vec![
-            101,  1, 2, 3, 4,  5, 6, 7,  8, 9, 10, 11, 12,  13, 14,  15, 16,  17,
+            101,  1, 2, 3, 4,  5, 6, 7,  8, 9, 10, 11, 12,  13, 14,  15, 16,  17,  9,
             18, 19, 20, 21, 22, 23, 24, 25, 26,
         ]);
 
@@ -448,11 +472,12 @@ mod tests {
         assert_eq!([13, 14], state.hd_op_bits());
         assert_eq!([15, 16], state.ctx_stack());
         assert_eq!([17], state.loop_stack());
+        assert_eq!(9, state.stack_size());
         assert_eq!([18, 19, 20, 21, 22, 23, 24, 25, 26], state.user_stack());
-        assert_eq!(27, state.width());
+        assert_eq!(28, state.width());
         assert_eq!(9, state.stack_depth());
         assert_eq!(vec![
-            101, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17,
+            101, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 9,
             18, 19, 20, 21, 22, 23, 24, 25, 26,
         ], state.to_vec());
     }
@@ -460,7 +485,7 @@ mod tests {
     #[test]
     fn update_from_trace() {
         let data = vec![
-            101,  1, 2, 3, 4,  5, 6, 7,  8, 9, 10, 11, 12,  13, 14,  15, 16,  17,  18, 19, 20
+            101,  1, 2, 3, 4,  5, 6, 7,  8, 9, 10, 11, 12,  13, 14,  15, 16,  17,  3,  18, 19, 20
         ];
         let mut trace = Vec::with_capacity(data.len());
         for i in 0..data.len() {
@@ -478,8 +503,9 @@ mod tests {
         assert_eq!([0, 0], state.hd_op_bits());
         assert_eq!([0, 0], state.ctx_stack());
         assert_eq!([0], state.loop_stack());
+        assert_eq!(0, state.stack_size());
         assert_eq!([0, 0, 0, 0, 0, 0, 0, 0], state.user_stack());
-        assert_eq!(21, state.width());
+        assert_eq!(22, state.width());
         assert_eq!(3, state.stack_depth());
 
         // second row
@@ -492,8 +518,9 @@ mod tests {
         assert_eq!([13, 14], state.hd_op_bits());
         assert_eq!([15, 16], state.ctx_stack());
         assert_eq!([17], state.loop_stack());
+        assert_eq!(3, state.stack_size());
         assert_eq!([18, 19, 20, 0, 0, 0, 0, 0], state.user_stack());
-        assert_eq!(21, state.width());
+        assert_eq!(22, state.width());
         assert_eq!(3, state.stack_depth());
     }
 
@@ -502,7 +529,7 @@ mod tests {
 
         // all zeros
         let state = TraceState::from_vec(1, 0, 2, &vec![
-            101,  1, 2, 3, 4,  0, 0, 0,  0, 0, 0, 0, 0,  0, 0,  15, 16, 17
+            101,  1, 2, 3, 4,  0, 0, 0,  0, 0, 0, 0, 0,  0, 0,  15, 2, 16, 17
         ]);
 
         assert_eq!([1, 0, 0, 0, 0, 0, 0, 0], state.cf_op_flags());
@@ -516,7 +543,7 @@ mod tests {
 
         // all ones
         let state = TraceState::from_vec(1, 0, 2, &vec![
-            101,  1, 2, 3, 4,  1, 1, 1,  1, 1, 1, 1, 1,  1, 1,  15, 16, 17
+            101,  1, 2, 3, 4,  1, 1, 1,  1, 1, 1, 1, 1,  1, 1,  15, 2, 16, 17
         ]);
 
         assert_eq!([0, 0, 0, 0, 0, 0, 0, 1], state.cf_op_flags());
@@ -530,7 +557,7 @@ mod tests {
 
         // mixed 1
         let state = TraceState::from_vec(1, 0, 2, &vec![
-            101,  1, 2, 3, 4,  1, 0, 0,  1, 0, 0, 0, 0,  1, 0,  15, 16, 17
+            101,  1, 2, 3, 4,  1, 0, 0,  1, 0, 0, 0, 0,  1, 0,  15, 2, 16, 17
         ]);
 
         assert_eq!([0, 1, 0, 0, 0, 0, 0, 0], state.cf_op_flags());
@@ -544,7 +571,7 @@ mod tests {
 
         // mixed 2
         let state = TraceState::from_vec(1, 0, 2, &vec![
-            101, 1, 2, 3, 4, 1, 1, 0, 1, 1, 0, 0, 0, 0, 1, 15, 16, 17
+            101, 1, 2, 3, 4, 1, 1, 0, 1, 1, 0, 0, 0, 0, 1, 15, 2, 16, 17
         ]);
 
         assert_eq!([0, 0, 0, 1, 0, 0, 0, 0], state.cf_op_flags());
@@ -553,27 +580,39 @@ mod tests {
             0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
         ], state.ld_op_flags());
         assert_eq!([0, 0, 1, 0], state.hd_op_flags());
+
+        // mixed 3: low-degree op flags do not depend on control flow op bits
+        let state = TraceState::from_vec(1, 0, 2, &vec![
+            101, 1, 2, 3, 4, 1, 0, 1, 0, 1, 0, 0, 0, 1, 1, 15, 2, 16, 17
+        ]);
+
+        assert_eq!([0, 0, 0, 0, 0, 1, 0, 0], state.cf_op_flags());
+        assert_eq!([
+            0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        ], state.ld_op_flags());
+        assert_eq!([0, 0, 0, 1], state.hd_op_flags());
     }
 
     #[test]
     fn op_code() {
         let state = TraceState::from_vec(1, 0, 2, &vec![
-            101,  1, 2, 3, 4,  1, 1, 1,  0, 0, 0, 0, 0,  0, 0,  15, 16, 17
+            101,  1, 2, 3, 4,  1, 1, 1,  0, 0, 0, 0, 0,  0, 0,  15, 2, 16, 17
         ]);
         assert_eq!(0, state.op_code());
 
         let state = TraceState::from_vec(1, 0, 2, &vec![
-            101,  1, 2, 3, 4,  1, 1, 1,  1, 1, 1, 1, 1,  1, 1,  15, 16, 17
+            101,  1, 2, 3, 4,  1, 1, 1,  1, 1, 1, 1, 1,  1, 1,  15, 2, 16, 17
         ]);
         assert_eq!(127, state.op_code());
 
         let state = TraceState::from_vec(1, 0, 2, &vec![
-            101,  1, 2, 3, 4,  1, 1, 1,  1, 1, 1, 1, 1,  1, 0,  15, 16, 17
+            101,  1, 2, 3, 4,  1, 1, 1,  1, 1, 1, 1, 1,  1, 0,  15, 2, 16, 17
         ]);
         assert_eq!(63, state.op_code());
 
         let state = TraceState::from_vec(1, 0, 2, &vec![
-            101,  1, 2, 3, 4,  1, 1, 1,  1, 0, 0, 0, 0,  1, 1,  15, 16, 17
+            101,  1, 2, 3, 4,  1, 1, 1,  1, 0, 0, 0, 0,  1, 1,  15, 2, 16, 17
         ]);
         assert_eq!(97, state.op_code());
     }
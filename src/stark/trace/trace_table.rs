@@ -1,3 +1,4 @@
+use crossbeam_utils::thread;
 use crate::math::{ field, fft, polynom, parallel };
 use crate::crypto::{ MerkleTree, HashFunction };
 use crate::stark::{ CompositionCoefficients, utils };
@@ -14,13 +15,20 @@ pub struct TraceTable {
     stack_depth     : usize,
     trace_length    : usize,
     extension_factor: usize,
+    zero_knowledge  : bool,
 }
 
 // TRACE TABLE IMPLEMENTATION
 // ================================================================================================
 impl TraceTable {
-    /// Returns a trace table constructed from the specified register traces.
-    pub fn new(registers: Vec<Vec<u128>>, ctx_depth: usize, loop_depth: usize, extension_factor: usize) -> TraceTable
+    /// Returns a trace table constructed from the specified register traces. If `zero_knowledge`
+    /// is set, trace polynomials are randomized when the trace table is extended.
+    pub fn new(
+        registers       : Vec<Vec<u128>>,
+        ctx_depth       : usize,
+        loop_depth      : usize,
+        extension_factor: usize,
+        zero_knowledge  : bool) -> TraceTable
     {
         // validate extension factor
         assert!(extension_factor.is_power_of_two(), "trace extension factor must be a power of 2");
@@ -36,9 +44,9 @@ impl TraceTable {
             "loop depth cannot be greater than {}", crate::MAX_LOOP_DEPTH);
 
         // compute stack depth
-        let decoder_width = TraceState::compute_decoder_width(ctx_depth, loop_depth);
-        assert!(registers.len() > decoder_width, "user stack must consist of at least one register");
-        let stack_depth = registers.len() - decoder_width;
+        let user_stack_offset = TraceState::compute_user_stack_offset(ctx_depth, loop_depth);
+        assert!(registers.len() > user_stack_offset, "user stack must consist of at least one register");
+        let stack_depth = registers.len() - user_stack_offset;
 
         // validate register traces
         assert!(registers.len() < crate::MAX_REGISTER_COUNT,
@@ -53,7 +61,7 @@ impl TraceTable {
         return TraceTable {
             registers, polys,
             ctx_depth, loop_depth, stack_depth,
-            trace_length, extension_factor
+            trace_length, extension_factor, zero_knowledge
         };
     }
 
@@ -95,6 +103,12 @@ impl TraceTable {
         return self.extension_factor;
     }
 
+    /// Returns the number of coefficients in trace polynomials; this is equal to the length
+    /// of the trace, unless trace polynomials are randomized.
+    pub fn poly_length(&self) -> usize {
+        return utils::get_trace_poly_length(self.trace_length, self.zero_knowledge);
+    }
+
     /// Returns the number of registers in the trace table.
     pub fn register_count(&self) -> usize {
         return self.registers.len();
@@ -140,7 +154,15 @@ impl TraceTable {
 
     /// Extends all registers of the trace table by the `extension_factor` specified during
     /// trace table construction. A trace table can be extended only once.
-    pub fn extend(&mut self, twiddles: &[u128]) {
+    ///
+    /// In zero-knowledge mode, each trace polynomial T(x) is replaced with
+    /// T(x) + R(x) * (x^steps - 1), where R(x) is a random polynomial of degree steps - 1. The
+    /// randomized polynomial is equal to T(x) over the trace domain (so all constraints still
+    /// hold), but its evaluations outside of the trace domain are uniformly random.
+    /// 
+    /// Registers are extended independently of each other, and so they are distributed across
+    /// up to `num_threads` threads.
+    pub fn extend(&mut self, twiddles: &[u128], num_threads: usize) {
         assert!(!self.is_extended(), "trace table has already been extended");
         assert!(twiddles.len() * 2 == self.domain_size(), "invalid number of twiddles");
 
@@ -151,37 +173,56 @@ impl TraceTable {
         // move register traces into polys
         std::mem::swap(&mut self.registers, &mut self.polys);
 
+        // split registers into batches, one batch per thread; if there are more threads than
+        // registers, the remaining threads are used to parallelize FFTs within each batch
+        let num_threads = std::cmp::max(num_threads, 1);
+        let batch_size = (self.polys.len() + num_threads - 1) / num_threads;
+        let num_batches = (self.polys.len() + batch_size - 1) / batch_size;
+        let fft_threads = parallel::get_num_batches(self.trace_length, num_threads / num_batches);
+
         // extend all registers
         let domain_size = self.domain_size();
-        for poly in self.polys.iter_mut() {
-
-            // interpolate register trace into a polynomial
-            polynom::interpolate_fft_twiddles(poly, &inv_twiddles, true);
-            
-            // allocate space to hold extended evaluations and copy the polynomial into it
-            let mut register = vec![field::ZERO; domain_size];
-            register[..poly.len()].copy_from_slice(&poly);
-            
-            // evaluate the polynomial over extended domain
-            polynom::eval_fft_twiddles(&mut register, &twiddles, true);
-            self.registers.push(register);
-        }
+        let trace_length = self.trace_length;
+        let zero_knowledge = self.zero_knowledge;
+        let inv_twiddles = &inv_twiddles;
+        let polys = &mut self.polys;
+        self.registers = thread::scope(|s| {
+            let handles = polys.chunks_mut(batch_size).map(|batch| s.spawn(move |_| {
+                return batch.iter_mut()
+                    .map(|poly| extend_register(poly, inv_twiddles, twiddles, trace_length, domain_size, zero_knowledge, fft_threads))
+                    .collect::<Vec<_>>();
+            })).collect::<Vec<_>>();
+            return handles.into_iter().flat_map(|handle| handle.join().unwrap()).collect();
+        }).unwrap();
     }
 
     /// Puts the trace table into a Merkle tree such that each state of the table becomes
     /// a distinct leaf in the tree; all registers at a given step are hashed together to
-    /// form a single leaf value.
-    pub fn build_merkle_tree(&self, hash: HashFunction) -> MerkleTree {
-        let mut trace_state = vec![field::ZERO; self.register_count()];
+    /// form a single leaf value. Both hashing of states and building of the tree are done
+    /// in up to `num_threads` threads.
+    /// 
+    /// Leaves are hashed only after the table has been fully extended: registers are extended
+    /// one at a time, and the extended registers are needed after the tree is built anyway (to
+    /// answer trace queries and to build the composition polynomial).
+    pub fn build_merkle_tree(&self, hash_fn: HashFunction, num_threads: usize) -> MerkleTree {
+        let num_batches = parallel::get_num_batches(self.domain_size(), num_threads);
+        let batch_size = self.domain_size() / num_batches;
         let mut hashed_states = uninit_vector::<[u8; 32]>(self.domain_size());
-        // TODO: this loop should be parallelized
-        for i in 0..self.domain_size() {
-            for j in 0..trace_state.len() {
-                trace_state[j] = self.registers[j][i];
+        thread::scope(|s| {
+            for (i, batch) in hashed_states.chunks_mut(batch_size).enumerate() {
+                s.spawn(move |_| {
+                    let mut trace_state = vec![field::ZERO; self.register_count()];
+                    let offset = i * batch_size;
+                    for (j, hash) in batch.iter_mut().enumerate() {
+                        for (k, register) in self.registers.iter().enumerate() {
+                            trace_state[k] = register[offset + j];
+                        }
+                        hash_fn.hash(as_bytes(&trace_state), hash);
+                    }
+                });
             }
-            hash(as_bytes(&trace_state), &mut hashed_states[i]);
-        }
-        return MerkleTree::new(hashed_states, hash);
+        }).unwrap();
+        return MerkleTree::new_parallel(hashed_states, hash_fn, num_threads);
     }
 
     /// Evaluates trace polynomials at the specified point `z`; can be called only after
@@ -203,7 +244,7 @@ impl TraceTable {
     /// T2_i(x) = (T_i(x) - T_i(z * g)) / (x - z * g) are computed for all i and combined
     /// together into a single polynomial using a pseudo-random linear combination;
     /// 3. Then the degree of the polynomial is adjusted to match the specified degree
-    pub fn get_composition_poly(&self, z: u128, cc: &CompositionCoefficients) -> (Vec<u128>, Vec<u128>, Vec<u128>) {
+    pub fn get_composition_poly(&self, z: u128, cc: &CompositionCoefficients, num_threads: usize) -> (Vec<u128>, Vec<u128>, Vec<u128>) {
 
         let trace_length = self.unextended_length();
         assert!(self.is_extended(), "trace table has not been extended yet");
@@ -215,20 +256,22 @@ impl TraceTable {
         let trace_state1 = self.eval_polys_at(z);
         let trace_state2 = self.eval_polys_at(next_z);
 
-        let mut t1_composition = vec![field::ZERO; trace_length];
-        let mut t2_composition = vec![field::ZERO; trace_length];
+        let poly_length = self.poly_length();
+        let mut t1_composition = vec![field::ZERO; poly_length];
+        let mut t2_composition = vec![field::ZERO; poly_length];
+        let num_threads = parallel::get_num_batches(poly_length, num_threads);
 
         // combine trace polynomials into 2 composition polynomials T1(x) and T2(x)
         for i in 0..self.polys.len() {
             // compute T1(x) = (T(x) - T(z)), multiply it by a pseudo-random coefficient,
             // and add the result into composition polynomial
-            parallel::mul_acc(&mut t1_composition, &self.polys[i], cc.trace1[i], 1);
+            parallel::mul_acc(&mut t1_composition, &self.polys[i], cc.trace1[i], num_threads);
             let adjusted_tz = field::mul(trace_state1[i], cc.trace1[i]);
             t1_composition[0] = field::sub(t1_composition[0], adjusted_tz);
 
             // compute T2(x) = (T(x) - T(z * g)), multiply it by a pseudo-random
             // coefficient, and add the result into composition polynomial
-            parallel::mul_acc(&mut t2_composition, &self.polys[i], cc.trace2[i], 1);
+            parallel::mul_acc(&mut t2_composition, &self.polys[i], cc.trace2[i], num_threads);
             let adjusted_tz = field::mul(trace_state2[i], cc.trace2[i]);
             t2_composition[0] = field::sub(t2_composition[0], adjusted_tz);
         }
@@ -237,30 +280,66 @@ impl TraceTable {
         // respectively and add the resulting polynomials together
         polynom::syn_div_in_place(&mut t1_composition, z);
         polynom::syn_div_in_place(&mut t2_composition, next_z);
-        parallel::add_in_place(&mut t1_composition, &t2_composition, 1);
+        parallel::add_in_place(&mut t1_composition, &t2_composition, num_threads);
 
         // adjust the degree of the polynomial to match the degree parameter by computing
         // C(x) = T(x) * k_1 + T(x) * x^incremental_degree * k_2
-        let poly_size = utils::get_composition_degree(trace_length).next_power_of_two();
+        let poly_size = utils::get_composition_degree(trace_length, self.zero_knowledge).next_power_of_two();
         let mut composition_poly = filled_vector(poly_size, self.domain_size(), field::ZERO);
-        let incremental_degree = utils::get_incremental_trace_degree(trace_length);
+        let incremental_degree = utils::get_incremental_trace_degree(trace_length, self.zero_knowledge);
         // this is equivalent to T(x) * k_1
         parallel::mul_acc(
-            &mut composition_poly[..trace_length],
+            &mut composition_poly[..poly_length],
             &t1_composition,
             cc.t1_degree,
-            1);
+            num_threads);
         // this is equivalent to T(x) * x^incremental_degree * k_2
         parallel::mul_acc(
-            &mut composition_poly[incremental_degree..(incremental_degree + trace_length)],
+            &mut composition_poly[incremental_degree..(incremental_degree + poly_length)],
             &t1_composition,
             cc.t2_degree,
-            1);
+            num_threads);
         
         return (composition_poly, trace_state1, trace_state2);
     }
 }
 
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Interpolates a single register trace into a polynomial (randomizing the polynomial in
+/// zero-knowledge mode), and returns evaluations of this polynomial over the extended domain.
+fn extend_register(
+    poly            : &mut Vec<u128>,
+    inv_twiddles    : &[u128],
+    twiddles        : &[u128],
+    trace_length    : usize,
+    domain_size     : usize,
+    zero_knowledge  : bool,
+    num_threads     : usize) -> Vec<u128>
+{
+    // interpolate register trace into a polynomial
+    polynom::interpolate_fft_twiddles_par(poly, inv_twiddles, true, num_threads);
+
+    // randomize the polynomial by adding R(x) * (x^steps - 1) to it
+    if zero_knowledge {
+        let randomizer = field::rand_vector(trace_length);
+        poly.resize(trace_length * 2, field::ZERO);
+        for (i, &r) in randomizer.iter().enumerate() {
+            poly[i] = field::sub(poly[i], r);
+            poly[i + trace_length] = r;
+        }
+    }
+    
+    // allocate space to hold extended evaluations and copy the polynomial into it
+    let mut register = vec![field::ZERO; domain_size];
+    register[..poly.len()].copy_from_slice(&poly);
+    
+    // evaluate the polynomial over extended domain
+    polynom::eval_fft_twiddles_par(&mut register, twiddles, true, num_threads);
+    return register;
+}
+
 // TESTS
 // ================================================================================================
 
@@ -270,7 +349,7 @@ mod tests {
     use std::collections::HashMap;
     use crate::{
         math::{ field, polynom, parallel, fft },
-        crypto::hash::blake3,
+        crypto::HashFunction,
         programs::{ Program, ProgramInputs, blocks::{ ProgramBlock, Span, Group } },
         processor::{ execute, OpCode },
         stark::{ TraceTable, CompositionCoefficients, utils::get_composition_degree }
@@ -280,9 +359,9 @@ mod tests {
 
     #[test]
     fn eval_polys_at() {
-        let mut trace = build_trace_table();
+        let mut trace = build_trace_table(false);
         let lde_root = field::get_root_of_unity(trace.domain_size());
-        trace.extend(&fft::get_twiddles(lde_root, trace.domain_size()));
+        trace.extend(&fft::get_twiddles(lde_root, trace.domain_size()), 1);
 
         let g = field::get_root_of_unity(trace.unextended_length());
 
@@ -295,23 +374,66 @@ mod tests {
         assert_eq!(v2, s2.to_vec());
     }
 
+    #[test]
+    fn eval_blinded_polys_at() {
+        let mut trace = build_trace_table(true);
+        let lde_root = field::get_root_of_unity(trace.domain_size());
+        trace.extend(&fft::get_twiddles(lde_root, trace.domain_size()), 1);
+
+        // randomized polynomials have twice as many coefficients as there are steps
+        let trace_length = trace.unextended_length();
+        assert_eq!(trace_length * 2, trace.poly_length());
+        assert_eq!(trace_length * 2 - 1, polynom::degree_of(trace.get_register_poly(0)));
+
+        // but over the trace domain, they still evaluate to the original trace
+        let mut original = build_trace_table(false);
+        let g = field::get_root_of_unity(trace_length);
+        for step in 0..trace_length {
+            let v = trace.eval_polys_at(field::exp(g, step as u128));
+            assert_eq!(original.get_state(step).to_vec(), v);
+        }
+
+        // evaluations outside of the trace domain are different from the original trace
+        original.extend(&fft::get_twiddles(lde_root, original.domain_size()), 1);
+        assert_ne!(original.get_state(1).to_vec(), trace.get_state(1).to_vec());
+    }
+
+    #[test]
+    fn extend_in_parallel() {
+        let mut expected = build_trace_table(false);
+        let lde_root = field::get_root_of_unity(expected.domain_size());
+        let twiddles = fft::get_twiddles(lde_root, expected.domain_size());
+        expected.extend(&twiddles, 1);
+        let expected_tree = expected.build_merkle_tree(HashFunction::Blake3, 1);
+
+        for &num_threads in [2, 3, 64].iter() {
+            let mut trace = build_trace_table(false);
+            trace.extend(&twiddles, num_threads);
+            for step in 0..trace.domain_size() {
+                assert_eq!(expected.get_state(step).to_vec(), trace.get_state(step).to_vec());
+            }
+            let tree = trace.build_merkle_tree(HashFunction::Blake3, num_threads);
+            assert_eq!(expected_tree.root(), tree.root());
+        }
+    }
+
     #[test]
     fn get_composition_poly() {
 
-        let mut trace = build_trace_table();
+        let mut trace = build_trace_table(false);
         let lde_root = field::get_root_of_unity(trace.domain_size());
-        trace.extend(&fft::get_twiddles(lde_root, trace.domain_size()));
+        trace.extend(&fft::get_twiddles(lde_root, trace.domain_size()), 1);
 
         // compute trace composition polynomial
-        let t_tree = trace.build_merkle_tree(blake3);
+        let t_tree = trace.build_merkle_tree(HashFunction::Blake3, 1);
         let z = field::prng(*t_tree.root());
         let cc = CompositionCoefficients::new(*t_tree.root());
-        let target_degree =  get_composition_degree(trace.unextended_length());
+        let target_degree =  get_composition_degree(trace.unextended_length(), false);
 
         let g = field::get_root_of_unity(trace.unextended_length());
         let zg = field::mul(z, g);
 
-        let (composition_poly, ..) = trace.get_composition_poly(z, &cc);
+        let (composition_poly, ..) = trace.get_composition_poly(z, &cc, 1);
         let mut actual_evaluations = composition_poly.clone();
         polynom::eval_fft(&mut actual_evaluations, true);
         assert_eq!(target_degree, polynom::infer_degree(&actual_evaluations));
@@ -362,7 +484,7 @@ mod tests {
         assert_eq!(expected_evaluations, actual_evaluations);
     }
 
-    fn build_trace_table() -> TraceTable {
+    fn build_trace_table(zero_knowledge: bool) -> TraceTable {
         let instructions = vec![
             OpCode::Begin, OpCode::Swap, OpCode::Dup2, OpCode::Drop,
             OpCode::Add,   OpCode::Swap, OpCode::Dup2, OpCode::Drop,
@@ -373,7 +495,7 @@ mod tests {
             ProgramBlock::Span(Span::new(instructions, HashMap::new()))
         ]));
         let inputs = ProgramInputs::from_public(&[1, 0]);
-        let (trace, ctx_depth, loop_depth) = execute(&program, &inputs);
-        return TraceTable::new(trace, ctx_depth, loop_depth, EXT_FACTOR);
+        let (trace, ctx_depth, loop_depth, _) = execute(&program, &inputs).unwrap();
+        return TraceTable::new(trace, ctx_depth, loop_depth, EXT_FACTOR, zero_knowledge);
     }
 }
\ No newline at end of file
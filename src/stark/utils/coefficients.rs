@@ -2,24 +2,26 @@ use std::{ ops::Range };
 use crate::{
     math::field,
     utils::RangeSlider,
-    MAX_REGISTER_COUNT, MAX_PUBLIC_INPUTS,
+    MAX_REGISTER_COUNT,
     SPONGE_WIDTH,
     MAX_CONTEXT_DEPTH, MAX_LOOP_DEPTH, MAX_STACK_DEPTH,
     MIN_CONTEXT_DEPTH, MIN_LOOP_DEPTH, MIN_STACK_DEPTH,
     NUM_CF_OP_BITS, NUM_LD_OP_BITS, NUM_HD_OP_BITS,
-    stark::constraints::{ NUM_STATIC_DECODER_CONSTRAINTS, NUM_AUX_STACK_CONSTRAINTS },
+    stark::constraints::{
+        NUM_STATIC_DECODER_CONSTRAINTS, NUM_AUX_STACK_CONSTRAINTS, NUM_STACK_SIZE_CONSTRAINTS },
 };
 
 // CONSTANTS
 // ================================================================================================
 const NUM_OP_BITS: usize = NUM_CF_OP_BITS + NUM_LD_OP_BITS + NUM_HD_OP_BITS;
-const MAX_USER_STACK_IO_CONSTRAINTS: usize = MAX_PUBLIC_INPUTS; // same as MAX_OUTPUTS
+const MAX_USER_STACK_IO_CONSTRAINTS: usize = MAX_STACK_DEPTH; // inputs and outputs can use all stack registers
 const NUM_BOUNDARY_CONSTRAINTS: usize =
     1   // for op_counter
     + SPONGE_WIDTH
     + NUM_OP_BITS
     + MAX_CONTEXT_DEPTH
     + MAX_LOOP_DEPTH
+    + 1 // for stack size
     + MAX_USER_STACK_IO_CONSTRAINTS;
 
 const NUM_TRANSITION_CONSTRAINTS: usize =
@@ -28,7 +30,8 @@ const NUM_TRANSITION_CONSTRAINTS: usize =
     + MAX_CONTEXT_DEPTH
     + MAX_LOOP_DEPTH
     + MAX_STACK_DEPTH
-    + NUM_AUX_STACK_CONSTRAINTS;
+    + NUM_AUX_STACK_CONSTRAINTS
+    + NUM_STACK_SIZE_CONSTRAINTS;
 
 const NUM_CONSTRAINTS: usize = NUM_TRANSITION_CONSTRAINTS + 2 * NUM_BOUNDARY_CONSTRAINTS;
 
@@ -46,6 +49,7 @@ pub struct BoundaryCoefficients {
     pub op_bits     : [u128; NUM_OP_BITS * 2],
     pub ctx_stack   : [u128; MAX_CONTEXT_DEPTH * 2],
     pub loop_stack  : [u128; MAX_LOOP_DEPTH * 2],
+    pub stack_size  : [u128; 2],
     pub user_stack  : [u128; MAX_USER_STACK_IO_CONSTRAINTS * 2],
 }
 
@@ -113,6 +117,7 @@ fn build_boundary_coefficients(coefficients: &[u128]) -> (BoundaryCoefficients,
         op_bits     : [0; NUM_OP_BITS * 2],
         ctx_stack   : [0; MAX_CONTEXT_DEPTH * 2],
         loop_stack  : [0; MAX_LOOP_DEPTH * 2],
+        stack_size  : [0; 2],
         user_stack  : [0; MAX_USER_STACK_IO_CONSTRAINTS * 2],
     };
 
@@ -131,6 +136,9 @@ fn build_boundary_coefficients(coefficients: &[u128]) -> (BoundaryCoefficients,
     range = range.slide(MAX_LOOP_DEPTH * 2);
     result.loop_stack.copy_from_slice(&coefficients[range.clone()]);
 
+    range = range.slide(2);
+    result.stack_size.copy_from_slice(&coefficients[range.clone()]);
+
     range = range.slide(MAX_USER_STACK_IO_CONSTRAINTS * 2);
     result.user_stack.copy_from_slice(&coefficients[range.clone()]);
 
@@ -148,7 +156,8 @@ fn build_transition_coefficients(coefficients: &[u128], ctx_depth: usize, loop_d
         + ctx_depth
         + loop_depth
         + stack_depth
-        + NUM_AUX_STACK_CONSTRAINTS;
+        + NUM_AUX_STACK_CONSTRAINTS
+        + NUM_STACK_SIZE_CONSTRAINTS;
 
     // we need 2 coefficients per constraint
     let mut result = vec![0; num_constraints * 2];
@@ -159,7 +168,8 @@ fn build_transition_coefficients(coefficients: &[u128], ctx_depth: usize, loop_d
     // 2. context stack constraints - the number depends on the actual context depth
     // 3. loop stack constraints - the number depends on the actual loop depth
     // 4. aux stack constraints
-    // 5. user stack constraints - the number depends on the actual stack depth
+    // 5. stack size constraints
+    // 6. user stack constraints - the number depends on the actual stack depth
 
     let mut s_range = new_range(0, NUM_STATIC_DECODER_CONSTRAINTS * 2);
     let mut t_range = new_range(0, NUM_STATIC_DECODER_CONSTRAINTS * 2);
@@ -177,6 +187,10 @@ fn build_transition_coefficients(coefficients: &[u128], ctx_depth: usize, loop_d
     t_range = t_range.slide(NUM_AUX_STACK_CONSTRAINTS * 2);
     result[t_range.clone()].copy_from_slice(&coefficients[s_range.clone()]);
 
+    s_range = s_range.slide(NUM_STACK_SIZE_CONSTRAINTS * 2);
+    t_range = t_range.slide(NUM_STACK_SIZE_CONSTRAINTS * 2);
+    result[t_range.clone()].copy_from_slice(&coefficients[s_range.clone()]);
+
     s_range = s_range.slide(stack_depth * 2);
     t_range = t_range.slide(stack_depth * 2);
     result[t_range.clone()].copy_from_slice(&coefficients[s_range.clone()]);
@@ -10,16 +10,31 @@ pub use coefficients::{ ConstraintCoefficients, CompositionCoefficients };
 mod proof_of_work;
 pub use proof_of_work::{ find_pow_nonce, verify_pow_nonce };
 
-pub fn get_composition_degree(trace_length: usize) -> usize {
-    return (MAX_CONSTRAINT_DEGREE - 1) * trace_length - 1;
-}
+mod public_coin;
+pub use public_coin::{ PublicCoin };
+
+mod serialization;
+pub use serialization::{ ByteWriter, ByteReader };
 
 // PUBLIC FUNCTIONS
 // ================================================================================================
 
-pub fn get_incremental_trace_degree(trace_length: usize) -> usize {
-    let composition_degree = get_composition_degree(trace_length);
-    return composition_degree - (trace_length - 2);
+/// Returns the number of coefficients in trace polynomials. In zero-knowledge mode, trace
+/// polynomials are randomized by adding a random multiple of the trace domain vanishing
+/// polynomial, and thus have twice as many coefficients as there are steps in the trace.
+pub fn get_trace_poly_length(trace_length: usize, zero_knowledge: bool) -> usize {
+    return if zero_knowledge { trace_length * 2 } else { trace_length };
+}
+
+pub fn get_composition_degree(trace_length: usize, zero_knowledge: bool) -> usize {
+    let poly_length = get_trace_poly_length(trace_length, zero_knowledge);
+    return MAX_CONSTRAINT_DEGREE * poly_length - trace_length - 1;
+}
+
+pub fn get_incremental_trace_degree(trace_length: usize, zero_knowledge: bool) -> usize {
+    let composition_degree = get_composition_degree(trace_length, zero_knowledge);
+    let poly_length = get_trace_poly_length(trace_length, zero_knowledge);
+    return composition_degree - (poly_length - 2);
 }
 
 pub fn compute_query_positions(seed: &[u8; 32], domain_size: usize, options: &ProofOptions) -> Vec<usize> {
@@ -1,57 +1,132 @@
-use std::slice;
-use crate::stark::{ ProofOptions };
+use std::sync::atomic::{ AtomicU64, Ordering };
+use crossbeam_utils::thread;
+use crate::crypto::HashFunction;
+use crate::stark::{ ProofOptions, VerificationError };
 
-pub fn find_pow_nonce(seed: [u8; 32], options: &ProofOptions) -> ([u8; 32], u64) {
+/// Finds the smallest nonce which, when hashed together with the `seed`, results in a value
+/// with at least `grinding_factor` trailing zeros, and returns this value together with the
+/// nonce. The search is distributed across up to `num_threads` threads; since the smallest
+/// nonce is always returned, the result does not depend on the number of threads.
+pub fn find_pow_nonce(seed: [u8; 32], options: &ProofOptions, num_threads: usize) -> ([u8; 32], u64) {
 
-    let hash = options.hash_fn();
+    let hash_fn = options.hash_fn();
     let grinding_factor = options.grinding_factor();
 
-    // copy seed into inputs and also get a reference to inputs as to an array of bytes
-    let mut inputs = [0u64; 8];
-    let input_bytes: &mut [u8] = unsafe {
-        slice::from_raw_parts_mut(inputs.as_ptr() as *mut u8, inputs.len() * 8)
-    };
-    input_bytes[0..32].copy_from_slice(&seed);
-
-    // create buffer to hold outputs and also get a reference to it as to any array of bytes
-    let output = [0u64; 4];
-    let mut output_bytes = unsafe {
-        slice::from_raw_parts_mut(output.as_ptr() as *mut u8, output.len() * 8)
-    };
-
-    loop {
-        inputs[4] += 1;
-        hash(&input_bytes, &mut output_bytes);
-        if output[0].trailing_zeros() >= grinding_factor { break; }
-    }
-
-    let mut result = [0; 32];
-    result.copy_from_slice(output_bytes);
+    // thread i checks nonces i + 1, i + 1 + num_threads, i + 1 + 2 * num_threads etc.; a thread
+    // stops as soon as its next nonce is greater than the smallest valid nonce found so far
+    let num_threads = std::cmp::max(num_threads, 1) as u64;
+    let best_nonce = AtomicU64::new(u64::MAX);
+    thread::scope(|s| {
+        let best_nonce = &best_nonce;
+        for i in 1..num_threads {
+            s.spawn(move |_| search_nonces(&seed, hash_fn, grinding_factor, i + 1, num_threads, best_nonce));
+        }
+        search_nonces(&seed, hash_fn, grinding_factor, 1, num_threads, best_nonce);
+    }).unwrap();
 
-    return (result, inputs[4]);
+    let nonce = best_nonce.load(Ordering::Relaxed);
+    let result = verify_pow_nonce(seed, nonce, options).expect("proof-of-work nonce is not valid");
+    return (result, nonce);
 }
 
-pub fn verify_pow_nonce(seed: [u8; 32], nonce: u64, options: &ProofOptions) -> Result<[u8; 32], String> {
+pub fn verify_pow_nonce(seed: [u8; 32], nonce: u64, options: &ProofOptions) -> Result<[u8; 32], VerificationError> {
 
-    let hash = options.hash_fn();
+    let hash_fn = options.hash_fn();
 
     // append nonce to seed for hashing
     let mut input_bytes = [0; 64];
     input_bytes[0..32].copy_from_slice(&seed);
     input_bytes[32..40].copy_from_slice(&nonce.to_le_bytes());
 
-    let output = [0u64; 4];
-    let mut output_bytes: &mut [u8] = unsafe {
-        slice::from_raw_parts_mut(output.as_ptr() as *mut u8, output.len() * 8)
-    };
+    let mut output = [0u8; 32];
+    hash_fn.hash(&input_bytes, &mut output);
+    if count_trailing_zeros(&output) < options.grinding_factor() {
+        return Err(VerificationError::ProofOfWorkNotVerified);
+    }
+
+    return Ok(output);
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Checks nonces `first`, `first + step`, `first + 2 * step` etc. until a valid nonce is found
+/// or until the nonces become greater than `best_nonce`; a valid nonce is recorded in
+/// `best_nonce` if it is smaller than the current value.
+fn search_nonces(
+    seed            : &[u8; 32],
+    hash_fn         : HashFunction,
+    grinding_factor : u32,
+    first           : u64,
+    step            : u64,
+    best_nonce      : &AtomicU64)
+{
+    // copy seed into inputs; nonce is appended to the seed right before hashing
+    let mut input_bytes = [0; 64];
+    input_bytes[0..32].copy_from_slice(seed);
 
-    hash(&input_bytes, &mut output_bytes);
-    if output[0].trailing_zeros() < options.grinding_factor() {
-        return Err(String::from("seed proof-of-work verification failed"));
+    let mut output = [0u8; 32];
+    let mut nonce = first;
+    while nonce < best_nonce.load(Ordering::Relaxed) {
+        input_bytes[32..40].copy_from_slice(&nonce.to_le_bytes());
+        hash_fn.hash(&input_bytes, &mut output);
+        if count_trailing_zeros(&output) >= grinding_factor {
+            best_nonce.fetch_min(nonce, Ordering::Relaxed);
+            return;
+        }
+        nonce = match nonce.checked_add(step) {
+            Some(nonce) => nonce,
+            None => return,
+        };
     }
+}
+
+/// Returns the number of trailing zeros in the first 8 bytes of the hash `output` interpreted
+/// as a little-endian integer.
+fn count_trailing_zeros(output: &[u8; 32]) -> u32 {
+    let mut word = [0u8; 8];
+    word.copy_from_slice(&output[..8]);
+    return u64::from_le_bytes(word).trailing_zeros();
+}
+
+// TESTS
+// ================================================================================================
+#[cfg(test)]
+mod tests {
+    use crate::crypto::HashFunction;
+    use crate::stark::{ ProofOptions, VerificationError };
+
+    #[test]
+    fn find_pow_nonce() {
+        let options = ProofOptions::new(32, 32, 8, HashFunction::Blake3);
+        let seed = [7u8; 32];
 
-    let mut result = [0; 32];
-    result.copy_from_slice(output_bytes);
+        // the smallest valid nonce is found regardless of the number of threads
+        let (expected_seed, expected_nonce) = super::find_pow_nonce(seed, &options, 1);
+        for nonce in 1..expected_nonce {
+            assert!(super::verify_pow_nonce(seed, nonce, &options).is_err());
+        }
+        assert_eq!(Ok(expected_seed), super::verify_pow_nonce(seed, expected_nonce, &options));
+        assert!(super::count_trailing_zeros(&expected_seed) >= options.grinding_factor());
 
-    return Ok(result);
-}
\ No newline at end of file
+        for &num_threads in [2, 3, 8].iter() {
+            let (seed, nonce) = super::find_pow_nonce(seed, &options, num_threads);
+            assert_eq!(expected_seed, seed);
+            assert_eq!(expected_nonce, nonce);
+        }
+    }
+
+    #[test]
+    fn verify_pow_nonce() {
+        // with grinding factor of 32, a nonce picked without grinding is rejected
+        let options = ProofOptions::new(32, 32, 32, HashFunction::Blake3);
+        let seed = [7u8; 32];
+        for nonce in 0..16 {
+            assert_eq!(Err(VerificationError::ProofOfWorkNotVerified), super::verify_pow_nonce(seed, nonce, &options));
+        }
+
+        // with grinding factor of 0, any nonce is accepted
+        let options = ProofOptions::new(32, 32, 0, HashFunction::Blake3);
+        assert!(super::verify_pow_nonce(seed, 0, &options).is_ok());
+    }
+}
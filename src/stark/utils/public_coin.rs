@@ -0,0 +1,140 @@
+use crate::crypto::{ HashFunction };
+use crate::stark::{ ProofOptions, TraceInfo };
+
+// TYPES AND INTERFACES
+// ================================================================================================
+
+/// Fiat-Shamir transcript shared by the prover and the verifier. The coin is seeded with the
+/// statement being proven (program hash, public inputs, outputs, claimed stack size, proof
+/// options and trace info), and is then re-seeded with every commitment made by the prover; all
+/// pseudo-random challenges are derived from the current seed of the coin, and are thus bound to
+/// the claim being proven.
+pub struct PublicCoin {
+    seed    : [u8; 32],
+    hash_fn : HashFunction,
+}
+
+// PUBLIC COIN IMPLEMENTATION
+// ================================================================================================
+impl PublicCoin {
+
+    /// Returns a new coin seeded with a hash of the statement being proven.
+    pub fn new(
+        program_hash    : &[u8; 32],
+        inputs          : &[u128],
+        outputs         : &[u128],
+        output_positions: &[usize],
+        stack_size      : Option<usize>,
+        trace_info      : &TraceInfo,
+        options         : &ProofOptions) -> PublicCoin
+    {
+        let mut statement = Vec::new();
+        statement.extend_from_slice(program_hash);
+
+        // lengths are absorbed together with values to make the encoding unambiguous
+        append_usize(&mut statement, inputs.len());
+        inputs.iter().for_each(|v| statement.extend_from_slice(&v.to_le_bytes()));
+        append_usize(&mut statement, outputs.len());
+        outputs.iter().for_each(|v| statement.extend_from_slice(&v.to_le_bytes()));
+        append_usize(&mut statement, output_positions.len());
+        output_positions.iter().for_each(|&p| append_usize(&mut statement, p));
+        statement.push(stack_size.is_some() as u8);
+        append_usize(&mut statement, stack_size.unwrap_or(0));
+
+        append_usize(&mut statement, options.extension_factor());
+        append_usize(&mut statement, options.num_queries());
+        statement.extend_from_slice(&options.grinding_factor().to_le_bytes());
+        statement.push(options.zero_knowledge() as u8);
+        append_usize(&mut statement, options.fixed_trace_length().unwrap_or(0));
+        let fixed_depths = options.fixed_trace_depths().unwrap_or([0, 0, 0]);
+        fixed_depths.iter().for_each(|&depth| append_usize(&mut statement, depth));
+
+        statement.push(trace_info.domain_depth);
+        statement.push(trace_info.ctx_depth);
+        statement.push(trace_info.loop_depth);
+        statement.push(trace_info.stack_depth);
+        statement.extend_from_slice(&trace_info.op_count.to_le_bytes());
+
+        let hash_fn = options.hash_fn();
+        let mut seed = [0u8; 32];
+        hash_fn.hash(&statement, &mut seed);
+
+        return PublicCoin { seed, hash_fn };
+    }
+
+    /// Returns the current seed of the coin.
+    pub fn seed(&self) -> [u8; 32] {
+        return self.seed;
+    }
+
+    /// Updates the seed of the coin to hash(seed || data); this should be called with every
+    /// commitment sent from the prover to the verifier.
+    pub fn reseed(&mut self, data: &[u8]) {
+        let mut input = Vec::with_capacity(32 + data.len());
+        input.extend_from_slice(&self.seed);
+        input.extend_from_slice(data);
+        self.hash_fn.hash(&input, &mut self.seed);
+    }
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+fn append_usize(target: &mut Vec<u8>, value: usize) {
+    target.extend_from_slice(&(value as u64).to_le_bytes());
+}
+
+// TESTS
+// ================================================================================================
+#[cfg(test)]
+mod tests {
+
+    use crate::stark::{ ProofOptions, TraceInfo };
+    use super::PublicCoin;
+
+    #[test]
+    fn bind_statement() {
+        let options = ProofOptions::default();
+        let info = TraceInfo::new(512, 1, 1, 8, 30);
+        let hash = [1u8; 32];
+
+        let coin1 = PublicCoin::new(&hash, &[1, 2], &[3], &[0], None, &info, &options);
+        let coin2 = PublicCoin::new(&hash, &[1, 2], &[3], &[0], None, &info, &options);
+        assert_eq!(coin1.seed(), coin2.seed());
+
+        // changing any part of the statement changes the seed
+        let coin2 = PublicCoin::new(&[2u8; 32], &[1, 2], &[3], &[0], None, &info, &options);
+        assert_ne!(coin1.seed(), coin2.seed());
+        let coin2 = PublicCoin::new(&hash, &[1, 3], &[3], &[0], None, &info, &options);
+        assert_ne!(coin1.seed(), coin2.seed());
+        let coin2 = PublicCoin::new(&hash, &[1, 2], &[4], &[0], None, &info, &options);
+        assert_ne!(coin1.seed(), coin2.seed());
+        let coin2 = PublicCoin::new(&hash, &[1, 2], &[3], &[1], None, &info, &options);
+        assert_ne!(coin1.seed(), coin2.seed());
+        let coin2 = PublicCoin::new(&hash, &[1, 2], &[3], &[0], None, &TraceInfo::new(512, 1, 1, 8, 31), &options);
+        assert_ne!(coin1.seed(), coin2.seed());
+        let coin2 = PublicCoin::new(&hash, &[1, 2], &[3], &[0], Some(1), &info, &options);
+        assert_ne!(coin1.seed(), coin2.seed());
+
+        // moving a value from inputs to outputs changes the seed
+        let coin2 = PublicCoin::new(&hash, &[1], &[2, 3], &[0], None, &info, &options);
+        assert_ne!(coin1.seed(), coin2.seed());
+    }
+
+    #[test]
+    fn reseed() {
+        let options = ProofOptions::default();
+        let info = TraceInfo::new(512, 1, 1, 8, 30);
+
+        let mut coin1 = PublicCoin::new(&[1u8; 32], &[], &[], &[], None, &info, &options);
+        let mut coin2 = PublicCoin::new(&[1u8; 32], &[], &[], &[], None, &info, &options);
+        let seed = coin1.seed();
+
+        coin1.reseed(&[5u8; 32]);
+        coin2.reseed(&[5u8; 32]);
+        assert_ne!(seed, coin1.seed());
+        assert_eq!(coin1.seed(), coin2.seed());
+
+        coin2.reseed(&[6u8; 32]);
+        assert_ne!(coin1.seed(), coin2.seed());
+    }
+}
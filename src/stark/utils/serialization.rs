@@ -0,0 +1,325 @@
+use std::collections::HashMap;
+use crate::math::field;
+use crate::stark::{ DeserializationError };
+
+// TYPES AND INTERFACES
+// ================================================================================================
+
+/// Writes values into a compact binary encoding. Integers are written as LEB128 varints, field
+/// elements as 16 little-endian bytes, and digests in lists are de-duplicated: a digest which was
+/// already written in any list is replaced with a reference to its first occurrence.
+pub struct ByteWriter {
+    bytes   : Vec<u8>,
+    digests : HashMap<[u8; 32], usize>,
+}
+
+/// Reads values written by a `ByteWriter`; every read fails with a `DeserializationError`
+/// rather than panicking when the input is malformed.
+pub struct ByteReader<'a> {
+    bytes   : &'a [u8],
+    pos     : usize,
+    digests : Vec<[u8; 32]>,
+}
+
+// BYTE WRITER IMPLEMENTATION
+// ================================================================================================
+impl ByteWriter {
+
+    pub fn new() -> ByteWriter {
+        return ByteWriter { bytes: Vec::new(), digests: HashMap::new() };
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        return self.bytes;
+    }
+
+    /// Returns the number of bytes written so far.
+    pub fn len(&self) -> usize {
+        return self.bytes.len();
+    }
+
+    pub fn write_u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    pub fn write_bool(&mut self, value: bool) {
+        self.bytes.push(value as u8);
+    }
+
+    pub fn write_varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.bytes.push((value as u8) | 0x80);
+            value = value >> 7;
+        }
+        self.bytes.push(value as u8);
+    }
+
+    pub fn write_usize(&mut self, value: usize) {
+        self.write_varint(value as u64);
+    }
+
+    /// Writes a digest as is; this should be used for values which are not repeated elsewhere
+    /// in the proof (e.g. Merkle roots).
+    pub fn write_digest(&mut self, value: &[u8; 32]) {
+        self.bytes.extend_from_slice(value);
+    }
+
+    /// Writes field elements without a length prefix.
+    pub fn write_elements(&mut self, values: &[u128]) {
+        for value in values.iter() {
+            self.bytes.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+
+    /// Writes a list of digests without a length prefix. The list is preceded by a bitmap which
+    /// marks digests replaced with references to earlier occurrences.
+    pub fn write_digests(&mut self, values: &[[u8; 32]]) {
+        let mut bitmap = vec![0u8; (values.len() + 7) / 8];
+        let mut references = Vec::with_capacity(values.len());
+        for (i, value) in values.iter().enumerate() {
+            match self.digests.get(value) {
+                Some(&index) => {
+                    bitmap[i / 8] |= 1 << (i % 8);
+                    references.push(Some(index));
+                },
+                None => {
+                    self.digests.insert(*value, self.digests.len());
+                    references.push(None);
+                }
+            }
+        }
+
+        self.bytes.extend_from_slice(&bitmap);
+        for (value, reference) in values.iter().zip(references) {
+            match reference {
+                Some(index) => self.write_usize(index),
+                None => self.bytes.extend_from_slice(value),
+            }
+        }
+    }
+
+    /// Writes lists of Merkle authentication nodes; lengths of the lists are written up front,
+    /// followed by all nodes written as a single digest list.
+    pub fn write_node_lists(&mut self, nodes: &[Vec<[u8; 32]>]) {
+        self.write_usize(nodes.len());
+        nodes.iter().for_each(|list| self.write_usize(list.len()));
+        self.write_digests(&nodes.concat());
+    }
+}
+
+// BYTE READER IMPLEMENTATION
+// ================================================================================================
+impl <'a> ByteReader<'a> {
+
+    pub fn new(bytes: &'a [u8]) -> ByteReader<'a> {
+        return ByteReader { bytes, pos: 0, digests: Vec::new() };
+    }
+
+    /// Makes sure all bytes of the input have been read.
+    pub fn finish(&self) -> Result<(), DeserializationError> {
+        if self.pos < self.bytes.len() {
+            return Err(DeserializationError::TrailingBytes { count: self.bytes.len() - self.pos });
+        }
+        return Ok(());
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, DeserializationError> {
+        let value = *self.bytes.get(self.pos).ok_or(DeserializationError::UnexpectedEnd)?;
+        self.pos += 1;
+        return Ok(value);
+    }
+
+    pub fn read_bool(&mut self) -> Result<bool, DeserializationError> {
+        return match self.read_u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            value => Err(DeserializationError::InvalidValue(format!("boolean value {}", value))),
+        };
+    }
+
+    pub fn read_varint(&mut self) -> Result<u64, DeserializationError> {
+        let mut result = 0u64;
+        for i in 0..10 {
+            let byte = self.read_u8()?;
+            let value = (byte & 0x7F) as u64;
+            // the 10th byte can hold only the highest bit of a 64-bit value; zero continuation
+            // bytes are rejected so that every value has a single encoding
+            if (i == 9 && value > 1) || (i > 0 && byte == 0) {
+                return Err(DeserializationError::InvalidValue(String::from("varint encoding")));
+            }
+            result |= value << (7 * i);
+            if byte & 0x80 == 0 {
+                return Ok(result);
+            }
+        }
+        return Err(DeserializationError::InvalidValue(String::from("varint encoding")));
+    }
+
+    pub fn read_usize(&mut self) -> Result<usize, DeserializationError> {
+        let value = self.read_varint()?;
+        if value > usize::MAX as u64 {
+            return Err(DeserializationError::InvalidValue(String::from("length")));
+        }
+        return Ok(value as usize);
+    }
+
+    /// Reads a length of a list and makes sure that the rest of the input can hold this many
+    /// items of at least `min_item_size` bytes each; this prevents huge allocations for
+    /// malformed inputs.
+    pub fn read_length(&mut self, min_item_size: usize) -> Result<usize, DeserializationError> {
+        let length = self.read_usize()?;
+        self.check_remaining(length, min_item_size)?;
+        return Ok(length);
+    }
+
+    pub fn read_digest(&mut self) -> Result<[u8; 32], DeserializationError> {
+        let bytes = self.read_slice(32)?;
+        let mut result = [0u8; 32];
+        result.copy_from_slice(bytes);
+        return Ok(result);
+    }
+
+    /// Reads `count` field elements; values which are not valid field elements are rejected.
+    pub fn read_elements(&mut self, count: usize) -> Result<Vec<u128>, DeserializationError> {
+        self.check_remaining(count, 16)?;
+        let mut result = Vec::with_capacity(count);
+        for _ in 0..count {
+            let mut bytes = [0u8; 16];
+            bytes.copy_from_slice(self.read_slice(16)?);
+            let value = u128::from_le_bytes(bytes);
+            if value >= field::MODULUS {
+                return Err(DeserializationError::InvalidValue(format!("field element {}", value)));
+            }
+            result.push(value);
+        }
+        return Ok(result);
+    }
+
+    /// Reads a list of `count` digests written by `ByteWriter::write_digests()`.
+    pub fn read_digests(&mut self, count: usize) -> Result<Vec<[u8; 32]>, DeserializationError> {
+        let bitmap = self.read_slice((count + 7) / 8)?.to_vec();
+        if count % 8 != 0 && bitmap[count / 8] >> (count % 8) != 0 {
+            return Err(DeserializationError::InvalidValue(String::from("digest reference bitmap")));
+        }
+
+        self.check_remaining(count, 1)?;
+        let mut result = Vec::with_capacity(count);
+        for i in 0..count {
+            if bitmap[i / 8] & (1 << (i % 8)) != 0 {
+                let index = self.read_usize()?;
+                match self.digests.get(index) {
+                    Some(&digest) => result.push(digest),
+                    None => return Err(DeserializationError::InvalidValue(
+                        format!("digest reference {}", index))),
+                }
+            }
+            else {
+                let digest = self.read_digest()?;
+                self.digests.push(digest);
+                result.push(digest);
+            }
+        }
+        return Ok(result);
+    }
+
+    /// Reads lists of Merkle authentication nodes written by `ByteWriter::write_node_lists()`.
+    pub fn read_node_lists(&mut self) -> Result<Vec<Vec<[u8; 32]>>, DeserializationError> {
+        let num_lists = self.read_length(1)?;
+        let mut lengths = Vec::with_capacity(num_lists);
+        let mut total_length = 0usize;
+        for _ in 0..num_lists {
+            let length = self.read_length(1)?;
+            total_length = total_length.checked_add(length)
+                .ok_or(DeserializationError::InvalidValue(String::from("length")))?;
+            lengths.push(length);
+        }
+        self.check_remaining(total_length, 1)?;
+
+        let mut digests = self.read_digests(total_length)?.into_iter();
+        return Ok(lengths.into_iter().map(|length| digests.by_ref().take(length).collect()).collect());
+    }
+
+    // HELPER METHODS
+    // --------------------------------------------------------------------------------------------
+    fn read_slice(&mut self, length: usize) -> Result<&'a [u8], DeserializationError> {
+        self.check_remaining(length, 1)?;
+        let result = &self.bytes[self.pos..(self.pos + length)];
+        self.pos += length;
+        return Ok(result);
+    }
+
+    fn check_remaining(&self, count: usize, item_size: usize) -> Result<(), DeserializationError> {
+        match count.checked_mul(item_size) {
+            Some(size) if size <= self.bytes.len() - self.pos => Ok(()),
+            _ => Err(DeserializationError::UnexpectedEnd),
+        }
+    }
+}
+
+// TESTS
+// ================================================================================================
+#[cfg(test)]
+mod tests {
+
+    use crate::stark::{ DeserializationError };
+    use super::{ ByteWriter, ByteReader };
+
+    #[test]
+    fn varints() {
+        let values = [0, 1, 127, 128, 300, u32::MAX as u64, u64::MAX];
+        let mut writer = ByteWriter::new();
+        values.iter().for_each(|&v| writer.write_varint(v));
+        let bytes = writer.into_bytes();
+        assert_eq!(1 + 1 + 1 + 2 + 2 + 5 + 10, bytes.len());
+
+        let mut reader = ByteReader::new(&bytes);
+        for &value in values.iter() {
+            assert_eq!(Ok(value), reader.read_varint());
+        }
+        assert_eq!(Ok(()), reader.finish());
+
+        // overlong and truncated encodings are rejected
+        let error = DeserializationError::InvalidValue(String::from("varint encoding"));
+        assert_eq!(Err(error), ByteReader::new(&[0x80, 0x00]).read_varint());
+        assert_eq!(Err(DeserializationError::UnexpectedEnd), ByteReader::new(&[0x80]).read_varint());
+    }
+
+    #[test]
+    fn digests() {
+        let lists = vec![vec![[1u8; 32], [2u8; 32]], vec![], vec![[1u8; 32], [3u8; 32], [2u8; 32]]];
+        let mut writer = ByteWriter::new();
+        writer.write_node_lists(&lists);
+        let bytes = writer.into_bytes();
+
+        // 4 length bytes, 1 bitmap byte, 3 digests and 2 single-byte references
+        assert_eq!(4 + 1 + 3 * 32 + 2, bytes.len());
+
+        let mut reader = ByteReader::new(&bytes);
+        assert_eq!(Ok(lists), reader.read_node_lists());
+        assert_eq!(Ok(()), reader.finish());
+
+        // references to digests which were not yet read are rejected
+        let mut writer = ByteWriter::new();
+        writer.write_digests(&[[1u8; 32], [1u8; 32]]);
+        let mut bytes = writer.into_bytes();
+        bytes[33] = 1;
+        let error = DeserializationError::InvalidValue(String::from("digest reference 1"));
+        assert_eq!(Err(error), ByteReader::new(&bytes).read_digests(2));
+    }
+
+    #[test]
+    fn elements() {
+        let mut writer = ByteWriter::new();
+        writer.write_elements(&[1, crate::math::field::MODULUS - 1]);
+        let bytes = writer.into_bytes();
+        assert_eq!(32, bytes.len());
+
+        let mut reader = ByteReader::new(&bytes);
+        assert_eq!(Ok(vec![1, crate::math::field::MODULUS - 1]), reader.read_elements(2));
+
+        // values outside of the field are rejected
+        let bytes = crate::math::field::MODULUS.to_le_bytes();
+        let error = ByteReader::new(&bytes).read_elements(1).err().unwrap();
+        assert!(matches!(error, DeserializationError::InvalidValue(_)));
+    }
+}
@@ -1,19 +1,181 @@
+use std::collections::HashMap;
+use std::panic::{ self, AssertUnwindSafe };
+use std::sync::atomic::{ AtomicUsize, Ordering };
+use crossbeam_utils::thread;
 use crate::{
     math::field,
     crypto::{ MerkleTree },
-    MIN_TRACE_LENGTH
+    utils::find_duplicate,
+    MIN_TRACE_LENGTH, MIN_STACK_DEPTH, MAX_PUBLIC_INPUTS, MAX_OUTPUTS,
 };
-use super::{ StarkProof, TraceState, ConstraintEvaluator, CompositionCoefficients, fri, utils };
+use super::{
+    StarkProof, TraceState, ConstraintEvaluator, CompositionCoefficients, VerificationError, fri, utils,
+    constraints::{ EvaluatorTemplate, TraceShape },
+};
+
+// TYPES AND INTERFACES
+// ================================================================================================
+
+/// Program hash, public inputs, outputs, output positions, claimed stack size, and a proof to be
+/// verified as a part of a batch.
+pub type BatchItem<'a> = (&'a [u8; 32], &'a [u128], &'a [u128], Vec<usize>, Option<usize>, &'a StarkProof);
+
+/// Values which depend only on the shape of the execution trace, and can be shared between all
+/// proofs of a batch: constraint evaluator templates for every distinct trace shape, and domain
+/// data for every distinct pair of trace length and extension factor.
+#[derive(Default)]
+struct BatchTemplates {
+    evaluators  : HashMap<TraceShape, EvaluatorTemplate>,
+    domains     : HashMap<(usize, usize), DomainTemplate>,
+}
+
+/// Roots of unity of the trace and LDE domains, and x coordinates of the FRI remainder for
+/// proofs with a given trace length and extension factor.
+#[derive(Clone)]
+struct DomainTemplate {
+    lde_root        : u128,
+    trace_root      : u128,
+    remainder_domain: Vec<u128>,
+}
 
-// VERIFIER FUNCTION
+// VERIFIER FUNCTIONS
 // ================================================================================================
 
-pub fn verify(program_hash: &[u8; 32], inputs: &[u128], outputs: &[u128], proof: &StarkProof) -> Result<bool, String>
+pub fn verify(
+    program_hash    : &[u8; 32],
+    inputs          : &[u128],
+    outputs         : &[u128],
+    output_positions: &[usize],
+    stack_size      : Option<usize>,
+    proof           : &StarkProof) -> Result<bool, VerificationError>
+{
+    return verify_with_templates(program_hash, inputs, outputs, output_positions, stack_size, proof, &BatchTemplates::default());
+}
+
+/// Verifies all proofs in the batch using up to `num_threads` threads, and returns the result
+/// of verification for each proof. Constraint evaluator templates are built once for every
+/// distinct shape of execution trace in the batch (constants extended over the evaluation
+/// domain are computed once for every distinct extension factor), and domain data is computed
+/// once for every distinct domain. A proof which causes the verifier to panic is rejected with
+/// `VerificationError::MalformedProof` without affecting verification of other proofs.
+pub fn verify_batch(items: &[BatchItem], num_threads: usize) -> Vec<Result<bool, VerificationError>> {
+    // build templates only for well-formed proofs; malformed proofs are rejected by the verifier
+    // before a template is needed
+    let mut templates = BatchTemplates::default();
+    let mut base_templates: HashMap<usize, EvaluatorTemplate> = HashMap::new();
+    for (_, _, _, _, _, proof) in items.iter() {
+        if proof.validate().is_ok() {
+            let shape = EvaluatorTemplate::get_shape(proof);
+            if !templates.evaluators.contains_key(&shape) {
+                let extension_factor = proof.options().extension_factor();
+                let template = match base_templates.get(&extension_factor) {
+                    Some(base) => base.with_shape(shape),
+                    None => EvaluatorTemplate::new(shape),
+                };
+                base_templates.entry(extension_factor).or_insert_with(|| template.clone());
+                templates.evaluators.insert(shape, template);
+            }
+
+            let domain_key = (proof.trace_length(), proof.options().extension_factor());
+            templates.domains.entry(domain_key).or_insert_with(|| DomainTemplate::new(domain_key.0, domain_key.1));
+        }
+    }
+
+    // threads pick the next unverified proof until all proofs have been verified
+    let next_item = AtomicUsize::new(0);
+    let num_threads = std::cmp::max(1, std::cmp::min(num_threads, items.len()));
+    let mut results = Vec::with_capacity(items.len());
+    results.resize(items.len(), Ok(false));
+    thread::scope(|s| {
+        let handles = (0..num_threads).map(|_| s.spawn(|_| {
+            let mut thread_results = Vec::new();
+            loop {
+                let i = next_item.fetch_add(1, Ordering::Relaxed);
+                if i >= items.len() { break; }
+                let (program_hash, inputs, outputs, output_positions, stack_size, proof) = &items[i];
+                let result = catch_panic(|| verify_with_templates(
+                    program_hash, inputs, outputs, output_positions, *stack_size, proof, &templates));
+                thread_results.push((i, result));
+            }
+            thread_results
+        })).collect::<Vec<_>>();
+
+        for handle in handles {
+            for (i, result) in handle.join().unwrap() {
+                results[i] = result;
+            }
+        }
+    }).unwrap();
+
+    return results;
+}
+
+/// Verifies the proof in the same way as `verify()`, but uses a constraint evaluator template
+/// and domain data from `templates` if there are ones for the shape of the proof's execution
+/// trace.
+fn verify_with_templates(
+    program_hash    : &[u8; 32],
+    inputs          : &[u128],
+    outputs         : &[u128],
+    output_positions: &[usize],
+    stack_size      : Option<usize>,
+    proof           : &StarkProof,
+    templates       : &BatchTemplates) -> Result<bool, VerificationError>
 {
     let options = proof.options();
     let hash_fn = options.hash_fn();
 
-    // 1 ----- Verify proof of work and determine query positions ---------------------------------
+    // 1 ----- Verify number of operations in the program -----------------------------------------
+    // make sure the proof is well-formed before reading any of its components
+    proof.validate()?;
+
+    // when op count is hidden, the proof shows only that fewer than trace length operations
+    // were executed
+    if !options.hides_op_count() && proof.op_count() < MIN_TRACE_LENGTH as u128 {
+        return Err(VerificationError::InsufficientOpCount {
+            op_count: proof.op_count(), min: MIN_TRACE_LENGTH });
+    }
+
+    // make sure inputs and outputs fit into the user stack of the execution trace
+    let stack_depth = std::cmp::max(proof.stack_depth(), MIN_STACK_DEPTH);
+    let output_span = output_positions.iter().map(|&p| p + 1).max().unwrap_or(0);
+    if inputs.len() > std::cmp::min(stack_depth, MAX_PUBLIC_INPUTS)
+        || output_span > std::cmp::min(stack_depth, MAX_OUTPUTS)
+        || outputs.len() > MAX_OUTPUTS
+    {
+        return Err(VerificationError::StackDepthMismatch {
+            inputs: inputs.len(), outputs: output_span, stack_depth });
+    }
+    if outputs.len() != output_positions.len() {
+        return Err(VerificationError::OutputCountMismatch {
+            expected: output_positions.len(), actual: outputs.len() });
+    }
+
+    // boundary constraints for outputs bound to the same position would be summed together, and
+    // so could be satisfied by outputs which differ from the stack item at that position
+    if let Some(position) = find_duplicate(output_positions) {
+        return Err(VerificationError::DuplicateOutputPosition { position });
+    }
+
+    // 2 ----- Verify proof of work and determine query positions ---------------------------------
+    // seed the public coin with the statement being verified, and absorb trace and constraint
+    // commitments in the same order as the prover did
+    let mut coin = utils::PublicCoin::new(
+        program_hash, inputs, outputs, output_positions, stack_size, proof.trace_info(), options);
+    if coin.seed() != *proof.statement_digest() {
+        return Err(VerificationError::StatementMismatch);
+    }
+    coin.reseed(proof.trace_root());
+    let constraint_seed = coin.seed();
+    coin.reseed(proof.constraint_root());
+    if options.zero_knowledge() {
+        match proof.mask_root() {
+            Some(mask_root) => coin.reseed(mask_root),
+            None => return Err(VerificationError::CompositionMaskMissing),
+        }
+    }
+    let deep_seed = coin.seed();
+
     let degree_proof = proof.degree_proof();
     let mut fri_roots: Vec<u8> = Vec::new();
     for layer in degree_proof.layers.iter() {
@@ -21,56 +183,89 @@ pub fn verify(program_hash: &[u8; 32], inputs: &[u128], outputs: &[u128], proof:
     }
     degree_proof.rem_root.iter().for_each(|&v| fri_roots.push(v));
 
-    let mut seed = [0u8; 32];
-    hash_fn(&fri_roots, &mut seed);
-    let seed = match utils::verify_pow_nonce(seed, proof.pow_nonce(), &options) {
-        Ok(seed) => seed,
-        Err(msg) => return Err(msg)
-    };
+    coin.reseed(&fri_roots);
+    let seed = utils::verify_pow_nonce(coin.seed(), proof.pow_nonce(), &options)?;
 
     let t_positions = utils::compute_query_positions(&seed, proof.domain_size(), options);
     let c_positions = utils::map_trace_to_constraint_positions(&t_positions);
-
-    // 2 ----- Verify number of operations in the program -----------------------------------------
-    if proof.op_count() < MIN_TRACE_LENGTH as u128 {
-        return Err(String::from("Verification of minimum operation count failed"));
+    if proof.trace_evaluations().len() != t_positions.len() {
+        return Err(VerificationError::MalformedProof(String::from("number of trace evaluations")));
     }
 
     // 3 ----- Verify trace and constraint Merkle proofs ------------------------------------------
     if !MerkleTree::verify_batch(proof.trace_root(), &t_positions, &proof.trace_proof(), hash_fn) {
-        return Err(String::from("verification of trace Merkle proof failed"));
+        return Err(VerificationError::TraceCommitmentMismatch);
     }
 
     if !MerkleTree::verify_batch(proof.constraint_root(), &c_positions, &proof.constraint_proof(), hash_fn) {
-        return Err(String::from("verification of constraint Merkle proof failed"));
+        return Err(VerificationError::ConstraintCommitmentMismatch);
     }
 
+    // in zero-knowledge mode, mask root has already been absorbed into the public coin
+    let mask_evaluations = if options.zero_knowledge() {
+        let mask_proof = proof.mask_proof().ok_or(VerificationError::CompositionMaskMissing)?;
+        if !MerkleTree::verify_batch(proof.mask_root().unwrap(), &c_positions, &mask_proof, hash_fn) {
+            return Err(VerificationError::CompositionMaskCommitmentMismatch);
+        }
+        Some(get_leaf_evaluations(&mask_proof.values, &t_positions, &c_positions))
+    }
+    else {
+        None
+    };
+
     // 4 ----- Compute constraint evaluations at DEEP point z -------------------------------------
-    // derive DEEP point z from the root of the constraint tree
-    let z = field::prng(*proof.constraint_root());
+    // derive DEEP point z from the public coin seeded with the root of the constraint tree
+    let z = field::prng(deep_seed);
 
     // evaluate constraints at z
+    let shape = EvaluatorTemplate::get_shape(proof);
+    let template = match templates.evaluators.get(&shape) {
+        Some(template) => std::borrow::Cow::Borrowed(template),
+        None => std::borrow::Cow::Owned(EvaluatorTemplate::new(shape)),
+    };
+    let domain = match templates.domains.get(&(proof.trace_length(), options.extension_factor())) {
+        Some(domain) => std::borrow::Cow::Borrowed(domain),
+        None => std::borrow::Cow::Owned(DomainTemplate::new(proof.trace_length(), options.extension_factor())),
+    };
     let constraint_evaluation_at_z = evaluate_constraints(
-        ConstraintEvaluator::from_proof(proof, program_hash, inputs, outputs),
+        ConstraintEvaluator::from_proof(proof, &template, &constraint_seed, program_hash, inputs, outputs, output_positions, stack_size),
         proof.get_state_at_z1(),
         proof.get_state_at_z2(),
         z
     );
 
     // 5 ----- Compute composition polynomial evaluations -----------------------------------------
-    // derive coefficient for linear combination from the root of constraint tree
-    let coefficients = CompositionCoefficients::new(*proof.constraint_root());
+    // derive coefficient for linear combination from the same seed as DEEP point z
+    let coefficients = CompositionCoefficients::new(deep_seed);
 
     // compute composition values separately for trace and constraints, and then add them together
-    let t_composition = compose_registers(&proof, &t_positions, z, &coefficients);
-    let c_composition = compose_constraints(&proof, &t_positions, &c_positions, z, constraint_evaluation_at_z, &coefficients);
-    let evaluations = t_composition.iter().zip(c_composition).map(|(&t, c)| field::add(t, c)).collect::<Vec<u128>>();
-    
+    let t_composition = compose_registers(&proof, &domain, &t_positions, z, &coefficients);
+    let c_composition = compose_constraints(&proof, &domain, &t_positions, &c_positions, z, constraint_evaluation_at_z, &coefficients);
+    let mut evaluations = t_composition.iter().zip(c_composition).map(|(&t, c)| field::add(t, c)).collect::<Vec<u128>>();
+
+    // in zero-knowledge mode, add evaluations of the mask polynomial
+    if let Some(mask_evaluations) = mask_evaluations {
+        for (evaluation, mask) in evaluations.iter_mut().zip(mask_evaluations) {
+            *evaluation = field::add(*evaluation, mask);
+        }
+    }
+
     // 6 ----- Verify low-degree proof -------------------------------------------------------------
-    let max_degree = utils::get_composition_degree(proof.trace_length());
-    return match fri::verify(&degree_proof, &evaluations, &t_positions, max_degree, options) {
-        Ok(result) => Ok(result),
-        Err(msg) => Err(format!("verification of low-degree proof failed: {}", msg))
+    let max_degree = utils::get_composition_degree(proof.trace_length(), options.zero_knowledge());
+    return fri::verify(&degree_proof, &evaluations, &t_positions, max_degree, &domain.remainder_domain, options);
+}
+
+// DOMAIN TEMPLATE IMPLEMENTATION
+// ================================================================================================
+impl DomainTemplate {
+
+    pub fn new(trace_length: usize, extension_factor: usize) -> DomainTemplate {
+        let domain_size = trace_length * extension_factor;
+        return DomainTemplate {
+            lde_root        : field::get_root_of_unity(domain_size),
+            trace_root      : field::get_root_of_unity(trace_length),
+            remainder_domain: fri::get_remainder_domain(domain_size),
+        };
     }
 }
 
@@ -95,17 +290,33 @@ fn evaluate_constraints(evaluator: ConstraintEvaluator, state1: TraceState, stat
     return result;
 }
 
-fn compose_registers(proof: &StarkProof, positions: &[usize], z: u128, cc: &CompositionCoefficients) -> Vec<u128>
+/// Runs the `verify` function and converts a panic raised by it into a verification error, so
+/// that a single proof cannot abort verification of an entire batch.
+fn catch_panic<F>(verify: F) -> Result<bool, VerificationError>
+    where F: FnOnce() -> Result<bool, VerificationError>
+{
+    return match panic::catch_unwind(AssertUnwindSafe(verify)) {
+        Ok(result) => result,
+        Err(payload) => {
+            let message = payload.downcast_ref::<&str>().map(|s| s.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| String::from("unknown error"));
+            Err(VerificationError::MalformedProof(format!("verifier panicked: {}", message)))
+        },
+    };
+}
+
+fn compose_registers(proof: &StarkProof, domain: &DomainTemplate, positions: &[usize], z: u128, cc: &CompositionCoefficients) -> Vec<u128>
 {    
-    let lde_root = field::get_root_of_unity(proof.domain_size());
-    let trace_root = field::get_root_of_unity(proof.trace_length());
-    let next_z = field::mul(z, trace_root);
+    let lde_root = domain.lde_root;
+    let next_z = field::mul(z, domain.trace_root);
 
     let trace_at_z1 = proof.get_state_at_z1().to_vec();
     let trace_at_z2 = proof.get_state_at_z2().to_vec();
     let evaluations = proof.trace_evaluations();
 
-    let incremental_degree = utils::get_incremental_trace_degree(proof.trace_length()) as u128;
+    let incremental_degree = utils::get_incremental_trace_degree(
+        proof.trace_length(), proof.options().zero_knowledge()) as u128;
 
     let mut result = Vec::with_capacity(evaluations.len());
     for (registers, &position) in evaluations.into_iter().zip(positions) {
@@ -135,18 +346,11 @@ fn compose_registers(proof: &StarkProof, positions: &[usize], z: u128, cc: &Comp
     return result;
 }
 
-fn compose_constraints(proof: &StarkProof, t_positions: &[usize], c_positions: &[usize], z: u128, evaluation_at_z: u128, cc: &CompositionCoefficients) -> Vec<u128> {
+fn compose_constraints(proof: &StarkProof, domain: &DomainTemplate, t_positions: &[usize], c_positions: &[usize], z: u128, evaluation_at_z: u128, cc: &CompositionCoefficients) -> Vec<u128> {
     // build constraint evaluation values from the leaves of constraint Merkle proof
-    let mut evaluations: Vec<u128> = Vec::with_capacity(t_positions.len());
-    let leaves = proof.constraint_proof().values;
-    for &position in t_positions.iter() {
-        let leaf_idx = c_positions.iter().position(|&v| v == position / 2).unwrap();
-        let element_start = (position % 2) * 16;
-        let element_bytes = &leaves[leaf_idx][element_start..(element_start + 16)];
-        evaluations.push(field::from_bytes(element_bytes));
-    }
+    let evaluations = get_leaf_evaluations(&proof.constraint_proof().values, t_positions, c_positions);
 
-    let lde_root = field::get_root_of_unity(proof.domain_size());
+    let lde_root = domain.lde_root;
 
     // divide out deep point from the evaluations
     let mut result = Vec::with_capacity(evaluations.len());
@@ -160,4 +364,36 @@ fn compose_constraints(proof: &StarkProof, t_positions: &[usize], c_positions: &
     }
 
     return result;
-}
\ No newline at end of file
+}
+/// Extracts evaluations at `t_positions` from Merkle tree leaves at `c_positions`; each leaf
+/// contains 2 consecutive evaluations.
+fn get_leaf_evaluations(leaves: &[[u8; 32]], t_positions: &[usize], c_positions: &[usize]) -> Vec<u128> {
+    let mut evaluations: Vec<u128> = Vec::with_capacity(t_positions.len());
+    for &position in t_positions.iter() {
+        let leaf_idx = c_positions.iter().position(|&v| v == position / 2).unwrap();
+        let element_start = (position % 2) * 16;
+        let element_bytes = &leaves[leaf_idx][element_start..(element_start + 16)];
+        evaluations.push(field::from_bytes(element_bytes));
+    }
+    return evaluations;
+}
+
+// TESTS
+// ================================================================================================
+#[cfg(test)]
+mod tests {
+
+    use super::{ VerificationError, catch_panic };
+
+    #[test]
+    fn catch_panic_in_verifier() {
+        assert_eq!(Ok(true), catch_panic(|| Ok(true)));
+        assert_eq!(Err(VerificationError::StatementMismatch), catch_panic(|| Err(VerificationError::StatementMismatch)));
+
+        let error = VerificationError::MalformedProof(String::from("verifier panicked: index out of bounds"));
+        assert_eq!(Err(error), catch_panic(|| panic!("index out of bounds")));
+
+        let error = VerificationError::MalformedProof(String::from("verifier panicked: position 7 is invalid"));
+        assert_eq!(Err(error), catch_panic(|| panic!("position {} is invalid", 7)));
+    }
+}
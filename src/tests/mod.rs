@@ -1,21 +1,17 @@
 use std::collections::HashMap;
 use crate::{
-    ProofOptions, Program, ProgramInputs, OpCode, OpHint,
-    blocks::{ ProgramBlock, Span, Group },
-    math::field, utils::hasher
+    ProofOptions, ProverConfig, StarkProof, VerifierPolicy, Program, ProgramInputs, OutputSpec, OpCode, OpHint, ExecutionTrace, ExecutionError, VerificationError, PolicyError,
+    assembly, blocks::{ ProgramBlock, Span, Group },
+    math::field, utils::hasher, crypto::HashFunction, MAX_CONTEXT_DEPTH, MAX_LOOP_DEPTH, MAX_STACK_DEPTH
 };
 
 mod branches;
 mod comparisons;
+mod serialization;
 
 #[test]
 fn execute_verify() {
-    let program = build_program(vec![
-        OpCode::Begin, OpCode::Swap, OpCode::Dup2, OpCode::Drop,
-        OpCode::Add,   OpCode::Swap, OpCode::Dup2, OpCode::Drop,
-        OpCode::Add,   OpCode::Swap, OpCode::Dup2, OpCode::Drop,
-        OpCode::Add,   OpCode::Noop, OpCode::Noop,
-    ], &[]);
+    let program = build_fibonacci_program();
 
     let options = ProofOptions::default();
     let inputs = ProgramInputs::from_public(&[1, 0]);
@@ -30,12 +26,7 @@ fn execute_verify() {
 
 #[test]
 fn execute_verify_fail() {
-    let program = build_program(vec![
-        OpCode::Begin, OpCode::Swap, OpCode::Dup2, OpCode::Drop,
-        OpCode::Add,   OpCode::Swap, OpCode::Dup2, OpCode::Drop,
-        OpCode::Add,   OpCode::Swap, OpCode::Dup2, OpCode::Drop,
-        OpCode::Add,   OpCode::Noop, OpCode::Noop,
-    ], &[]);
+    let program = build_fibonacci_program();
 
     let options = ProofOptions::default();
     let inputs = ProgramInputs::from_public(&[1, 0]);
@@ -44,22 +35,659 @@ fn execute_verify_fail() {
     let (outputs, proof) = super::execute(&program, &inputs, num_outputs, &options);
     assert_eq!(outputs, [3]);
 
+    // the proof carries the seed of the public coin derived from the statement it was generated
+    // for, and so verifying against a wrong statement is rejected before any other checks
     // wrong inputs
     let result = super::verify(program.hash(), &[1, 1], &outputs, &proof);
-    let err_msg = format!("verification of low-degree proof failed: evaluations did not match column value at depth 0");
-    assert_eq!(Err(err_msg), result);
+    assert_eq!(Err(VerificationError::StatementMismatch), result);
 
     // wrong outputs
     let result = super::verify(program.hash(), inputs.get_public_inputs(), &[5], &proof);
-    let err_msg = format!("verification of low-degree proof failed: evaluations did not match column value at depth 0");
-    assert_eq!(Err(err_msg), result);
+    assert_eq!(Err(VerificationError::StatementMismatch), result);
 
     // wrong program hash
     let mut program_hash2 = program.hash().clone();
     program_hash2[0] = 1;
     let result = super::verify(&program_hash2, inputs.get_public_inputs(), &outputs, &proof);
-    let err_msg = format!("verification of low-degree proof failed: evaluations did not match column value at depth 0");
-    assert_eq!(Err(err_msg), result);
+    assert_eq!(Err(VerificationError::StatementMismatch), result);
+}
+
+#[test]
+fn prove_with_config() {
+    let fibonacci = build_fibonacci_program();
+    let looping = assembly::compile("begin read while.true push.2 mul read end end").unwrap();
+    let programs = [
+        (&fibonacci, ProgramInputs::from_public(&[1, 0]), 3, ProofOptions::default()),
+        (&looping, ProgramInputs::new(&[5], &[1, 1, 1, 0], &[]), 40, ProofOptions::default()),
+        (&looping, ProgramInputs::new(&[5], &[1, 1, 1, 0], &[]), 40, ProofOptions::with_zero_knowledge(32, 40, 0, HashFunction::Blake3)),
+    ];
+
+    // proofs do not depend on the number of threads used to generate them; in debug mode,
+    // degrees of individual constraints evaluated by different threads are validated as well
+    for (program, inputs, expected, options) in programs.iter() {
+        let mut proofs = Vec::new();
+        for &num_threads in [1, 3, 4, 16].iter() {
+            let trace = super::generate_trace(program, inputs, &OutputSpec::Top(1)).unwrap();
+            let proof = super::prove_with_config(trace, options, &ProverConfig::new(num_threads)).unwrap();
+            let result = super::verify(program.hash(), inputs.get_public_inputs(), &[*expected], &proof);
+            assert_eq!(Ok(true), result);
+            proofs.push(proof.to_bytes().unwrap());
+        }
+        assert!(options.zero_knowledge() || proofs.iter().all(|proof| *proof == proofs[0]));
+    }
+}
+
+#[test]
+fn run() {
+    let program = build_fibonacci_program();
+
+    let inputs = ProgramInputs::from_public(&[1, 0]);
+    let result = super::run(&program, &inputs).unwrap();
+    assert_eq!([3, 2, 0, 0, 0, 0, 0, 0], result.stack[..]);
+    assert_eq!(30, result.op_count);
+    assert_eq!(32, result.trace_length);
+    assert_eq!(0, result.ctx_depth);
+    assert_eq!(0, result.loop_depth);
+    assert_eq!(4, result.stack_depth);
+
+    // outputs of execution-only mode should match outputs of proven execution
+    let options = ProofOptions::default();
+    let (outputs, _) = super::execute(&program, &inputs, 2, &options);
+    assert_eq!(outputs, result.stack[..2]);
+}
+
+#[test]
+fn generate_trace_prove() {
+    let program = build_fibonacci_program();
+
+    let inputs = ProgramInputs::from_public(&[1, 0]);
+    let trace = super::generate_trace(&program, &inputs, &OutputSpec::Top(1)).unwrap();
+    assert_eq!([3], trace.outputs());
+    assert_eq!(program.hash(), trace.program_hash());
+    assert_eq!(32, trace.trace_length());
+
+    // a trace should survive a serialization round trip
+    let trace_bytes = bincode::serialize(&trace).unwrap();
+    let trace2 = bincode::deserialize::<super::ExecutionTrace>(&trace_bytes).unwrap();
+
+    // proofs generated from the same trace with different options should be valid
+    let options = ProofOptions::default();
+    let proof = super::prove(trace, &options).unwrap();
+    let result = super::verify(program.hash(), inputs.get_public_inputs(), &[3], &proof);
+    assert_eq!(Ok(true), result);
+
+    let options = ProofOptions::new(16, 40, 8, HashFunction::Blake3);
+    let proof = super::prove(trace2, &options).unwrap();
+    let result = super::verify(program.hash(), inputs.get_public_inputs(), &[3], &proof);
+    assert_eq!(Ok(true), result);
+}
+
+#[test]
+fn prove_malformed_trace() {
+    let program = build_fibonacci_program();
+
+    let inputs = ProgramInputs::from_public(&[1, 0]);
+    let trace = super::generate_trace(&program, &inputs, &OutputSpec::Top(1)).unwrap();
+    assert_eq!(Ok(()), trace.validate());
+    let registers = trace.registers().to_vec();
+    let options = ProofOptions::default();
+
+    // outputs which do not match the trace
+    let trace = ExecutionTrace::new(registers.clone(), 0, 0, program.hash(), &[1, 0], &[4], &[0], None);
+    let error = ExecutionError::MalformedTrace(String::from("output 0 does not match stack register 0 at the last step"));
+    assert_eq!(Err(error), super::prove(trace, &options).map(|_| ()));
+
+    // inputs which do not match the trace
+    let trace = ExecutionTrace::new(registers.clone(), 0, 0, program.hash(), &[2, 0], &[3], &[0], None);
+    let error = ExecutionError::MalformedTrace(String::from("input 0 does not match stack register 0 at the first step"));
+    assert_eq!(Err(error), trace.validate());
+
+    // output positions outside of the user stack
+    let trace = ExecutionTrace::new(registers.clone(), 0, 0, program.hash(), &[1, 0], &[0], &[4], None);
+    let error = ExecutionError::MalformedTrace(String::from("output position 4 is outside of user stack of depth 4"));
+    assert_eq!(Err(error), trace.validate());
+
+    // output positions used more than once
+    let trace = ExecutionTrace::new(registers.clone(), 0, 0, program.hash(), &[1, 0], &[3, 3], &[0, 0], None);
+    let error = ExecutionError::MalformedTrace(String::from("output position 0 is used more than once"));
+    assert_eq!(Err(error), trace.validate());
+
+    // stack size which does not match the trace
+    let trace = ExecutionTrace::new(registers.clone(), 0, 0, program.hash(), &[1, 0], &[3], &[0], Some(1));
+    let error = ExecutionError::MalformedTrace(String::from("stack size at the last step is not equal to the claimed size 1"));
+    assert_eq!(Err(error), trace.validate());
+
+    // program hash which does not match the trace
+    let trace = ExecutionTrace::new(registers.clone(), 0, 0, &[1; 32], &[1, 0], &[3], &[0], None);
+    let error = ExecutionError::MalformedTrace(String::from("program hash does not match the hash computed by the decoder"));
+    assert_eq!(Err(error), trace.validate());
+
+    // depths which do not match the number of registers
+    let trace = ExecutionTrace::new(registers.clone(), 2, 0, program.hash(), &[1, 0], &[3], &[0], None);
+    let error = ExecutionError::MalformedTrace(String::from("input 0 does not match stack register 0 at the first step"));
+    assert_eq!(Err(error), trace.validate());
+
+    let trace = ExecutionTrace::new(registers.clone(), 8, 0, program.hash(), &[], &[], &[], None);
+    let error = ExecutionError::MalformedTrace(String::from(
+        "trace of 20 registers has no user stack registers; decoder and stack size require 24 registers"));
+    assert_eq!(Err(error), trace.validate());
+
+    let trace = ExecutionTrace::new(registers.clone(), 0, 9, program.hash(), &[], &[], &[], None);
+    let error = ExecutionError::MalformedTrace(String::from("loop depth 9 is greater than 8"));
+    assert_eq!(Err(error), trace.validate());
+
+    // registers of invalid lengths
+    let mut registers2 = registers.clone();
+    registers2[7].pop();
+    let trace = ExecutionTrace::new(registers2, 0, 0, program.hash(), &[1, 0], &[3], &[0], None);
+    let error = ExecutionError::MalformedTrace(String::from("register 7 has 31 steps, but register 0 has 32 steps"));
+    assert_eq!(Err(error), trace.validate());
+
+    let registers2 = registers.iter().map(|r| r[..24].to_vec()).collect();
+    let trace = ExecutionTrace::new(registers2, 0, 0, program.hash(), &[1, 0], &[3], &[0], None);
+    let error = ExecutionError::MalformedTrace(String::from(
+        "trace length 24 is not a power of 2 greater than or equal to 16"));
+    assert_eq!(Err(error), trace.validate());
+}
+
+#[test]
+fn execute_verify_many_inputs_outputs() {
+    let program = build_fibonacci_program();
+
+    let options = ProofOptions::default();
+    let public_inputs = (0..20).map(|i| if i == 0 { 1 } else { i * 3 }).collect::<Vec<u128>>();
+    let inputs = ProgramInputs::from_public(&public_inputs);
+    let num_outputs = 24;
+
+    let (outputs, proof) = super::execute(&program, &inputs, num_outputs, &options);
+    let mut expected = public_inputs.clone();
+    expected[0] = 9;
+    expected[1] = 5;
+    expected.resize(num_outputs, 0);
+    assert_eq!(expected, outputs);
+
+    let result = super::verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
+    assert_eq!(Ok(true), result);
+
+    // wrong output deep in the stack
+    let mut outputs2 = outputs.clone();
+    outputs2[15] = 7;
+    let result = super::verify(program.hash(), inputs.get_public_inputs(), &outputs2, &proof);
+    assert_eq!(Err(VerificationError::StatementMismatch), result);
+
+    // wrong input deep in the stack
+    let mut inputs2 = public_inputs.clone();
+    inputs2[19] = 7;
+    let result = super::verify(program.hash(), &inputs2, &outputs, &proof);
+    assert_eq!(Err(VerificationError::StatementMismatch), result);
+
+    // more outputs than stack registers in the execution trace
+    let mut outputs3 = outputs.clone();
+    outputs3.resize(30, 0);
+    let result = super::verify(program.hash(), inputs.get_public_inputs(), &outputs3, &proof);
+    let error = VerificationError::StackDepthMismatch { inputs: 20, outputs: 30, stack_depth: 24 };
+    assert_eq!(Err(error), result);
+}
+
+#[test]
+fn execute_verify_committed_inputs() {
+    // the program sums 40 values read from tape A, and commits to them with a digest
+    let program = assembly::compile("
+    use.std::io
+    begin
+        pad.2 push.40 pad
+        repeat.20
+            exec.io::read_absorb swap.2 add roll.4 add roll.4 add pad roll.4 roll.4
+        end
+    end").unwrap();
+
+    let options = ProofOptions::default();
+    let values = (1..=40).map(|i| i * 7).collect::<Vec<u128>>();
+    let inputs = ProgramInputs::new(&[], &values, &[]);
+    let output_spec = OutputSpec::Positions(vec![3, 0, 1]);
+    let trace = super::generate_trace(&program, &inputs, &output_spec).unwrap();
+    let proof = super::prove(trace, &options).unwrap();
+
+    // the verifier binds the full vector of values through its digest
+    let digest = hasher::digest_sequence(&values);
+    let outputs = [values.iter().sum(), digest[1], digest[0]];
+    let result = super::verify_with_outputs(program.hash(), &[], &output_spec, &outputs, &proof);
+    assert_eq!(Ok(true), result);
+
+    let mut values2 = values.clone();
+    values2[39] = 0;
+    let digest = hasher::digest_sequence(&values2);
+    let outputs = [values.iter().sum(), digest[1], digest[0]];
+    let result = super::verify_with_outputs(program.hash(), &[], &output_spec, &outputs, &proof);
+    assert_eq!(Err(VerificationError::StatementMismatch), result);
+}
+
+#[test]
+fn execute_verify_sequences() {
+    // the program doubles each of 40 values of the input sequence, and writes the results to
+    // the output tape; the loop keeps the output digest on top of the input digest
+    let program = assembly::compile("
+    use.std::io
+    begin
+        push.40 pad
+        repeat.20
+            swap.2 exec.io::read_absorb swap.2
+            dup add swap dup add swap swap.2
+            pad.2 swap.4 exec.io::write_absorb swap.2 drop.2
+        end
+    end").unwrap();
+
+    let options = ProofOptions::default();
+    let values = (1..=40).map(|i| i * 7).collect::<Vec<u128>>();
+    assert!(values.len() > MAX_STACK_DEPTH);
+    let inputs = ProgramInputs::from_sequence(&values, &[], &[]);
+    let (outputs, proof) = super::execute_sequences(&program, &inputs, &options).unwrap();
+    assert_eq!(values.iter().map(|v| v * 2).collect::<Vec<u128>>(), outputs);
+
+    let result = super::verify_sequences(program.hash(), &values, &outputs, &proof);
+    assert_eq!(Ok(true), result);
+
+    // the verifier binds every value and the length of both sequences
+    let mut values2 = values.clone();
+    values2[39] = 0;
+    let result = super::verify_sequences(program.hash(), &values2, &outputs, &proof);
+    assert_eq!(Err(VerificationError::StatementMismatch), result);
+
+    let result = super::verify_sequences(program.hash(), &values[..39], &outputs, &proof);
+    assert_eq!(Err(VerificationError::StatementMismatch), result);
+
+    let mut outputs2 = outputs.clone();
+    outputs2[0] = 0;
+    let result = super::verify_sequences(program.hash(), &values, &outputs2, &proof);
+    assert_eq!(Err(VerificationError::StatementMismatch), result);
+
+    outputs2.push(0);
+    let result = super::verify_sequences(program.hash(), &values, &outputs2[1..], &proof);
+    assert_eq!(Err(VerificationError::StatementMismatch), result);
+}
+
+#[test]
+fn execute_verify_output_spec() {
+    let program = build_fibonacci_program();
+
+    let options = ProofOptions::default();
+    let inputs = ProgramInputs::from_public(&[1, 0, 7, 8, 9]);
+
+    // outputs from arbitrary stack positions
+    let output_spec = OutputSpec::Positions(vec![3, 0, 4]);
+    let trace = super::generate_trace(&program, &inputs, &output_spec).unwrap();
+    assert_eq!([8, 3, 9], trace.outputs());
+    let proof = super::prove(trace, &options).unwrap();
+
+    let result = super::verify_with_outputs(program.hash(), inputs.get_public_inputs(), &output_spec, &[8, 3, 9], &proof);
+    assert_eq!(Ok(true), result);
+
+    let result = super::verify_with_outputs(program.hash(), inputs.get_public_inputs(), &output_spec, &[3, 8, 9], &proof);
+    assert_eq!(Err(VerificationError::StatementMismatch), result);
+
+    let output_spec2 = OutputSpec::Positions(vec![3, 1, 4]);
+    let result = super::verify_with_outputs(program.hash(), inputs.get_public_inputs(), &output_spec2, &[8, 3, 9], &proof);
+    assert_eq!(Err(VerificationError::StatementMismatch), result);
+
+    // the entire stack
+    let trace = super::generate_trace(&program, &inputs, &OutputSpec::Stack).unwrap();
+    assert_eq!([3, 2, 7, 8, 9], trace.outputs());
+    assert_eq!(Some(5), trace.stack_size());
+    let proof = super::prove(trace, &options).unwrap();
+
+    let outputs = [3, 2, 7, 8, 9];
+    let result = super::verify_with_outputs(program.hash(), inputs.get_public_inputs(), &OutputSpec::Stack, &outputs, &proof);
+    assert_eq!(Ok(true), result);
+
+    // omitting an item of the stack, or claiming an item which is not on the stack is rejected
+    let result = super::verify_with_outputs(program.hash(), inputs.get_public_inputs(), &OutputSpec::Stack, &outputs[..4], &proof);
+    assert_eq!(Err(VerificationError::StatementMismatch), result);
+
+    let result = super::verify_with_outputs(program.hash(), inputs.get_public_inputs(), &OutputSpec::Stack, &[3, 2, 7, 8, 9, 0], &proof);
+    assert_eq!(Err(VerificationError::StatementMismatch), result);
+
+    // invalid output positions
+    let error = super::generate_trace(&program, &inputs, &OutputSpec::Positions(vec![1, 32])).err().unwrap();
+    assert_eq!(ExecutionError::InvalidOutputPosition { position: 32, max: 32 }, error);
+
+    let error = super::generate_trace(&program, &inputs, &OutputSpec::Positions(vec![1, 0, 1])).err().unwrap();
+    assert_eq!(ExecutionError::DuplicateOutputPosition { position: 1 }, error);
+}
+
+#[test]
+fn verify_invalid_output_positions() {
+    let program = build_fibonacci_program();
+
+    let options = ProofOptions::default();
+    let inputs = ProgramInputs::from_public(&[1, 0, 7, 8, 9]);
+    let trace = super::generate_trace(&program, &inputs, &OutputSpec::Top(1)).unwrap();
+    assert_eq!([3], trace.outputs());
+    let proof = super::prove(trace, &options).unwrap();
+
+    // constraints for outputs bound to the same position would be summed together, and so
+    // outputs [s + d, s - d] would be accepted for any d if duplicate positions were allowed
+    let d = 5;
+    let outputs = [field::add(3, d), field::sub(3, d)];
+    let output_spec = OutputSpec::Positions(vec![0, 0]);
+    let result = super::verify_with_outputs(program.hash(), inputs.get_public_inputs(), &output_spec, &outputs, &proof);
+    assert_eq!(Err(VerificationError::DuplicateOutputPosition { position: 0 }), result);
+
+    // positions outside of the user stack of the execution trace are rejected
+    let output_spec = OutputSpec::Positions(vec![0, 20]);
+    let result = super::verify_with_outputs(program.hash(), inputs.get_public_inputs(), &output_spec, &[3, 0], &proof);
+    assert_eq!(Err(VerificationError::StackDepthMismatch { inputs: 5, outputs: 21, stack_depth: 8 }), result);
+}
+
+#[test]
+fn execute_verify_zero_knowledge() {
+    let program = build_fibonacci_program();
+
+    let options = ProofOptions::with_zero_knowledge(32, 16, 0, HashFunction::Blake3);
+    let inputs = ProgramInputs::from_public(&[1, 0]);
+
+    let (outputs, proof1) = super::execute(&program, &inputs, 1, &options);
+    assert_eq!(outputs, [3]);
+    assert_eq!(options.min_trace_length(), proof1.trace_length());
+
+    let result = super::verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof1);
+    assert_eq!(Ok(true), result);
+
+    let result = super::verify(program.hash(), inputs.get_public_inputs(), &[5], &proof1);
+    assert_eq!(Err(VerificationError::StatementMismatch), result);
+
+    // proofs of the same statement reveal different trace values
+    let (_, proof2) = super::execute(&program, &inputs, 1, &options);
+    let result = super::verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof2);
+    assert_eq!(Ok(true), result);
+    assert!(proof1.trace_root() != proof2.trace_root());
+    assert!(proof1.mask_root() != proof2.mask_root());
+}
+
+#[test]
+fn execute_verify_fixed_trace_length() {
+    let program = assembly::compile("
+    begin
+        read
+        if.true
+            add
+        else
+            mul dup add dup add dup add dup add dup add dup add dup add dup add dup add
+        end
+    end").unwrap();
+
+    let options = ProofOptions::default().with_fixed_trace_length(256);
+    let mut proofs = Vec::new();
+    let mut op_counts = Vec::new();
+    for &(branch, expected) in [(1, 3), (0, 1024)].iter() {
+        let inputs = ProgramInputs::new(&[1, 2], &[branch], &[]);
+        op_counts.push(super::run(&program, &inputs).unwrap().op_count);
+
+        let (outputs, proof) = super::execute(&program, &inputs, 1, &options);
+        assert_eq!(outputs, [expected]);
+
+        let result = super::verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
+        assert_eq!(Ok(true), result);
+        proofs.push(proof);
+    }
+
+    // executions take different paths, but proofs do not reveal this
+    assert!(op_counts[0] != op_counts[1]);
+    for proof in proofs.iter() {
+        assert_eq!(256, proof.trace_length());
+        assert_eq!(0, proof.op_count());
+    }
+
+    // executions which do not fit into the fixed trace length cannot be proven
+    let options = ProofOptions::default().with_fixed_trace_length(16);
+    let inputs = ProgramInputs::new(&[1, 2], &[0], &[]);
+    let error = super::try_execute(&program, &inputs, 1, &options).err().unwrap();
+    assert_eq!(ExecutionError::TraceTooLong { trace_length: 128, max: 16 }, error);
+}
+
+#[test]
+fn execute_verify_fixed_trace_depths() {
+    let program = assembly::compile("
+    begin
+        read
+        if.true
+            add
+        else
+            push.1
+            while.true
+                push.2 push.3 push.4 push.5 push.6 push.7 push.8 push.0
+                if.true
+                    noop
+                else
+                    drop drop drop drop drop drop drop
+                end
+                push.0
+            end
+        end
+    end").unwrap();
+
+    // the second branch uses deeper context, loop, and user stacks
+    let inputs = [ProgramInputs::new(&[1, 2], &[1], &[]), ProgramInputs::new(&[1, 2], &[0], &[])];
+    let results = inputs.iter().map(|inputs| super::run(&program, inputs).unwrap()).collect::<Vec<_>>();
+    assert!(results[0].ctx_depth < results[1].ctx_depth);
+    assert!(results[0].loop_depth < results[1].loop_depth);
+    assert!(results[0].stack_depth < results[1].stack_depth);
+
+    // with fixed trace length, all traces are padded to the same depths
+    for options in [
+        ProofOptions::default().with_fixed_trace_length(256),
+        ProofOptions::default().with_fixed_trace_depths(4, 2, 16).with_fixed_trace_length(256),
+    ].iter() {
+        let [ctx_depth, loop_depth, stack_depth] = options.fixed_trace_depths().unwrap();
+        for inputs in inputs.iter() {
+            let (outputs, proof) = super::execute(&program, inputs, 1, options);
+            let result = super::verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
+            assert_eq!(Ok(true), result);
+            assert_eq!((ctx_depth, loop_depth, stack_depth), (proof.ctx_depth(), proof.loop_depth(), proof.stack_depth()));
+        }
+    }
+    assert_eq!(Some([MAX_CONTEXT_DEPTH, MAX_LOOP_DEPTH, MAX_STACK_DEPTH]),
+        ProofOptions::default().with_fixed_trace_length(256).fixed_trace_depths());
+
+    // executions which do not fit into the fixed trace depths cannot be proven
+    let options = ProofOptions::default().with_fixed_trace_depths(1, 1, 8);
+    let error = super::try_execute(&program, &inputs[1], 1, &options).err().unwrap();
+    let depths = [results[1].ctx_depth, results[1].loop_depth, results[1].stack_depth];
+    assert_eq!(ExecutionError::TraceTooWide { depths, max: [1, 1, 8] }, error);
+}
+
+#[test]
+fn execute_verify_hash_functions() {
+    let program = build_fibonacci_program();
+    let inputs = ProgramInputs::from_public(&[1, 0]);
+
+    for &hash_fn in HashFunction::all().iter() {
+        let options = ProofOptions::new(32, 16, 4, hash_fn);
+        let (outputs, proof) = super::execute(&program, &inputs, 1, &options);
+        assert_eq!(outputs, [3]);
+
+        // proofs survive both serialization formats
+        let proof: StarkProof = bincode::deserialize(&bincode::serialize(&proof).unwrap()).unwrap();
+        assert_eq!(hash_fn, proof.options().hash_fn());
+        let proof = StarkProof::from_bytes(&proof.to_bytes().unwrap()).unwrap();
+        assert_eq!(hash_fn, proof.options().hash_fn());
+
+        let result = super::verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
+        assert_eq!(Ok(true), result, "verification failed for {:?}", hash_fn);
+    }
+}
+
+#[test]
+fn verify_with_policy() {
+    let program = build_fibonacci_program();
+
+    let inputs = ProgramInputs::from_public(&[1, 0]);
+    let policy = VerifierPolicy::new(100, &[HashFunction::Blake3], 16, 1024).unwrap().with_conjectured_security();
+
+    // default options satisfy the policy for conjectured security, but not for proven security
+    let options = ProofOptions::default();
+    let (outputs, proof) = super::execute(&program, &inputs, 1, &options);
+    let result = super::verify_with_policy(program.hash(), inputs.get_public_inputs(), &outputs, &proof, &policy);
+    assert_eq!(Ok(true), result);
+
+    let proven_policy = VerifierPolicy::new(100, &[HashFunction::Blake3], 16, 1024).unwrap();
+    let result = super::verify_with_policy(program.hash(), inputs.get_public_inputs(), &outputs, &proof, &proven_policy);
+    let error = VerificationError::OptionsPolicyViolation(
+        String::from("proven security level of 50 bits is below the minimum of 100 bits"));
+    assert_eq!(Err(error), result);
+
+    // a proof with a single query is valid, but falls below the policy
+    let options = ProofOptions::new(32, 1, 0, HashFunction::Blake3);
+    let (outputs, proof) = super::execute(&program, &inputs, 1, &options);
+    let result = super::verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
+    assert_eq!(Ok(true), result);
+
+    let result = super::verify_with_policy(program.hash(), inputs.get_public_inputs(), &outputs, &proof, &policy);
+    let error = VerificationError::OptionsPolicyViolation(
+        String::from("conjectured security level of 2 bits is below the minimum of 100 bits"));
+    assert_eq!(Err(error), result);
+
+    // trace length is outside of the allowed range
+    let policy = VerifierPolicy::new(100, &[HashFunction::Blake3], 64, 1024).unwrap().with_conjectured_security();
+    let (outputs, proof) = super::execute(&program, &inputs, 1, &ProofOptions::default());
+    let result = super::verify_with_policy(program.hash(), inputs.get_public_inputs(), &outputs, &proof, &policy);
+    let error = VerificationError::OptionsPolicyViolation(
+        String::from("trace length 32 is outside of the allowed range [64, 1024]"));
+    assert_eq!(Err(error), result);
+
+    // hash function is not allowed
+    let policy = VerifierPolicy::new(40, &[HashFunction::Sha3], 16, 1024).unwrap();
+    let result = super::verify_with_policy(program.hash(), inputs.get_public_inputs(), &outputs, &proof, &policy);
+    let error = VerificationError::OptionsPolicyViolation(String::from("hash function is not allowed"));
+    assert_eq!(Err(error), result);
+}
+
+#[test]
+fn verifier_policy_bounds() {
+    // no hash functions are allowed
+    let result = VerifierPolicy::new(100, &[], 16, 1024);
+    assert_eq!(Err(PolicyError::NoHashFunctions), result.map(|_| ()));
+
+    // trace length range is empty
+    let result = VerifierPolicy::new(100, &[HashFunction::Blake3], 1024, 16);
+    let error = PolicyError::InvalidTraceLengthRange { min: 1024, max: 16 };
+    assert_eq!(Err(error), result.map(|_| ()));
+
+    // a range with a single trace length is fine
+    assert!(VerifierPolicy::new(100, &[HashFunction::Blake3], 64, 64).is_ok());
+}
+
+#[test]
+fn verify_outputs_and_batch_with_policy() {
+    let program = build_fibonacci_program();
+
+    let inputs = ProgramInputs::from_public(&[1, 0, 7]);
+    let policy = VerifierPolicy::new(40, &[HashFunction::Blake3], 16, 1024).unwrap();
+    let violation = VerificationError::OptionsPolicyViolation(
+        String::from("proven security level of 0 bits is below the minimum of 40 bits"));
+
+    let strong_options = ProofOptions::default();
+    let weak_options = ProofOptions::new(32, 1, 0, HashFunction::Blake3);
+    let output_spec = OutputSpec::Positions(vec![2, 0]);
+    let mut proofs = Vec::new();
+    for options in [&strong_options, &weak_options].iter() {
+        let trace = super::generate_trace(&program, &inputs, &output_spec).unwrap();
+        assert_eq!([7, 3], trace.outputs());
+        proofs.push(super::prove(trace, options).unwrap());
+    }
+
+    // outputs selected by an output spec
+    let result = super::verify_with_outputs_and_policy(
+        program.hash(), inputs.get_public_inputs(), &output_spec, &[7, 3], &proofs[0], &policy);
+    assert_eq!(Ok(true), result);
+
+    let result = super::verify_with_outputs_and_policy(
+        program.hash(), inputs.get_public_inputs(), &output_spec, &[7, 3], &proofs[1], &policy);
+    assert_eq!(Err(violation.clone()), result);
+
+    // a batch of proofs; only proofs which satisfy the policy are verified
+    let (outputs, strong_proof) = super::execute(&program, &inputs, 1, &strong_options);
+    let (_, weak_proof) = super::execute(&program, &inputs, 1, &weak_options);
+    let batch = [
+        (program.hash(), inputs.get_public_inputs(), &outputs[..], &weak_proof),
+        (program.hash(), inputs.get_public_inputs(), &outputs[..], &strong_proof),
+        (program.hash(), inputs.get_public_inputs(), &[4][..], &strong_proof),
+    ];
+    let results = super::verify_batch_with_policy(&batch, &policy);
+    assert_eq!(vec![Err(violation), Ok(true), Err(VerificationError::StatementMismatch)], results);
+}
+
+#[test]
+fn verify_batch() {
+    let program = build_fibonacci_program();
+
+    // proofs for traces of different shapes, and several proofs for the same shape
+    let all_options = [
+        ProofOptions::default(),
+        ProofOptions::default().with_fixed_trace_length(64),
+        ProofOptions::with_zero_knowledge(32, 24, 8, HashFunction::Blake3),
+    ];
+    let mut statements = Vec::new();
+    for options in all_options.iter() {
+        for &a in [1, 2].iter() {
+            let inputs = ProgramInputs::from_public(&[a, 0]);
+            let (outputs, proof) = super::execute(&program, &inputs, 1, options);
+            statements.push((inputs.get_public_inputs().to_vec(), outputs, proof));
+        }
+    }
+
+    // claim wrong outputs for one of the proofs
+    statements[3].1 = vec![5];
+
+    let batch = statements.iter()
+        .map(|(inputs, outputs, proof)| (program.hash(), &inputs[..], &outputs[..], proof))
+        .collect::<Vec<_>>();
+    let results = super::verify_batch(&batch);
+
+    assert_eq!(batch.len(), results.len());
+    for (i, (inputs, outputs, proof)) in statements.iter().enumerate() {
+        assert_eq!(super::verify(program.hash(), inputs, outputs, proof), results[i]);
+    }
+    assert_eq!(Err(VerificationError::StatementMismatch), results[3]);
+    assert_eq!(5, results.iter().filter(|&result| *result == Ok(true)).count());
+
+    assert_eq!(Vec::<Result<bool, VerificationError>>::new(), super::verify_batch(&[]));
+}
+
+#[test]
+fn verify_batch_mixed_shapes() {
+    let fibonacci = build_fibonacci_program();
+    let looping = assembly::compile("begin read while.true push.2 mul read end end").unwrap();
+
+    // programs with different trace depths, proven with different extension factors and trace
+    // lengths; proofs with the same extension factor share extended constants
+    let mut statements = Vec::new();
+    let programs = [
+        (&fibonacci, ProgramInputs::from_public(&[1, 0]), 3),
+        (&looping, ProgramInputs::new(&[5], &[1, 1, 1, 0], &[]), 40),
+    ];
+    for (program, inputs, expected) in programs.iter() {
+        for options in [
+            ProofOptions::new(16, 40, 8, HashFunction::Blake3),
+            ProofOptions::default(),
+            ProofOptions::default().with_fixed_trace_length(128),
+        ].iter() {
+            let (outputs, proof) = super::execute(program, inputs, 1, options);
+            assert_eq!([*expected], outputs[..]);
+            statements.push((program.hash(), inputs.get_public_inputs().to_vec(), outputs, proof));
+        }
+    }
+    assert!(statements[0].3.loop_depth() != statements[3].3.loop_depth());
+
+    let batch = statements.iter()
+        .map(|(program_hash, inputs, outputs, proof)| (*program_hash, &inputs[..], &outputs[..], proof))
+        .collect::<Vec<_>>();
+    let results = super::verify_batch(&batch);
+    assert!(results.iter().all(|result| *result == Ok(true)), "{:?}", results);
+
+    // results do not depend on which proofs are verified together
+    for (program_hash, inputs, outputs, proof) in statements.iter() {
+        assert_eq!(Ok(true), super::verify(program_hash, inputs, outputs, proof));
+    }
 }
 
 #[test]
@@ -146,6 +774,62 @@ fn selection_operations_panic() {
     super::execute(&program, &inputs, num_outputs, &options);
 }
 
+#[test]
+fn selection_operations_error() {
+    let program = build_program(vec![
+        OpCode::Begin, OpCode::Choose, OpCode::Choose, OpCode::Noop,
+        OpCode::Noop,  OpCode::Noop,   OpCode::Noop,   OpCode::Noop,
+        OpCode::Noop,  OpCode::Noop,   OpCode::Noop,   OpCode::Noop,
+        OpCode::Noop,  OpCode::Noop,   OpCode::Noop,
+    ], &[]);
+
+    let options = ProofOptions::default();
+    let inputs = ProgramInputs::from_public(&[3, 4, 2, 5, 0, 6, 7, 8]);
+    let num_outputs = 8;
+
+    let error = super::try_execute(&program, &inputs, num_outputs, &options).err().unwrap();
+    assert_eq!(ExecutionError::NonBinaryCondition {
+        op: OpCode::Choose, step: 2, stack: vec![3, 4, 2, 5, 0, 6, 7, 8]
+    }, error);
+}
+
+#[test]
+fn execution_errors() {
+    let options = ProofOptions::default();
+
+    // too many outputs
+    let program = build_program(vec![
+        OpCode::Begin, OpCode::Noop, OpCode::Noop, OpCode::Noop,
+        OpCode::Noop,  OpCode::Noop, OpCode::Noop, OpCode::Noop,
+        OpCode::Noop,  OpCode::Noop, OpCode::Noop, OpCode::Noop,
+        OpCode::Noop,  OpCode::Noop, OpCode::Noop,
+    ], &[]);
+    let error = super::try_execute(&program, &ProgramInputs::none(), 100, &options).err().unwrap();
+    assert_eq!(ExecutionError::TooManyOutputs { requested: 100, max: super::MAX_OUTPUTS }, error);
+
+    // failed assertion
+    let program = build_program(vec![
+        OpCode::Begin, OpCode::Assert, OpCode::Noop, OpCode::Noop,
+        OpCode::Noop,  OpCode::Noop,   OpCode::Noop, OpCode::Noop,
+        OpCode::Noop,  OpCode::Noop,   OpCode::Noop, OpCode::Noop,
+        OpCode::Noop,  OpCode::Noop,   OpCode::Noop,
+    ], &[]);
+    let inputs = ProgramInputs::from_public(&[2, 3]);
+    let error = super::try_execute(&program, &inputs, 1, &options).err().unwrap();
+    assert_eq!(ExecutionError::FailedAssertion { op: OpCode::Assert, step: 2, stack: vec![2, 3] }, error);
+    assert_eq!("ASSERT failed at step 2", error.to_string());
+
+    // empty input tape
+    let program = build_program(vec![
+        OpCode::Begin, OpCode::Read, OpCode::Noop, OpCode::Noop,
+        OpCode::Noop,  OpCode::Noop, OpCode::Noop, OpCode::Noop,
+        OpCode::Noop,  OpCode::Noop, OpCode::Noop, OpCode::Noop,
+        OpCode::Noop,  OpCode::Noop, OpCode::Noop,
+    ], &[]);
+    let error = super::try_execute(&program, &ProgramInputs::none(), 1, &options).err().unwrap();
+    assert_eq!("attempt to read from empty tape A at step 2", error.to_string());
+}
+
 #[test]
 fn math_operations() {
     let program = build_program(vec![
@@ -296,6 +980,18 @@ fn assert_operations() {
 
 // HELPER FUNCTIONS
 // ================================================================================================
+
+/// Builds a program which computes the 4th Fibonacci number starting with public inputs [1, 0];
+/// the output of the program is [3].
+fn build_fibonacci_program() -> Program {
+    return build_program(vec![
+        OpCode::Begin, OpCode::Swap, OpCode::Dup2, OpCode::Drop,
+        OpCode::Add,   OpCode::Swap, OpCode::Dup2, OpCode::Drop,
+        OpCode::Add,   OpCode::Swap, OpCode::Dup2, OpCode::Drop,
+        OpCode::Add,   OpCode::Noop, OpCode::Noop,
+    ], &[]);
+}
+
 fn build_program(instructions: Vec<OpCode>, push_values: &[u128]) -> Program {
 
     // build hint map for PUSh operations
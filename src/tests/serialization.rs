@@ -0,0 +1,311 @@
+use crate::{
+    ProofOptions, StarkProof, ExecutionReceipt, DeserializationError, VerificationError,
+    RECEIPT_FORMAT_VERSION, crypto::HashFunction };
+use super::{
+    build_fibonacci_program,
+    super::{ execute, generate_trace, prove, verify, verify_receipt, ProgramInputs, OutputSpec }
+};
+
+#[test]
+fn proof_to_bytes_from_bytes() {
+    for options in [ProofOptions::default(), ProofOptions::with_zero_knowledge(32, 24, 8, HashFunction::Blake3)].iter() {
+        let (program, inputs, outputs, proof) = build_proof(options);
+
+        let bytes = proof.to_bytes().unwrap();
+        let decoded = StarkProof::from_bytes(&bytes).unwrap();
+
+        // all fields of the proof, including the omitted depths of Merkle proofs, are restored
+        assert_eq!(bincode::serialize(&proof).unwrap(), bincode::serialize(&decoded).unwrap());
+        assert_eq!(bytes, decoded.to_bytes().unwrap());
+
+        let result = verify(program.hash(), inputs.get_public_inputs(), &outputs, &decoded);
+        assert_eq!(Ok(true), result);
+    }
+}
+
+#[test]
+fn proof_size_regression() {
+    for options in [ProofOptions::default(), ProofOptions::with_zero_knowledge(32, 24, 8, HashFunction::Blake3)].iter() {
+        let (_, _, _, proof) = build_proof(options);
+
+        // the binary format must be at least 5% smaller than bincode encoding of the proof
+        let bincode_size = bincode::serialize(&proof).unwrap().len();
+        let binary_size = proof.to_bytes().unwrap().len();
+        assert!(binary_size * 100 <= bincode_size * 95,
+            "proof size of {} bytes is not sufficiently smaller than {} bytes", binary_size, bincode_size);
+    }
+}
+
+#[test]
+fn proof_from_malformed_bytes() {
+    let (_, _, _, proof) = build_proof(&ProofOptions::default());
+    let bytes = proof.to_bytes().unwrap();
+
+    // unsupported version
+    let mut malformed = bytes.clone();
+    malformed[0] = 2;
+    let error = DeserializationError::UnsupportedVersion { version: 2, expected: 3 };
+    assert_eq!(Err(error), StarkProof::from_bytes(&malformed).map(|_| ()));
+
+    // invalid options
+    let mut malformed = bytes.clone();
+    malformed[2] = 0;
+    let error = DeserializationError::InvalidValue(String::from("number of queries"));
+    assert_eq!(Err(error), StarkProof::from_bytes(&malformed).map(|_| ()));
+
+    // truncated input
+    for &length in [0, 1, 20, bytes.len() / 2, bytes.len() - 1].iter() {
+        let error = DeserializationError::UnexpectedEnd;
+        assert_eq!(Err(error), StarkProof::from_bytes(&bytes[..length]).map(|_| ()));
+    }
+
+    // trailing bytes
+    let mut malformed = bytes.clone();
+    malformed.extend_from_slice(&[0, 0]);
+    let error = DeserializationError::TrailingBytes { count: 2 };
+    assert_eq!(Err(error), StarkProof::from_bytes(&malformed).map(|_| ()));
+
+    // corrupting any byte never causes a panic
+    for i in 0..bytes.len() {
+        let mut malformed = bytes.clone();
+        malformed[i] ^= 0xFF;
+        let _ = StarkProof::from_bytes(&malformed);
+    }
+}
+
+#[test]
+fn verify_malformed_proof() {
+    let (program, inputs, outputs, proof) = build_proof(&ProofOptions::default());
+    let bytes = bincode::serialize(&proof).unwrap();
+
+    // trace info follows the statement digest and the trace root in bincode encoding
+    let mut malformed = bytes.clone();
+    malformed[67] = 200;
+    let proof: StarkProof = bincode::deserialize(&malformed).unwrap();
+    let result = verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
+    let error = VerificationError::MalformedProof(String::from("stack depth 200"));
+    assert_eq!(Err(error), result);
+
+    let mut malformed = bytes.clone();
+    malformed[64] = 60;
+    let proof: StarkProof = bincode::deserialize(&malformed).unwrap();
+    let result = verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
+    let error = VerificationError::MalformedProof(String::from("trace domain size 2^60"));
+    assert_eq!(Err(error), result);
+
+    // options are at the end of bincode encoding, and are validated on de-serialization
+    let mut malformed = bytes.clone();
+    let num_queries_idx = malformed.len() - 8;
+    malformed[num_queries_idx] = 0;
+    let error = bincode::deserialize::<StarkProof>(&malformed).err().unwrap();
+    assert!(error.to_string().contains("number of queries"));
+
+    let mut malformed = bytes.clone();
+    let extension_factor_idx = malformed.len() - 9;
+    malformed[extension_factor_idx] = 255;
+    let error = bincode::deserialize::<StarkProof>(&malformed).err().unwrap();
+    assert!(error.to_string().contains("extension factor"));
+}
+
+#[test]
+fn verify_forged_statement_digest() {
+    let (program, inputs, outputs, proof) = build_proof(&ProofOptions::default());
+    let inputs2 = ProgramInputs::from_public(&[1, 1]);
+    let (outputs2, proof2) = execute(&program, &inputs2, 1, &ProofOptions::default());
+    assert!(outputs != outputs2);
+
+    // replacing the statement digest with the digest of another statement passes the up-front
+    // check, but the proof is still rejected since the challenges are derived from the statement
+    let digest = hex::encode(proof.statement_digest());
+    let digest2 = hex::encode(proof2.statement_digest());
+    let forged = StarkProof::from_json(&proof.to_json().replacen(&digest, &digest2, 1)).unwrap();
+    let result = verify(program.hash(), inputs2.get_public_inputs(), &outputs2, &forged);
+    assert_eq!(Err(VerificationError::ProofOfWorkNotVerified), result);
+
+    let result = verify(program.hash(), inputs.get_public_inputs(), &outputs, &forged);
+    assert_eq!(Err(VerificationError::StatementMismatch), result);
+}
+
+#[test]
+fn proof_to_json_from_json() {
+    for options in [ProofOptions::default(), ProofOptions::with_zero_knowledge(32, 24, 8, HashFunction::Sha3)].iter() {
+        let (program, inputs, outputs, proof) = build_proof(options);
+
+        let json = proof.to_json();
+        let decoded = StarkProof::from_json(&json).unwrap();
+        assert_eq!(bincode::serialize(&proof).unwrap(), bincode::serialize(&decoded).unwrap());
+        assert_eq!(json, decoded.to_json());
+
+        let result = verify(program.hash(), inputs.get_public_inputs(), &outputs, &decoded);
+        assert_eq!(Ok(true), result);
+    }
+
+    // field elements are written as decimal strings and hashes as hex strings
+    let (_, _, _, proof) = build_proof(&ProofOptions::default());
+    let json = proof.to_json();
+    assert!(json.contains(&format!("\"root\":\"{}\"", hex::encode(proof.trace_root()))));
+    assert!(json.contains(&format!("\"{}\"", proof.trace_evaluations()[0][1])));
+    assert!(json.contains("\"hash_fn\":\"blake3\""));
+}
+
+#[test]
+fn proof_from_malformed_json() {
+    let (_, _, _, proof) = build_proof(&ProofOptions::default());
+    let json = proof.to_json();
+
+    let malformed = json.replacen("\"version\":3", "\"version\":2", 1);
+    let error = DeserializationError::UnsupportedVersion { version: 2, expected: 3 };
+    assert_eq!(Err(error), StarkProof::from_json(&malformed).map(|_| ()));
+
+    let malformed = json.replacen("\"blake3\"", "\"md5\"", 1);
+    let error = DeserializationError::InvalidValue(String::from("hash function md5"));
+    assert_eq!(Err(error), StarkProof::from_json(&malformed).map(|_| ()));
+
+    let root = hex::encode(proof.trace_root());
+    let malformed = json.replacen(&root, &root[2..], 1);
+    let error = DeserializationError::InvalidValue(format!("digest {}", &root[2..]));
+    assert_eq!(Err(error), StarkProof::from_json(&malformed).map(|_| ()));
+
+    let nonce = format!("\"pow_nonce\":\"{}\"", proof.pow_nonce());
+    let malformed = json.replacen(&nonce, "\"pow_nonce\":\"-1\"", 1);
+    let error = DeserializationError::InvalidValue(String::from("proof-of-work nonce -1"));
+    assert_eq!(Err(error), StarkProof::from_json(&malformed).map(|_| ()));
+
+    // values outside of the field are rejected
+    let element = format!("[\"{}\"", proof.trace_evaluations()[0][0]);
+    let modulus = crate::math::field::MODULUS;
+    let malformed = json.replacen(&element, &format!("[\"{}\"", modulus), 1);
+    let error = DeserializationError::InvalidValue(format!("field element {}", modulus));
+    assert_eq!(Err(error), StarkProof::from_json(&malformed).map(|_| ()));
+
+    let error = StarkProof::from_json(&json[..json.len() - 1]).err().unwrap();
+    assert!(matches!(error, DeserializationError::InvalidValue(_)));
+}
+
+#[test]
+fn malformed_proof_to_bytes() {
+    let (program, inputs, outputs, proof) = build_proof(&ProofOptions::default());
+
+    // proofs deserialized with serde are not validated; a FRI remainder with a length which is
+    // not a power of 2 cannot be encoded, and must be reported as an error rather than a panic;
+    // in bincode encoding, the remainder is written as its length followed by its values
+    let rem_values = &proof.degree_proof().rem_values;
+    let mut prefix = (rem_values.len() as u64).to_le_bytes().to_vec();
+    prefix.extend_from_slice(&rem_values[0].to_le_bytes());
+    let mut bytes = bincode::serialize(&proof).unwrap();
+    let start = bytes.windows(prefix.len()).position(|window| window == &prefix[..]).unwrap();
+    bytes[start..start + 8].copy_from_slice(&(rem_values.len() as u64 - 1).to_le_bytes());
+    let end = start + 8 + rem_values.len() * 16;
+    bytes.drain(end - 16..end);
+    let proof: StarkProof = bincode::deserialize(&bytes).unwrap();
+
+    let error = VerificationError::MalformedProof(String::from("FRI remainder length 255"));
+    assert_eq!(Err(error.clone()), proof.to_bytes());
+    assert_eq!(Err(error.clone()), proof.stats().map(|_| ()));
+
+    let receipt = ExecutionReceipt::new(program.hash(), inputs.get_public_inputs(), &outputs, proof);
+    assert_eq!(Err(error), receipt.to_bytes());
+}
+
+#[test]
+fn proof_stats() {
+    for options in [ProofOptions::default(), ProofOptions::with_zero_knowledge(32, 24, 8, HashFunction::Blake3)].iter() {
+        let (_, _, _, proof) = build_proof(options);
+        let stats = proof.stats().unwrap();
+
+        assert_eq!(proof.to_bytes().unwrap().len(), stats.total());
+        assert_eq!(proof.degree_proof().layers.len(), stats.fri_layers.len());
+        assert!(stats.fri_layers.iter().all(|&size| size > 0));
+        assert!(stats.trace_queries > stats.constraint_queries);
+        assert!(stats.pow_nonce > 0);
+    }
+}
+
+#[test]
+fn receipt_to_bytes_from_bytes() {
+    let (program, inputs, outputs, proof) = build_proof(&ProofOptions::default());
+    let receipt = ExecutionReceipt::new(program.hash(), inputs.get_public_inputs(), &outputs, proof);
+    assert_eq!(RECEIPT_FORMAT_VERSION, receipt.version());
+    assert_eq!(Ok(true), verify_receipt(&receipt));
+
+    let bytes = receipt.to_bytes().unwrap();
+    let decoded = ExecutionReceipt::from_bytes(&bytes).unwrap();
+    assert_eq!(program.hash(), decoded.program_hash());
+    assert_eq!(inputs.get_public_inputs(), decoded.public_inputs());
+    assert_eq!(outputs, decoded.outputs());
+    assert_eq!(Ok(true), verify_receipt(&decoded));
+
+    let decoded: ExecutionReceipt = bincode::deserialize(&bincode::serialize(&receipt).unwrap()).unwrap();
+    assert_eq!(Ok(true), verify_receipt(&decoded));
+
+    // receipts with a different statement are rejected
+    let mut malformed = bytes.clone();
+    malformed[1] ^= 1;
+    let decoded = ExecutionReceipt::from_bytes(&malformed).unwrap();
+    assert_eq!(Err(VerificationError::StatementMismatch), verify_receipt(&decoded));
+}
+
+#[test]
+fn receipt_with_output_spec() {
+    let (program, _, _, _) = build_proof(&ProofOptions::default());
+    let inputs = ProgramInputs::from_public(&[1, 0, 7]);
+
+    for output_spec in [OutputSpec::Positions(vec![2, 0]), OutputSpec::Stack].iter() {
+        let trace = generate_trace(&program, &inputs, output_spec).unwrap();
+        let outputs = trace.outputs().to_vec();
+        let proof = prove(trace, &ProofOptions::default()).unwrap();
+        let receipt = ExecutionReceipt::with_outputs(
+            program.hash(), inputs.get_public_inputs(), output_spec, &outputs, proof.clone());
+        assert_eq!(Ok(true), verify_receipt(&receipt));
+
+        // the output spec is restored from both encodings
+        let decoded = ExecutionReceipt::from_bytes(&receipt.to_bytes().unwrap()).unwrap();
+        assert_eq!(output_spec, decoded.output_spec());
+        assert_eq!(Ok(true), verify_receipt(&decoded));
+
+        let decoded: ExecutionReceipt = bincode::deserialize(&bincode::serialize(&receipt).unwrap()).unwrap();
+        assert_eq!(output_spec, decoded.output_spec());
+        assert_eq!(Ok(true), verify_receipt(&decoded));
+
+        // the same outputs taken from the top of the stack are a different statement
+        let receipt = ExecutionReceipt::new(program.hash(), inputs.get_public_inputs(), &outputs, proof);
+        assert_eq!(Err(VerificationError::StatementMismatch), verify_receipt(&receipt));
+    }
+
+    // output spec tag follows the version, the program hash, and 2 public inputs
+    let (_, _, outputs, proof) = build_proof(&ProofOptions::default());
+    let receipt = ExecutionReceipt::new(program.hash(), &[1, 0], &outputs, proof);
+    let mut bytes = receipt.to_bytes().unwrap();
+    bytes[1 + 32 + 1 + 2 * 16] = 3;
+    let error = DeserializationError::InvalidValue(String::from("output spec 3"));
+    assert_eq!(Err(error), ExecutionReceipt::from_bytes(&bytes).map(|_| ()));
+}
+
+#[test]
+fn receipt_version_mismatch() {
+    let (program, inputs, outputs, proof) = build_proof(&ProofOptions::default());
+    let receipt = ExecutionReceipt::new(program.hash(), inputs.get_public_inputs(), &outputs, proof);
+
+    // the version is checked before the rest of the receipt is decoded
+    let mut bytes = receipt.to_bytes().unwrap();
+    bytes.truncate(1);
+    bytes[0] = RECEIPT_FORMAT_VERSION + 1;
+    let error = DeserializationError::UnsupportedVersion {
+        version: RECEIPT_FORMAT_VERSION + 1, expected: RECEIPT_FORMAT_VERSION };
+    assert_eq!(Err(error), ExecutionReceipt::from_bytes(&bytes).map(|_| ()));
+
+    // the version is the first byte of bincode encoding as well
+    let mut bytes = bincode::serialize(&receipt).unwrap();
+    bytes[0] = RECEIPT_FORMAT_VERSION + 1;
+    let receipt: ExecutionReceipt = bincode::deserialize(&bytes).unwrap();
+    let error = VerificationError::UnsupportedReceiptVersion {
+        version: RECEIPT_FORMAT_VERSION + 1, expected: RECEIPT_FORMAT_VERSION };
+    assert_eq!(Err(error), verify_receipt(&receipt));
+}
+
+fn build_proof(options: &ProofOptions) -> (crate::Program, ProgramInputs, Vec<u128>, StarkProof) {
+    let program = build_fibonacci_program();
+    let inputs = ProgramInputs::from_public(&[1, 0]);
+    let (outputs, proof) = execute(&program, &inputs, 1, options);
+    return (program, inputs, outputs, proof);
+}
@@ -25,6 +25,19 @@ pub fn digest(values: &[u128]) -> Vec<u128> {
     return state[..DIGEST_SIZE].to_vec();
 }
 
+/// Returns a digest of an arbitrarily long sequence of `values`; the digest is initialized to
+/// [n, 0], where n is the number of values, and then absorbs the values two at a time (an odd
+/// sequence is padded with a 0). This is the digest computed by read_absorb procedure of std::io
+/// module of the assembly standard library.
+pub fn digest_sequence(values: &[u128]) -> Vec<u128> {
+    let mut result = vec![values.len() as u128, field::ZERO];
+    for chunk in values.chunks(2) {
+        let b = if chunk.len() == 2 { chunk[1] } else { field::ZERO };
+        result = digest(&[result[0], result[1], chunk[0], b]);
+    }
+    return result;
+}
+
 pub fn apply_round(state: &mut [u128], step: usize) {
     
     let ark_idx = step % CYCLE_LENGTH;
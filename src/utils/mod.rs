@@ -1,4 +1,4 @@
-use std::{ mem, slice, ops::Range };
+use std::{ mem, slice, ops::Range, collections::HashSet };
 
 // RE-EXPORTS
 // ================================================================================================
@@ -19,6 +19,12 @@ pub fn filled_vector<T: Copy>(length: usize, capacity: usize, value: T) -> Vec<T
     return vector;
 }
 
+/// Returns the first value which occurs in `values` more than once, if there is such a value.
+pub fn find_duplicate(values: &[usize]) -> Option<usize> {
+    let mut seen = HashSet::with_capacity(values.len());
+    return values.iter().find(|&&value| !seen.insert(value)).copied();
+}
+
 #[cfg(test)]
 pub fn remove_leading_zeros(values: &[u128]) -> Vec<u128> {
     for i in (0..values.len()).rev() {
@@ -72,4 +78,12 @@ mod tests {
         ];
         assert_eq!(expected, bytes);
     }
+
+    #[test]
+    fn find_duplicate() {
+        assert_eq!(None, super::find_duplicate(&[]));
+        assert_eq!(None, super::find_duplicate(&[3, 1, 2]));
+        assert_eq!(Some(1), super::find_duplicate(&[3, 1, 2, 1]));
+        assert_eq!(Some(0), super::find_duplicate(&[0, 0, 5, 5]));
+    }
 }
\ No newline at end of file